@@ -0,0 +1,3 @@
+pub mod player;
+pub mod resources;
+pub mod systems;
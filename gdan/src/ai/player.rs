@@ -0,0 +1,355 @@
+// Computer player: picks the orders of one side, one command at a time
+
+use crate::game::entities::GamePhase;
+use crate::map::hex::Hex;
+use crate::oper::components::{Oper, Side};
+use crate::rule::commands::GameCommand;
+
+/*
+ * The computer plays through the same `GameCommand`s as a human player, and only knows what its
+ * side has spotted: enemies in sight where they stand, the others at their last known hex.
+ *
+ * In the movement phase each unit in turn goes to the hex it can reach that scores best,
+ * its current hex included. A hex scores, each term weighted by the `AiProfile`:
+ *
+ *   objective    victory points of the objectives, divided by 1 + their distance
+ *   cover        defence the terrain gives against fire (the combat table's terrain modifier)
+ *   threat       damage the known enemies can be expected to do to the unit there, subtracted
+ *   aggression   damage the unit can be expected to do from there to the best spotted target
+ *
+ * In the shooting and close combat phases it makes the attack with the most expected damage,
+ * over and over, until no attack is worth `AiProfile::min_attack`. Other phases are ended.
+ * Damage is counted in strength points, from the odds of the combat table.
+ *
+ * A profile is a RON file, every weight optional:
+ *
+ * (
+ *     objective: 0.3,
+ *     cover: 0.3,
+ *     threat: 1.0,
+ *     aggression: 1.0,
+ *     min_attack: 0.2,
+ * )
+ */
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct AiProfile {
+    pub objective: f32,
+    pub cover: f32,
+    pub threat: f32,
+    pub aggression: f32,
+    /// Least expected damage, in strength points, an attack must do to be made.
+    pub min_attack: f32,
+    /// Worth of suppressing a unit, in strength points.
+    pub suppression: f32,
+    /// Most orders given in one phase before ending it.
+    pub max_orders: usize,
+}
+
+impl Default for AiProfile {
+    fn default() -> Self {
+        AiProfile {
+            objective: 0.3,
+            cover: 0.3,
+            threat: 1.0,
+            aggression: 1.0,
+            min_attack: 0.2,
+            suppression: 0.5,
+            max_orders: 64,
+        }
+    }
+}
+
+impl crate::tools::ron_file::RonFile for AiProfile {
+    const WHAT: &'static str = "computer player profile";
+}
+
+/// The game as the computer player sees it, from a `GameSession` or from the ECS world.
+pub struct AiView<'a> {
+    pub rules: &'a crate::rule::resources::RuleBook,
+    pub turn_info: &'a crate::game::turn::TurnInfo,
+    pub units: &'a [crate::game::session::Unit],
+    /// Intel of the side played.
+    pub intel: crate::rule::fog::Intel,
+}
+
+impl<'a> AiView<'a> {
+    pub fn from_session(session: &'a crate::game::session::GameSession, side: Side) -> Self {
+        AiView {
+            rules: &session.rules,
+            turn_info: &session.turn_info,
+            units: &session.units,
+            intel: session.intel.get(&side).cloned().unwrap_or_default(),
+        }
+    }
+
+    /*
+     * Enemy units still in the game that `side` knows of: those in sight as they are, the others
+     * where and as they were last seen. Whether they are in sight comes with each.
+     */
+    fn known_enemies(&self, side: Side) -> Vec<(&Oper, Hex, bool)> {
+        self.units
+            .iter()
+            .filter(|unit| unit.oper.side != side)
+            .filter_map(|unit| {
+                let id = unit.oper.id;
+                if self.intel.is_spotted(id) {
+                    (!unit.oper.is_destroyed()).then_some((&unit.oper, unit.hex, true))
+                } else {
+                    self.intel
+                        .last_known
+                        .get(&id)
+                        .zip(self.intel.last_seen.get(&id))
+                        .map(|(hex, oper)| (oper, *hex, false))
+                }
+            })
+            .collect()
+    }
+}
+
+/// Plays one side; keeps track of the orders given in the current phase.
+#[derive(Clone, Debug)]
+pub struct AiPlayer {
+    pub side: Side,
+    pub profile: AiProfile,
+    phase: Option<(u32, Side, GamePhase)>,
+    /// Units already given an order this phase, refused orders included.
+    ordered: bevy::utils::HashSet<u32>,
+    orders: usize,
+}
+
+impl AiPlayer {
+    pub fn new(side: Side, profile: AiProfile) -> Self {
+        AiPlayer {
+            side,
+            profile,
+            phase: None,
+            ordered: bevy::utils::HashSet::new(),
+            orders: 0,
+        }
+    }
+
+    /// The next order of the side, `None` while it is not its turn.
+    pub fn next_command(&mut self, view: &AiView) -> Option<GameCommand> {
+        let turn_info = view.turn_info;
+        if turn_info.side != self.side
+            || matches!(turn_info.phase, GamePhase::Inactive | GamePhase::GameOver)
+        {
+            return None;
+        }
+        let phase = Some((turn_info.turn, turn_info.side, turn_info.phase));
+        if self.phase != phase {
+            self.phase = phase;
+            self.ordered.clear();
+            self.orders = 0;
+        }
+        if self.orders >= self.profile.max_orders {
+            return Some(GameCommand::EndPhase);
+        }
+        let command = match turn_info.phase {
+            GamePhase::Movement => self.plan_move(view),
+            GamePhase::Shooting | GamePhase::CloseCombat => self.plan_attack(view),
+            _ => None,
+        };
+        match command {
+            Some(GameCommand::Move { oper, .. }) | Some(GameCommand::Attack { oper, .. }) => {
+                self.ordered.insert(oper);
+                self.orders += 1;
+            }
+            _ => {}
+        }
+        Some(command.unwrap_or(GameCommand::EndPhase))
+    }
+
+    /// Expected damage of an attack in strength points, `None` if it is not allowed.
+    fn expected_damage(
+        &self,
+        view: &AiView,
+        (attacker, from): (&Oper, Hex),
+        (defender, to): (&Oper, Hex),
+        close_combat: bool,
+    ) -> Option<f32> {
+        let distance = from.distance(to);
+        let weapon = if close_combat {
+            None
+        } else {
+            Some(crate::rule::combat::best_weapon(
+                attacker, defender, distance,
+            )?)
+        };
+        let input = crate::rule::combat::CombatInput {
+            attacker,
+            defender,
+            weapon,
+            distance,
+            attacker_terrain: view.rules.terrain.get(from),
+            defender_terrain: view.rules.terrain.get(to),
+            defender_hexside: view.rules.terrain.hexsides.entering(from, to),
+            visible: weapon.is_none()
+                || crate::rule::los::line_of_sight(&view.rules.terrain, from, to).visible,
+        };
+        let odds = view.rules.combat_table.result_odds(&input).ok()?;
+        Some(
+            odds.iter()
+                .map(|(result, chance)| {
+                    let damage = match *result {
+                        crate::rule::combat::CombatResult::NoEffect => 0.,
+                        crate::rule::combat::CombatResult::Suppressed => {
+                            if defender.state == crate::oper::components::OperState::Suppressed {
+                                0.
+                            } else {
+                                self.profile.suppression
+                            }
+                        }
+                        crate::rule::combat::CombatResult::Damaged(loss) => {
+                            loss.min(defender.strength) as f32
+                        }
+                        crate::rule::combat::CombatResult::Destroyed => defender.strength as f32,
+                    };
+                    damage * chance
+                })
+                .sum(),
+        )
+    }
+
+    /// Score of `oper` standing in `hex`, see the module comment.
+    fn score_hex(
+        &self,
+        view: &AiView,
+        oper: &Oper,
+        hex: Hex,
+        enemies: &[(&Oper, Hex, bool)],
+    ) -> f32 {
+        let objective: f32 = view
+            .rules
+            .objectives
+            .iter()
+            .map(|objective| objective.points as f32 / (1 + objective.hex().distance(hex)) as f32)
+            .sum();
+        let terrain = view.rules.terrain.get(hex).terrain;
+        let cover = -view
+            .rules
+            .combat_table
+            .modifiers
+            .terrain
+            .get(&terrain)
+            .copied()
+            .unwrap_or(0) as f32;
+        let threat: f32 = enemies
+            .iter()
+            .filter_map(|(enemy, from, _)| {
+                self.expected_damage(view, (enemy, *from), (oper, hex), false)
+            })
+            .sum();
+        let aggression = enemies
+            .iter()
+            .filter(|(_, _, spotted)| *spotted)
+            .filter_map(|(enemy, to, _)| {
+                self.expected_damage(view, (oper, hex), (enemy, *to), false)
+            })
+            .fold(0., f32::max);
+        self.profile.objective * objective + self.profile.cover * cover
+            - self.profile.threat * threat
+            + self.profile.aggression * aggression
+    }
+
+    fn plan_move(&self, view: &AiView) -> Option<GameCommand> {
+        let enemies = view.known_enemies(self.side);
+        // the enemies it does not know of are left out; a move into one of them is refused
+        let occupied: bevy::utils::HashMap<Hex, Side> = view
+            .units
+            .iter()
+            .filter(|unit| !unit.oper.is_destroyed())
+            .filter(|unit| unit.oper.side == self.side || view.intel.is_spotted(unit.oper.id))
+            .map(|unit| (unit.hex, unit.oper.side))
+            .collect();
+        for unit in view.units.iter() {
+            let oper = &unit.oper;
+            if oper.side != self.side
+                || oper.is_destroyed()
+                || oper.movement_left == 0
+                || self.ordered.contains(&oper.id)
+            {
+                continue;
+            }
+            let mut others = occupied.clone();
+            others.remove(&unit.hex);
+            let reachable = crate::rule::movement::oper_reachable(
+                oper,
+                unit.hex,
+                &view.rules.terrain,
+                &view.rules.layout,
+                &others,
+            );
+            let stay = self.score_hex(view, oper, unit.hex, &enemies);
+            let best = reachable
+                .destinations()
+                .map(|hex| (hex, self.score_hex(view, oper, hex, &enemies)))
+                // ties go to the smaller hex, so the same game always gets the same orders
+                .max_by(|(a, a_score), (b, b_score)| {
+                    a_score
+                        .total_cmp(b_score)
+                        .then_with(|| (b.q, b.r).cmp(&(a.q, a.r)))
+                });
+            if let Some((to, score)) = best {
+                if score > stay {
+                    return Some(GameCommand::Move { oper: oper.id, to });
+                }
+            }
+        }
+        None
+    }
+
+    fn plan_attack(&self, view: &AiView) -> Option<GameCommand> {
+        let close_combat = view.turn_info.phase == GamePhase::CloseCombat;
+        let mut best: Option<(GameCommand, f32)> = None;
+        for attacker in view.units.iter() {
+            if attacker.oper.side != self.side
+                || attacker.oper.is_destroyed()
+                || attacker.fired
+                || self.ordered.contains(&attacker.oper.id)
+            {
+                continue;
+            }
+            for defender in view.units.iter() {
+                if defender.oper.side == self.side || !view.intel.is_spotted(defender.oper.id) {
+                    continue;
+                }
+                let Some(damage) = self.expected_damage(
+                    view,
+                    (&attacker.oper, attacker.hex),
+                    (&defender.oper, defender.hex),
+                    close_combat,
+                ) else {
+                    continue;
+                };
+                if damage >= self.profile.min_attack
+                    && best.as_ref().is_none_or(|(_, best)| damage > *best)
+                {
+                    let command = GameCommand::Attack {
+                        oper: attacker.oper.id,
+                        target: defender.oper.id,
+                    };
+                    best = Some((command, damage));
+                }
+            }
+        }
+        best.map(|(command, _)| command)
+    }
+}
+
+/// Plays whole phases of `session` with the computer players, until it is a human's turn.
+pub fn play_session(session: &mut crate::game::session::GameSession, players: &mut [AiPlayer]) {
+    while !session.is_over() {
+        let side = session.turn_info.side;
+        let Some(player) = players.iter_mut().find(|player| player.side == side) else {
+            return;
+        };
+        let Some(command) = player.next_command(&AiView::from_session(session, side)) else {
+            return;
+        };
+        if let Err(error) = session.apply(&command) {
+            bevy::log::debug!("{:?}: {}", command, error);
+        }
+    }
+}
@@ -0,0 +1,21 @@
+/// Seconds between two orders of a computer player, so its moves can be followed on the map.
+pub const AI_STEP_SECONDS: f32 = 0.3;
+
+/// The sides played by the computer in the game on the map.
+#[derive(bevy::ecs::system::Resource, Clone, Debug)]
+pub struct AiPlayers {
+    pub players: Vec<crate::ai::player::AiPlayer>,
+    pub timer: bevy::time::Timer,
+}
+
+impl AiPlayers {
+    pub fn new(players: Vec<crate::ai::player::AiPlayer>) -> Self {
+        AiPlayers {
+            players,
+            timer: bevy::time::Timer::from_seconds(
+                AI_STEP_SECONDS,
+                bevy::time::TimerMode::Repeating,
+            ),
+        }
+    }
+}
@@ -0,0 +1,42 @@
+use bevy::prelude::*;
+
+/*
+ * Gives the next order of the computer player whose turn it is, every `AI_STEP_SECONDS`.
+ * The order goes out as a `GameCommand` like the mouse's, so it is checked, carried out,
+ * recorded and sent over the network the same way.
+ */
+pub fn ai_play(
+    time: Res<Time>,
+    mut ai_players: ResMut<crate::ai::resources::AiPlayers>,
+    rules: Option<Res<crate::rule::resources::RuleBook>>,
+    turn_info: Res<crate::game::turn::TurnInfo>,
+    fog: Res<crate::rule::resources::FogOfWar>,
+    query_oper: Query<crate::game::session::UnitComponents>,
+    mut game_commands: EventWriter<crate::rule::commands::GameCommand>,
+) {
+    let Some(rules) = rules else {
+        return;
+    };
+    if !ai_players.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let side = turn_info.side;
+    let Some(player) = ai_players
+        .players
+        .iter_mut()
+        .find(|player| player.side == side)
+    else {
+        return;
+    };
+    let (_, units) = crate::game::session::units_from_world(query_oper.iter());
+    let view = crate::ai::player::AiView {
+        rules: &rules,
+        turn_info: &turn_info,
+        units: &units,
+        intel: fog.intel.get(&side).cloned().unwrap_or_default(),
+    };
+    if let Some(command) = player.next_command(&view) {
+        info!("computer {:?}: {:?}", side, command);
+        game_commands.send(command);
+    }
+}
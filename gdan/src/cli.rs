@@ -0,0 +1,212 @@
+// Command line: `gdan` opens the game window, `gdan simulate` plays scenarios without one
+
+/*
+ *     gdan --state main-menu --width 1920 --height 1080
+ *     gdan --scenario scenarios/river-crossing.scenario.ron --debug
+ *     gdan --load saves/quicksave.save.ron --fullscreen
+ *     gdan --state map-editor --scenario scenarios/river-crossing.scenario.ron
+ *     gdan simulate scenarios/river-crossing.scenario.ron --runs 1000 --seed 42
+ *     gdan simulate scenarios/river-crossing.scenario.ron --format json --output report.json
+ *     gdan tile assets/wg/mlx/map/1-8819p-6299p.png --tile-size 512
+ *
+ * The scenario path is relative to the asset folder, as in the game.
+ */
+
+use crate::tools::ron_file::RonFile;
+
+#[derive(clap::Parser, Debug)]
+#[command(version, about = "Hex map wargame")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    #[command(flatten)]
+    pub launch: LaunchArgs,
+}
+
+/// How the game window opens.
+#[derive(clap::Args, Debug)]
+pub struct LaunchArgs {
+    /// Screen to open on; the map when a scenario or a save file is given.
+    #[arg(long, value_enum)]
+    pub state: Option<crate::MyAppState>,
+    /// Scenario to play, relative to the asset folder.
+    #[arg(long)]
+    pub scenario: Option<String>,
+    /// Saved game to carry on.
+    #[arg(long, conflicts_with = "scenario")]
+    pub load: Option<std::path::PathBuf>,
+    #[arg(long, default_value_t = 1280.)]
+    pub width: f32,
+    #[arg(long, default_value_t = 720.)]
+    pub height: f32,
+    #[arg(long)]
+    pub fullscreen: bool,
+    #[arg(long, default_value = "assets")]
+    pub assets: String,
+    /// Show the frame rate, the current screen and the hex under the cursor.
+    #[arg(long)]
+    pub debug: bool,
+}
+
+#[derive(clap::Subcommand, Debug)]
+pub enum Command {
+    /// Play a scenario many times with the computer on both sides and report the statistics.
+    Simulate(SimulateArgs),
+    /// Cut a map image into the tile pyramid the map streams from, next to the image.
+    Tile(TileArgs),
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct SimulateArgs {
+    /// Scenario file, relative to the asset folder.
+    pub scenario: String,
+    #[arg(long, default_value_t = 100)]
+    pub runs: usize,
+    /// Seed of the dice of the first run; run `i` uses `seed + i`.
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+    #[arg(long, value_enum, default_value_t = ReportFormat::Csv)]
+    pub format: ReportFormat,
+    /// File to write the report to, instead of the standard output.
+    #[arg(long)]
+    pub output: Option<std::path::PathBuf>,
+    #[arg(long, default_value = "assets")]
+    pub assets: std::path::PathBuf,
+    /// Computer player profile of the red side, see `crate::ai::player::AiProfile`.
+    #[arg(long)]
+    pub red_ai: Option<std::path::PathBuf>,
+    /// Computer player profile of the blue side.
+    #[arg(long)]
+    pub blue_ai: Option<std::path::PathBuf>,
+}
+
+#[derive(clap::Args, Debug)]
+pub struct TileArgs {
+    /// Map image file.
+    pub image: String,
+    #[arg(long, default_value_t = 512, value_parser = clap::value_parser!(u32).range(1..))]
+    pub tile_size: u32,
+    /// Satellite map level of the full size image.
+    #[arg(long, default_value_t = crate::map::resources::MapInfo::default().satellite_map_level)]
+    pub level: u8,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CliError {
+    #[error(transparent)]
+    Session(#[from] crate::game::session::SessionError),
+    #[error(transparent)]
+    File(#[from] crate::tools::ron_file::RonFileError),
+    #[error("--load carries a game on on the map, not with --state {0}")]
+    LoadOffTheMap(String),
+    #[error(transparent)]
+    Report(#[from] crate::game::simulation::ReportError),
+    #[error(transparent)]
+    Tile(#[from] crate::map::tiles::TileError),
+    #[error("could not write report: {0}")]
+    Io(#[from] std::io::Error),
+}
+
+/// Asset path of `path`: a file given from the working directory, asset folder included, is found too.
+fn asset_path(path: &str, assets: impl AsRef<std::path::Path>) -> String {
+    std::path::Path::new(path)
+        .strip_prefix(assets)
+        .map_or(path.to_string(), |path| path.to_string_lossy().into_owned())
+}
+
+impl LaunchArgs {
+    pub fn initial_state(&self) -> crate::MyAppState {
+        match self.state.clone() {
+            Some(state) => state,
+            None if self.scenario.is_some() || self.load.is_some() => crate::MyAppState::MapMenu,
+            None => crate::MyAppState::default(),
+        }
+    }
+
+    pub fn window(&self) -> bevy::window::Window {
+        bevy::window::Window {
+            resolution: (self.width, self.height).into(),
+            mode: if self.fullscreen {
+                bevy::window::WindowMode::BorderlessFullscreen
+            } else {
+                bevy::window::WindowMode::Windowed
+            },
+            ..Default::default()
+        }
+    }
+
+    /// Asset path of the scenario given, or of the scenario of the save file given.
+    pub fn scenario(&self, save: Option<&crate::game::save::SaveGame>) -> Option<String> {
+        match (&self.scenario, save) {
+            (Some(scenario), _) => Some(asset_path(scenario, &self.assets)),
+            (None, Some(save)) => Some(save.scenario.clone()),
+            (None, None) => None,
+        }
+    }
+
+    /// The save to carry on, only when opening on the map: another screen would leave it waiting
+    /// for the first new game, which would carry it on instead, so it is refused.
+    pub fn read_save(&self) -> Result<Option<crate::game::save::SaveGame>, CliError> {
+        Ok(match &self.load {
+            Some(path) if self.initial_state() == crate::MyAppState::MapMenu => {
+                Some(crate::game::save::SaveGame::read(path)?)
+            }
+            Some(_) => {
+                let state = clap::ValueEnum::to_possible_value(&self.initial_state())
+                    .map_or(String::new(), |value| value.get_name().to_string());
+                return Err(CliError::LoadOffTheMap(state));
+            }
+            None => None,
+        })
+    }
+}
+
+/// Runs `gdan simulate`; progress goes to the standard error.
+pub fn simulate(args: SimulateArgs) -> Result<(), CliError> {
+    let scenario = asset_path(&args.scenario, &args.assets);
+    let session = crate::game::session::GameSession::load(&args.assets, &scenario, args.seed)?;
+    let profile = |path: &Option<std::path::PathBuf>| match path {
+        Some(path) => crate::ai::player::AiProfile::read(path),
+        None => Ok(crate::ai::player::AiProfile::default()),
+    };
+    let profiles = [
+        (crate::oper::components::Side::Red, profile(&args.red_ai)?),
+        (crate::oper::components::Side::Blue, profile(&args.blue_ai)?),
+    ];
+    let games =
+        crate::game::simulation::simulate(&session, args.runs, args.seed, &profiles, |played| {
+            if played % 100 == 0 || played == args.runs {
+                eprintln!("{}/{} games played", played, args.runs);
+            }
+        });
+    let report = crate::game::simulation::SimulationReport::new(&scenario, args.seed, games);
+    let text = match args.format {
+        ReportFormat::Csv => report.to_csv(),
+        ReportFormat::Json => report.to_json()?,
+    };
+    match args.output {
+        Some(path) => std::fs::write(path, text)?,
+        None => print!("{}", text),
+    }
+    Ok(())
+}
+
+/// Runs `gdan tile`.
+pub fn tile(args: TileArgs) -> Result<(), CliError> {
+    let pyramid = crate::map::tiles::cut(&args.image, args.tile_size, args.level)?;
+    eprintln!(
+        "{}: levels {} to {} written to {}",
+        args.image,
+        pyramid.min_level,
+        pyramid.max_level,
+        crate::map::tiles::pyramid_path(&args.image)
+    );
+    Ok(())
+}
@@ -0,0 +1,238 @@
+// Map documents: the terrain and objectives of a map, as the editor changes them
+
+use crate::map::hex::Hex;
+use crate::map::hexside::{Hexside, HexsideFeature, HexsideMap};
+use crate::map::terrain::{HexTerrain, TerrainMap, TerrainType};
+
+/// Highest elevation level the editor paints.
+pub const MAX_ELEVATION: i32 = 9;
+/// Largest brush, in hexes around the one under the cursor.
+pub const MAX_BRUSH: u32 = 5;
+/// Strokes kept for undo.
+const UNDO_STEPS: usize = 100;
+/// Victory points of a new objective; the designer changes them in the scenario file.
+pub const OBJECTIVE_POINTS: u32 = 10;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditorTool {
+    /// Paints the terrain type of the hexes.
+    Terrain(TerrainType),
+    /// Paints the elevation level chosen.
+    Elevation,
+    /// Runs a road from hex to hex as the cursor passes over them.
+    Road,
+    /// Puts a bridge across the hex edge nearest the cursor.
+    Bridge,
+    /// Draws a river along the hex edge nearest the cursor.
+    River,
+    /// Builds a wall along the hex edge nearest the cursor.
+    Wall,
+    /// Places an objective in the hex.
+    Objective,
+}
+
+impl std::fmt::Display for EditorTool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EditorTool::Terrain(terrain) => write!(f, "{:?}", terrain),
+            EditorTool::Elevation => write!(f, "Elevation"),
+            EditorTool::Road => write!(f, "Road"),
+            EditorTool::Bridge => write!(f, "Bridge"),
+            EditorTool::River => write!(f, "River"),
+            EditorTool::Wall => write!(f, "Wall"),
+            EditorTool::Objective => write!(f, "Objective"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Snapshot {
+    hexes: bevy::utils::HashMap<Hex, HexTerrain>,
+    hexsides: HexsideMap,
+    objectives: Vec<crate::game::scenario::Objective>,
+}
+
+/*
+ * Every change belongs to a stroke: from pressing the mouse button to letting it go.
+ * The document as it was before the stroke is kept for undo the first time the stroke changes
+ * something, so a click that changes nothing leaves nothing to undo.
+ * Hexes painted back to the default terrain are dropped, as the terrain file leaves them out.
+ */
+#[derive(Clone, Debug, Default)]
+pub struct MapDocument {
+    pub terrain: TerrainMap,
+    pub objectives: Vec<crate::game::scenario::Objective>,
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+    /// The document before the stroke under way, until the stroke changes it.
+    stroke: Option<Snapshot>,
+    /// Changed since the terrain file was last written.
+    pub terrain_changed: bool,
+    /// Changed since the scenario file was last written.
+    pub objectives_changed: bool,
+}
+
+impl MapDocument {
+    pub fn new(terrain: TerrainMap, objectives: Vec<crate::game::scenario::Objective>) -> Self {
+        MapDocument {
+            terrain,
+            objectives,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_changed(&self) -> bool {
+        self.terrain_changed || self.objectives_changed
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            hexes: self.terrain.hexes.clone(),
+            hexsides: self.terrain.hexsides.clone(),
+            objectives: self.objectives.clone(),
+        }
+    }
+
+    pub fn begin_stroke(&mut self) {
+        self.stroke = Some(self.snapshot());
+    }
+
+    pub fn end_stroke(&mut self) {
+        self.stroke = None;
+    }
+
+    /// Keeps the document before the stroke for undo, the first time the stroke changes it.
+    fn record(&mut self) {
+        if let Some(snapshot) = self.stroke.take() {
+            self.undo.push(snapshot);
+            if self.undo.len() > UNDO_STEPS {
+                self.undo.remove(0);
+            }
+            self.redo.clear();
+        }
+    }
+
+    fn edit_hex(&mut self, hex: Hex, edit: impl FnOnce(&mut HexTerrain)) {
+        let current = self.terrain.get(hex);
+        let mut edited = current.clone();
+        edit(&mut edited);
+        if edited == *current {
+            return;
+        }
+        self.record();
+        self.terrain_changed = true;
+        if edited == self.terrain.default {
+            self.terrain.hexes.remove(&hex);
+        } else {
+            self.terrain.hexes.insert(hex, edited);
+        }
+    }
+
+    /// Paints `hexes` with the terrain or elevation tool; `erase` puts back the default.
+    pub fn paint(&mut self, hexes: &[Hex], tool: EditorTool, elevation: i32, erase: bool) {
+        let default_terrain = self.terrain.default.terrain;
+        for hex in hexes.iter() {
+            match tool {
+                EditorTool::Terrain(terrain) => self.edit_hex(*hex, |hex_terrain| {
+                    hex_terrain.terrain = if erase { default_terrain } else { terrain };
+                }),
+                EditorTool::Elevation => self.edit_hex(*hex, |hex_terrain| {
+                    hex_terrain.elevation = if erase { 0 } else { elevation };
+                }),
+                _ => (),
+            }
+        }
+    }
+
+    /// Puts `feature` on the hexside, or takes it away with `erase`.
+    pub fn set_hexside(&mut self, hexside: Hexside, feature: HexsideFeature, erase: bool) {
+        if self.terrain.hexsides.get(hexside).contains(&feature) != erase {
+            return;
+        }
+        self.record();
+        self.terrain_changed = true;
+        if erase {
+            self.terrain.hexsides.remove(hexside, feature);
+        } else {
+            self.terrain.hexsides.add(hexside, feature);
+        }
+    }
+
+    pub fn objective_at(&self, hex: Hex) -> Option<&crate::game::scenario::Objective> {
+        self.objectives
+            .iter()
+            .find(|objective| objective.hex() == hex)
+    }
+
+    /// Places an objective named `name` in `hex` unless it holds one, or takes it away with `erase`.
+    pub fn place_objective(&mut self, hex: Hex, name: String, erase: bool) {
+        if self.objective_at(hex).is_some() != erase {
+            return;
+        }
+        self.record();
+        self.objectives_changed = true;
+        if erase {
+            self.objectives.retain(|objective| objective.hex() != hex);
+        } else {
+            let offset = hex.to_offset();
+            self.objectives.push(crate::game::scenario::Objective {
+                name,
+                col: offset.col,
+                row: offset.row,
+                points: OBJECTIVE_POINTS,
+                owner: None,
+            });
+        }
+    }
+
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.terrain_changed |=
+            self.terrain.hexes != snapshot.hexes || self.terrain.hexsides != snapshot.hexsides;
+        self.objectives_changed |= self.objectives != snapshot.objectives;
+        self.terrain.hexes = snapshot.hexes;
+        self.terrain.hexsides = snapshot.hexsides;
+        self.objectives = snapshot.objectives;
+    }
+
+    /// Takes back the last stroke; false when there is none.
+    pub fn undo(&mut self) -> bool {
+        let Some(snapshot) = self.undo.pop() else {
+            return false;
+        };
+        self.redo.push(self.snapshot());
+        self.restore(snapshot);
+        true
+    }
+
+    /// Makes the last stroke taken back again; false when there is none.
+    pub fn redo(&mut self) -> bool {
+        let Some(snapshot) = self.redo.pop() else {
+            return false;
+        };
+        self.undo.push(self.snapshot());
+        self.restore(snapshot);
+        true
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EditorError {
+    #[error("could not write {0}: {1}")]
+    Io(String, std::io::Error),
+    #[error("could not write map: {0}")]
+    Ron(#[from] ron::Error),
+}
+
+/// Writes a file of the asset folder, making its folders as needed.
+pub fn write_asset(
+    asset_folder: &std::path::Path,
+    path: &str,
+    contents: &str,
+) -> Result<(), EditorError> {
+    let file = asset_folder.join(path);
+    if let Some(folder) = file.parent() {
+        std::fs::create_dir_all(folder)
+            .map_err(|error| EditorError::Io(path.to_string(), error))?;
+    }
+    std::fs::write(&file, contents).map_err(|error| EditorError::Io(path.to_string(), error))
+}
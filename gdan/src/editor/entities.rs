@@ -0,0 +1,5 @@
+use bevy::ecs::component::Component;
+
+/// Line of the map editor telling the tool, the brush and what was last done.
+#[derive(Component)]
+pub struct EditorText;
@@ -0,0 +1,4 @@
+pub mod document;
+pub mod entities;
+pub mod resources;
+pub mod systems;
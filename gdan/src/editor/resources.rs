@@ -0,0 +1,39 @@
+use crate::editor::document::{EditorTool, MapDocument};
+
+/// Folder the assets are read from, and the editor writes to, see `crate::cli::LaunchArgs`.
+#[derive(bevy::ecs::system::Resource)]
+pub struct AssetFolder(pub std::path::PathBuf);
+
+/// The map being edited, and how the editor paints it.
+#[derive(bevy::ecs::system::Resource)]
+pub struct MapEditor {
+    /// `None` until the scenario and its terrain file are loaded.
+    pub document: Option<MapDocument>,
+    /// Asset path of the terrain file written on saving.
+    pub terrain_path: String,
+    /// Asset path of the scenario file, written on saving when the objectives changed.
+    pub scenario_path: Option<String>,
+    pub tool: EditorTool,
+    /// Hexes painted around the one under the cursor.
+    pub brush: u32,
+    /// Level the elevation tool paints.
+    pub elevation: i32,
+    /// 'B' was pressed once with unsaved changes; pressing it again leaves without saving.
+    pub leaving: bool,
+    pub message: String,
+}
+
+impl Default for MapEditor {
+    fn default() -> Self {
+        MapEditor {
+            document: None,
+            terrain_path: String::new(),
+            scenario_path: None,
+            tool: EditorTool::Terrain(crate::map::terrain::TerrainType::Forest),
+            brush: 0,
+            elevation: 1,
+            leaving: false,
+            message: "loading the map...".to_string(),
+        }
+    }
+}
@@ -0,0 +1,455 @@
+// Systems of the map editor, drawn over the 2D map
+
+use bevy::prelude::*;
+
+use crate::editor::document::{EditorTool, MAX_BRUSH, MAX_ELEVATION};
+use crate::map::hexside::HexsideFeature;
+use crate::map::terrain::TerrainType;
+
+pub fn map_editor_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    info!("map_editor_menu");
+    commands.insert_resource(crate::editor::resources::MapEditor::default());
+    commands.spawn((
+        TextBundle::from_section(
+            "map editor\n \
+            WASD or the arrows to pan, drag with the middle or right button, scroll to zoom\n \
+            press 'G' to toggle the hex grid\n \
+            '1' Open '2' Forest '3' Urban '4' Water, 'E' elevation ('-' '=' to change the level)\n \
+            'R' road from hex to hex, 'N' bridge, 'V' river, 'L' wall along the hex edges\n \
+            'O' objective\n \
+            left click or drag to paint, hold Shift to erase, '[' ']' to size the brush\n \
+            Ctrl+Z to undo, Ctrl+Y to redo, Ctrl+S to save, 'B' back to Main Menu",
+            TextStyle {
+                font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                font_size: 20.,
+                color: Color::WHITE,
+            },
+        ),
+        crate::map::entities::MapMenu,
+    ));
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                font_size: 18.,
+                color: Color::YELLOW,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(10.),
+            bottom: Val::Px(34.),
+            ..default()
+        }),
+        crate::editor::entities::EditorText,
+        crate::map::entities::MapMenu,
+    ));
+}
+
+pub fn map_editor_loading(editor: Res<crate::editor::resources::MapEditor>) -> bool {
+    editor.document.is_none()
+}
+
+/*
+ * The editor opens the terrain file and the objectives of the selected scenario once they are
+ * loaded. A scenario whose terrain file is not there yet starts from an empty terrain, written
+ * to that file on saving.
+ */
+pub fn load_map_editor(
+    asset_server: Res<AssetServer>,
+    selected_scenario: Res<crate::game::resources::SelectedScenario>,
+    scenarios: Res<Assets<crate::game::scenario::Scenario>>,
+    terrains: Res<Assets<crate::map::terrain::TerrainMap>>,
+    mut editor: ResMut<crate::editor::resources::MapEditor>,
+) {
+    let Some(scenario) = scenarios.get(&selected_scenario.0) else {
+        if asset_server.load_state(&selected_scenario.0) == bevy::asset::LoadState::Failed {
+            editor.message = "the scenario could not be loaded, nothing to edit".to_string();
+        }
+        return;
+    };
+    let terrain = match terrains.get(&scenario.terrain) {
+        Some(terrain) => terrain.clone(),
+        None if asset_server.load_state(&scenario.terrain) == bevy::asset::LoadState::Failed => {
+            crate::map::terrain::TerrainMap::default()
+        }
+        None => return,
+    };
+    info!("load_map_editor: {}", scenario.file.terrain);
+    editor.message = format!("editing {}", scenario.file.terrain);
+    editor.terrain_path = scenario.file.terrain.clone();
+    editor.scenario_path = asset_server
+        .get_path(&selected_scenario.0)
+        .map(|path| path.path().to_string_lossy().into_owned());
+    editor.document = Some(crate::editor::document::MapDocument::new(
+        terrain,
+        scenario.file.objectives.clone(),
+    ));
+}
+
+/// Tools, brush size, elevation level, undo and redo, from the keyboard.
+pub fn map_editor_keys(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut editor: ResMut<crate::editor::resources::MapEditor>,
+) {
+    let control = keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]);
+    let shift = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    if control {
+        let undo = keyboard_input.just_pressed(KeyCode::KeyZ) && !shift;
+        let redo = keyboard_input.just_pressed(KeyCode::KeyY)
+            || (keyboard_input.just_pressed(KeyCode::KeyZ) && shift);
+        let Some(document) = editor.document.as_mut() else {
+            return;
+        };
+        let message = if undo {
+            document.undo().then_some("undone")
+        } else if redo {
+            document.redo().then_some("redone")
+        } else {
+            None
+        };
+        if let Some(message) = message {
+            editor.message = message.to_string();
+        }
+        return;
+    }
+    for (key, tool) in [
+        (KeyCode::Digit1, EditorTool::Terrain(TerrainType::Open)),
+        (KeyCode::Digit2, EditorTool::Terrain(TerrainType::Forest)),
+        (KeyCode::Digit3, EditorTool::Terrain(TerrainType::Urban)),
+        (KeyCode::Digit4, EditorTool::Terrain(TerrainType::Water)),
+        (KeyCode::KeyE, EditorTool::Elevation),
+        (KeyCode::KeyR, EditorTool::Road),
+        (KeyCode::KeyN, EditorTool::Bridge),
+        (KeyCode::KeyV, EditorTool::River),
+        (KeyCode::KeyL, EditorTool::Wall),
+        (KeyCode::KeyO, EditorTool::Objective),
+    ] {
+        if keyboard_input.just_pressed(key) {
+            editor.tool = tool;
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::BracketLeft) {
+        editor.brush = editor.brush.saturating_sub(1);
+    }
+    if keyboard_input.just_pressed(KeyCode::BracketRight) {
+        editor.brush = (editor.brush + 1).min(MAX_BRUSH);
+    }
+    if keyboard_input.just_pressed(KeyCode::Minus) {
+        editor.elevation = (editor.elevation - 1).max(0);
+        editor.tool = EditorTool::Elevation;
+    }
+    if keyboard_input.just_pressed(KeyCode::Equal) {
+        editor.elevation = (editor.elevation + 1).min(MAX_ELEVATION);
+        editor.tool = EditorTool::Elevation;
+    }
+}
+
+/*
+ * A stroke runs from pressing the left button on the map to letting it go: area tools paint
+ * every hex the brush passes over, the road tool joins each hex the cursor passes over to the one
+ * before, both along the line between when the cursor skips hexes, the bridge, river and wall tools every hex edge the cursor passes near, the objective
+ * tool only the hex first clicked. Holding Shift erases instead.
+ */
+#[allow(clippy::too_many_arguments)]
+pub fn map_editor_paint(
+    buttons: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    query_interaction: Query<&Interaction>,
+    hovered_hex: Res<crate::map::resources::HoveredHex>,
+    camera2d_coords: Res<crate::map::resources::Camera2dCoords>,
+    hex_grid: Res<crate::map::resources::HexGrid>,
+    map_info: Res<crate::map::resources::MapInfo>,
+    mut editor: ResMut<crate::editor::resources::MapEditor>,
+    mut painting: Local<bool>,
+    mut last_hex: Local<Option<crate::map::hex::Hex>>,
+) {
+    let editor = editor.as_mut();
+    let Some(document) = editor.document.as_mut() else {
+        return;
+    };
+    if buttons.just_released(MouseButton::Left) {
+        document.end_stroke();
+        *painting = false;
+    }
+    let Some(hex) = hovered_hex.0 else {
+        return;
+    };
+    // clicks on buttons belong to the UI, not to the map below it
+    if buttons.just_pressed(MouseButton::Left)
+        && query_interaction
+            .iter()
+            .all(|interaction| *interaction == Interaction::None)
+    {
+        document.begin_stroke();
+        *painting = true;
+        *last_hex = None;
+        editor.leaving = false;
+    }
+    if !*painting || !buttons.pressed(MouseButton::Left) {
+        return;
+    }
+    let erase = keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+    match editor.tool {
+        EditorTool::Road => {
+            // the cursor may skip hexes when it moves fast: the road follows the line between
+            let line = last_hex.map_or_else(Vec::new, |last_hex| last_hex.line_to(hex));
+            for pair in line.windows(2) {
+                if let Some(hexside) = crate::map::hexside::Hexside::between(pair[0], pair[1]) {
+                    document.set_hexside(hexside, HexsideFeature::Road, erase);
+                }
+            }
+            *last_hex = Some(hex);
+        }
+        EditorTool::Bridge | EditorTool::River | EditorTool::Wall => {
+            let feature = match editor.tool {
+                EditorTool::Bridge => HexsideFeature::Bridge,
+                EditorTool::River => HexsideFeature::River,
+                _ => HexsideFeature::Wall,
+            };
+            let (hex, direction) = hex_grid.0.nearest_edge(camera2d_coords.0);
+            document.set_hexside(
+                crate::map::hexside::Hexside::new(hex, direction),
+                feature,
+                erase,
+            );
+        }
+        EditorTool::Objective => {
+            if buttons.just_pressed(MouseButton::Left) {
+                document.place_objective(hex, map_info.hex_label(hex), erase);
+            }
+        }
+        tool => {
+            let line = last_hex.map_or_else(|| vec![hex], |last_hex| last_hex.line_to(hex));
+            let hexes: Vec<crate::map::hex::Hex> = line
+                .iter()
+                .flat_map(|hex| hex.spiral(editor.brush))
+                .filter(|hex| hex_grid.0.contains(*hex))
+                .collect();
+            document.paint(&hexes, tool, editor.elevation, erase);
+            *last_hex = Some(hex);
+        }
+    }
+}
+
+/*
+ * Ctrl+S writes the terrain file, and the scenario file when its objectives changed, into the
+ * asset folder. The loaded assets are updated too, so the map shows the new terrain without
+ * reloading. Writing the scenario file again drops the comments it had.
+ */
+pub fn save_map_editor(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    asset_folder: Res<crate::editor::resources::AssetFolder>,
+    selected_scenario: Res<crate::game::resources::SelectedScenario>,
+    mut scenarios: ResMut<Assets<crate::game::scenario::Scenario>>,
+    mut terrains: ResMut<Assets<crate::map::terrain::TerrainMap>>,
+    mut editor: ResMut<crate::editor::resources::MapEditor>,
+) {
+    if !keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight])
+        || !keyboard_input.just_pressed(KeyCode::KeyS)
+    {
+        return;
+    }
+    let editor = editor.as_mut();
+    let Some(document) = editor.document.as_mut() else {
+        return;
+    };
+    let Some(scenario) = scenarios.get_mut(&selected_scenario.0) else {
+        return;
+    };
+    let mut saved = Vec::new();
+    if document.terrain_changed {
+        let written = document
+            .terrain
+            .to_ron()
+            .map_err(Into::into)
+            .and_then(|ron| {
+                crate::editor::document::write_asset(&asset_folder.0, &editor.terrain_path, &ron)
+            });
+        if let Err(error) = written {
+            warn!("{}", error);
+            editor.message = error.to_string();
+            return;
+        }
+        terrains.insert(&scenario.terrain, document.terrain.clone());
+        document.terrain_changed = false;
+        saved.push(editor.terrain_path.clone());
+    }
+    if let (true, Some(scenario_path)) = (document.objectives_changed, &editor.scenario_path) {
+        let mut file = scenario.file.clone();
+        file.objectives = document.objectives.clone();
+        let written =
+            ron::ser::to_string_pretty(&file, ron::ser::PrettyConfig::default().depth_limit(2))
+                .map_err(Into::into)
+                .and_then(|ron| {
+                    crate::editor::document::write_asset(&asset_folder.0, scenario_path, &ron)
+                });
+        if let Err(error) = written {
+            warn!("{}", error);
+            editor.message = error.to_string();
+            return;
+        }
+        scenario.file = file;
+        document.objectives_changed = false;
+        saved.push(scenario_path.clone());
+    }
+    editor.message = if saved.is_empty() {
+        "nothing to save".to_string()
+    } else {
+        format!("saved {}", saved.join(", "))
+    };
+    info!("save_map_editor: {}", editor.message);
+}
+
+/// 'B' goes back to the main menu, but asks again first when there are unsaved changes.
+pub fn leave_map_editor(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut editor: ResMut<crate::editor::resources::MapEditor>,
+    mut next_state: ResMut<NextState<crate::MyAppState>>,
+) {
+    if !keyboard_input.just_pressed(KeyCode::KeyB) {
+        return;
+    }
+    let changed = editor
+        .document
+        .as_ref()
+        .is_some_and(|document| document.is_changed());
+    if changed && !editor.leaving {
+        editor.leaving = true;
+        editor.message =
+            "unsaved changes: Ctrl+S to save them, 'B' again to leave without".to_string();
+        return;
+    }
+    next_state.set(crate::MyAppState::MainMenu);
+    info!("AppState::MainMenu");
+}
+
+/*
+ * The terrain as the editor sees it, over the hexes in sight: an inner outline in the colour of
+ * the terrain type, one brown contour a level of elevation, the hexside features as on the map,
+ * objectives as circles. The brush outlines the hexes it paints, or the hex edge it draws on.
+ */
+pub fn draw_map_editor(
+    mut gizmos: Gizmos,
+    hex_grid: Res<crate::map::resources::HexGrid>,
+    hovered_hex: Res<crate::map::resources::HoveredHex>,
+    camera2d_coords: Res<crate::map::resources::Camera2dCoords>,
+    editor: Res<crate::editor::resources::MapEditor>,
+    query_camera: Query<
+        (&OrthographicProjection, &GlobalTransform),
+        With<crate::map::entities::MapCamera2d>,
+    >,
+) {
+    let (Some(document), Ok((projection, camera_transform))) =
+        (editor.document.as_ref(), query_camera.get_single())
+    else {
+        return;
+    };
+    let layout = &hex_grid.0;
+    let outline = |gizmos: &mut Gizmos, hex: crate::map::hex::Hex, inset: f32, color: Color| {
+        let center = layout.hex_to_world(hex);
+        let corners = layout
+            .corners(hex)
+            .map(|corner| center + (corner - center) * inset);
+        gizmos.linestrip_2d(
+            corners.into_iter().chain(std::iter::once(corners[0])),
+            color,
+        );
+    };
+    let center = camera_transform.translation().truncate();
+    let (min, max) = (center + projection.area.min, center + projection.area.max);
+    crate::map::systems::draw_hexside_features(
+        &mut gizmos,
+        layout,
+        &document.terrain.hexsides,
+        min,
+        max,
+    );
+    for hex in layout.hexes_in_rect(min, max) {
+        let hex_terrain = document.terrain.get(hex);
+        let color = match hex_terrain.terrain {
+            TerrainType::Open => None,
+            TerrainType::Forest => Some(Color::rgb(0.1, 0.55, 0.1)),
+            TerrainType::Urban => Some(Color::GRAY),
+            TerrainType::Water => Some(Color::rgb(0.2, 0.5, 0.9)),
+        };
+        if let Some(color) = color {
+            for inset in [0.88, 0.86] {
+                outline(&mut gizmos, hex, inset, color);
+            }
+        }
+        for level in 1..=hex_terrain.elevation.min(MAX_ELEVATION) {
+            outline(
+                &mut gizmos,
+                hex,
+                0.8 - 0.06 * level as f32,
+                crate::map::systems::ROAD_COLOR,
+            );
+        }
+        let hex_center = layout.hex_to_world(hex);
+        if let Some(objective) = document.objective_at(hex) {
+            let color = match objective.owner {
+                Some(crate::oper::components::Side::Red) => Color::RED,
+                Some(crate::oper::components::Side::Blue) => Color::BLUE,
+                None => Color::WHITE,
+            };
+            for radius in [0.7, 0.75] {
+                gizmos
+                    .circle_2d(hex_center, layout.size * radius, color)
+                    .segments(48);
+            }
+        }
+    }
+    let Some(hex) = hovered_hex.0 else {
+        return;
+    };
+    match editor.tool {
+        EditorTool::Bridge | EditorTool::River | EditorTool::Wall => {
+            let (hex, direction) = layout.nearest_edge(camera2d_coords.0);
+            let [from, to] = layout.edge(hex, direction);
+            gizmos.line_2d(from, to, Color::CYAN);
+        }
+        EditorTool::Road | EditorTool::Objective => outline(&mut gizmos, hex, 0.92, Color::CYAN),
+        _ => {
+            for hex in hex.spiral(editor.brush) {
+                if layout.contains(hex) {
+                    outline(&mut gizmos, hex, 0.92, Color::CYAN);
+                }
+            }
+        }
+    }
+}
+
+/*
+ * e.g.
+ *
+ *   tool Elevation 2  brush 1  unsaved
+ *   saved wg/mlx/map/1-8819p-6299p.terrain.ron
+ */
+pub fn update_map_editor_text(
+    editor: Res<crate::editor::resources::MapEditor>,
+    mut query_text: Query<&mut Text, With<crate::editor::entities::EditorText>>,
+) {
+    if !editor.is_changed() {
+        return;
+    }
+    let mut line = format!("tool {}", editor.tool);
+    if editor.tool == EditorTool::Elevation {
+        line += &format!(" {}", editor.elevation);
+    }
+    line += &format!("  brush {}", editor.brush);
+    if editor
+        .document
+        .as_ref()
+        .is_some_and(|document| document.is_changed())
+    {
+        line += "  unsaved";
+    }
+    let label = format!("{}\n{}", line, editor.message);
+    for mut text in query_text.iter_mut() {
+        if text.sections[0].value != label {
+            text.sections[0].value = label.clone();
+        }
+    }
+}
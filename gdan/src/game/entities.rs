@@ -4,8 +4,125 @@ use bevy::prelude::*;
 #[derive(Component)]
 pub struct GameMenu;
 
+/// Main menu button that cycles through the available scenarios.
+#[derive(Component)]
+pub struct ScenarioButton;
+
+/// In-game panel showing the turn, the side to play and the phase.
+#[derive(Component)]
+pub struct TurnText;
+
+/// In-game button that ends the current phase.
+#[derive(Component)]
+pub struct EndPhaseButton;
+
+/// Button that writes the game in progress to disk.
+#[derive(Component)]
+pub struct SaveButton;
+
+/// Button that loads the saved game, in the main menu and on the map.
+#[derive(Component)]
+pub struct LoadButton;
+
+/// Main menu button that replays the last game played.
+#[derive(Component)]
+pub struct ReplayButton;
+
+/// Root of the new game screen.
+#[derive(Component)]
+pub struct NewGameMenu;
+
+/// Buttons of the new game screen; the scenario is picked with a `ScenarioButton`.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NewGameButton {
+    Mode,
+    /// Side played on this machine in a network game.
+    Side,
+    /// Address of a network game, typed in while the button shows it.
+    Address,
+    FogOfWar,
+    Start,
+    Back,
+}
+
+/// Screen hiding the map while the players of a hot-seat game swap places.
+#[derive(Component)]
+pub struct HandOffScreen;
+
+/// Button of the hand-off screen that shows the map to the next player.
+#[derive(Component)]
+pub struct HandOffButton;
+
+/// Buttons of the replay panel.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReplayControl {
+    Start,
+    Back,
+    PlayPause,
+    Step,
+    End,
+}
+
+/// Replay progress bar, click on it to seek.
+#[derive(Component)]
+pub struct ReplaySeekBar;
+
+/// Filled part of the replay progress bar.
+#[derive(Component)]
+pub struct ReplaySeekFill;
+
+/// Label of the play/pause button.
+#[derive(Component)]
+pub struct ReplayPlayLabel;
+
+/// Replay panel text: the step shown and the number of steps.
+#[derive(Component)]
+pub struct ReplayText;
+
+/*
+ * Phase of the turn being played on the map, see `crate::game::turn::TurnInfo`.
+ * Bevy 0.13 has no sub-states, so the state lives next to `MyAppState` and sits in `Inactive`
+ * whenever no game is being played.
+ */
+#[derive(
+    States, Default, Debug, Clone, PartialEq, Eq, Hash, Copy, serde::Serialize, serde::Deserialize,
+)]
+pub enum GamePhase {
+    #[default]
+    Inactive,
+    Movement,
+    Shooting,
+    CloseCombat,
+    End,
+    GameOver,
+}
+
+/*
+ * Whether the map shows a replay rather than a game being played: while it does,
+ * the player's orders are ignored and the commands come from the replay.
+ */
+#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash, Copy)]
+pub enum ReplayState {
+    #[default]
+    Off,
+    Paused,
+    Playing,
+}
+
+/*
+ * In hot-seat games the map is hidden behind a hand-off screen (`Waiting`) whenever the side to
+ * play changes, until its player is at the machine.
+ */
+#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash, Copy)]
+pub enum HandOffState {
+    #[default]
+    Off,
+    Waiting,
+}
+
+/// Intersection test shown in the GameMenu demo.
 #[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash, Copy)]
-pub enum GameState {
+pub enum IntersectionTest {
     AabbSweep,
     CircleSweep,
     #[default]
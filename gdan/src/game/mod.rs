@@ -1,4 +1,11 @@
 pub mod components;
 pub mod entities;
+pub mod replay;
 pub mod resources;
+pub mod save;
+pub mod scenario;
+pub mod session;
+pub mod simulation;
 pub mod systems;
+pub mod turn;
+pub mod victory;
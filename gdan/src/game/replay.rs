@@ -0,0 +1,43 @@
+// Replays: the commands of a whole game, played back on the map to review it afterwards
+
+/*
+ * A replay file is RON holding the scenario played (its asset path), the seed of the combat
+ * dice and every command given, in order:
+ *
+ * (
+ *     scenario: "scenarios/river-crossing.scenario.ron",
+ *     seed: 42,
+ *     commands: [Select(oper: Some(1)), Move(oper: 1, to: (q: 14, r: 14)), EndPhase, ...],
+ * )
+ *
+ * The rules are deterministic, so starting the scenario again with the same seed and carrying
+ * out the same commands plays the exact same game.
+ */
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Replay {
+    pub scenario: String,
+    pub seed: u64,
+    pub commands: Vec<crate::rule::commands::GameCommand>,
+}
+
+impl crate::tools::ron_file::RonFile for Replay {
+    const WHAT: &'static str = "replay file";
+}
+
+impl Replay {
+    /// An empty recording of a game of the scenario at asset path `scenario`.
+    pub fn new(scenario: &str, seed: u64) -> Self {
+        Replay {
+            scenario: scenario.to_string(),
+            seed,
+            commands: Vec::new(),
+        }
+    }
+
+    /// Plays the first `steps` commands on `session`; refused commands are skipped as they were.
+    pub fn play(&self, session: &mut crate::game::session::GameSession, steps: usize) {
+        for command in self.commands.iter().take(steps) {
+            let _ = session.apply(command);
+        }
+    }
+}
@@ -0,0 +1,171 @@
+pub const DEFAULT_SCENARIO: &str = "scenarios/river-crossing.scenario.ron";
+pub const DEFAULT_COMBAT_TABLE: &str = "wg/mlx/rule/combat.crt.ron";
+/// Where the game is saved to and loaded from, relative to the folder of the assets, see `save_path`.
+pub const SAVE_PATH: &str = "saves/quicksave.save.ron";
+/// Where the last game played is recorded to, and replayed from.
+pub const REPLAY_PATH: &str = "saves/last.replay.ron";
+/// Seconds between two commands when a replay plays on its own.
+pub const REPLAY_STEP_SECONDS: f32 = 0.6;
+
+/// `SAVE_PATH` next to the asset folder, found as the asset server finds it whatever the working folder.
+pub fn save_path() -> std::path::PathBuf {
+    bevy::asset::io::file::FileAssetReader::get_base_path().join(SAVE_PATH)
+}
+
+/// Every scenario found in the "scenarios" asset folder.
+#[derive(bevy::ecs::system::Resource, Default)]
+pub struct ScenarioList(pub bevy::asset::Handle<bevy::asset::LoadedFolder>);
+
+/// The scenario played when entering the map.
+#[derive(bevy::ecs::system::Resource, Default)]
+pub struct SelectedScenario(pub bevy::asset::Handle<crate::game::scenario::Scenario>);
+
+/// Asset path of the scenario selected when the app starts, see `crate::cli::LaunchArgs`.
+#[derive(bevy::ecs::system::Resource)]
+pub struct StartScenario(pub String);
+
+impl Default for StartScenario {
+    fn default() -> Self {
+        StartScenario(DEFAULT_SCENARIO.to_string())
+    }
+}
+
+/// A saved game waiting for its scenario to load before it is restored on the map.
+#[derive(bevy::ecs::system::Resource, Default)]
+pub struct PendingLoad(pub Option<crate::game::save::SaveGame>);
+
+/// A replay waiting for its scenario to load before it is played on the map.
+#[derive(bevy::ecs::system::Resource, Default)]
+pub struct PendingReplay(pub Option<crate::game::replay::Replay>);
+
+/// The commands of the game being played, `None` when it cannot be replayed from its seed.
+#[derive(bevy::ecs::system::Resource, Default)]
+pub struct Recording(pub Option<crate::game::replay::Replay>);
+
+/*
+ * The replay being played on the map: `next` is the index of the next command to carry out.
+ * Seeking goes back to `start`, the game as it was before the first command, and plays the
+ * commands up to the step sought on a headless session.
+ */
+#[derive(bevy::ecs::system::Resource)]
+pub struct ReplayPlayer {
+    pub replay: crate::game::replay::Replay,
+    pub start: crate::game::session::GameSession,
+    pub next: usize,
+    /// Step to seek to on the next frame.
+    pub seek: Option<usize>,
+    pub timer: bevy::time::Timer,
+}
+
+impl ReplayPlayer {
+    pub fn new(
+        replay: crate::game::replay::Replay,
+        start: crate::game::session::GameSession,
+    ) -> Self {
+        ReplayPlayer {
+            replay,
+            start,
+            next: 0,
+            seek: None,
+            timer: bevy::time::Timer::from_seconds(
+                REPLAY_STEP_SECONDS,
+                bevy::time::TimerMode::Repeating,
+            ),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.replay.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.replay.commands.is_empty()
+    }
+
+    pub fn is_at_end(&self) -> bool {
+        self.next >= self.len()
+    }
+
+    /// The next command to carry out, `None` at the end of the replay.
+    pub fn step(&mut self) -> Option<crate::rule::commands::GameCommand> {
+        let command = self.replay.commands.get(self.next).cloned()?;
+        self.next += 1;
+        Some(command)
+    }
+}
+
+/// How the sides of a game are played.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum GameMode {
+    /// One player gives the orders of both sides.
+    #[default]
+    Solo,
+    /// Two players share the machine and play one side each, with a hand-off screen between
+    /// their turns so neither sees what the other side has spotted.
+    HotSeat,
+    /// One player against the computer, which plays the other side, see `crate::ai`.
+    Computer,
+    /// This machine runs the game for a player connecting over the network, see `crate::net`.
+    Host,
+    /// This machine plays one side of a game run by a host.
+    Join,
+}
+
+impl GameMode {
+    pub fn is_network(self) -> bool {
+        matches!(self, GameMode::Host | GameMode::Join)
+    }
+}
+
+/// Options picked on the new game screen.
+#[derive(
+    bevy::ecs::system::Resource, Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize,
+)]
+pub struct GameSetup {
+    pub mode: GameMode,
+    /// Show only what the side to play has spotted; always on unless in solo games.
+    pub fog_of_war: bool,
+    /// Side of the player in games against the computer and in network games, where the host
+    /// gives the other side to the client.
+    pub side: crate::oper::components::Side,
+    /// Address to join, or whose port to host on.
+    pub address: String,
+    /// Name shown to the other player of a network game.
+    pub name: String,
+}
+
+impl Default for GameSetup {
+    fn default() -> Self {
+        GameSetup {
+            mode: GameMode::Solo,
+            fog_of_war: true,
+            side: crate::oper::components::Side::Red,
+            address: format!("127.0.0.1:{}", crate::net::resources::DEFAULT_PORT),
+            name: std::env::var("USER")
+                .or_else(|_| std::env::var("USERNAME"))
+                .unwrap_or_else(|_| "player".to_string()),
+        }
+    }
+}
+
+impl GameSetup {
+    /// Whether one side only is played on this machine.
+    pub fn plays_one_side(&self) -> bool {
+        self.mode == GameMode::Computer || self.mode.is_network()
+    }
+
+    pub fn fog_view(&self) -> crate::rule::fog::FogView {
+        if self.plays_one_side() {
+            crate::rule::fog::FogView::Side(self.side)
+        } else if self.fog_of_war || self.mode == GameMode::HotSeat {
+            crate::rule::fog::FogView::SideToPlay
+        } else {
+            crate::rule::fog::FogView::All
+        }
+    }
+
+    /// Whether the orders of `side` are given on this machine.
+    pub fn plays(&self, side: crate::oper::components::Side) -> bool {
+        !self.plays_one_side() || side == self.side
+    }
+}
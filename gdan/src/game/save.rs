@@ -0,0 +1,61 @@
+// Saved games: a game in progress written to disk and read back exactly as it was
+
+/*
+ * A save file is RON holding the scenario being played (its asset path), the turn and phase,
+ * the dice generator with its exact state, every unit and the combat log:
+ *
+ * (
+ *     scenario: "scenarios/river-crossing.scenario.ron",
+ *     turn_info: (turn: 2, turn_limit: 10, first_side: Red, side: Blue, phase: Shooting),
+ *     rng: (seed: 42, rng: (...)),
+ *     units: [(oper: (id: 1, name: "Infantry platoon", ...), hex: (q: 14, r: 15), ...)],
+ *     combat_log: [(turn: 1, attacker_id: 3, ...)],
+ *     intel: {Red: (spotted: [101], last_known: {101: (q: 20, r: 9)}), Blue: (...)},
+ *     replay: Some((scenario: "scenarios/river-crossing.scenario.ron", seed: 42, commands: [...])),
+ *     setup: Some((mode: Computer, fog_of_war: true, side: Blue, address: "...", name: "...")),
+ *     fog_view: Some(Side(Blue)),
+ * )
+ *
+ * The map, terrain, unit types and combat table are not saved, they are read from the scenario.
+ * The recording of the game so far is saved too, so a loaded game can still be replayed, and so
+ * are the options of the new game screen and the side the map was shown for, so it is played on
+ * the same way. Saves written without them keep the options of the game being played.
+ */
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct SaveGame {
+    pub scenario: String,
+    pub turn_info: crate::game::turn::TurnInfo,
+    pub rng: crate::rule::resources::GameRng,
+    pub units: Vec<crate::game::session::Unit>,
+    pub combat_log: Vec<crate::rule::combat::CombatLogEntry>,
+    /// What each side knows of the enemy, with the last known hexes of the units out of sight.
+    #[serde(default)]
+    pub intel: bevy::utils::HashMap<crate::oper::components::Side, crate::rule::fog::Intel>,
+    #[serde(default)]
+    pub replay: Option<crate::game::replay::Replay>,
+    #[serde(default)]
+    pub setup: Option<crate::game::resources::GameSetup>,
+    #[serde(default)]
+    pub fog_view: Option<crate::rule::fog::FogView>,
+}
+
+impl crate::tools::ron_file::RonFile for SaveGame {
+    const WHAT: &'static str = "save file";
+}
+
+impl SaveGame {
+    /// Snapshot of a session playing the scenario at asset path `scenario`, without a recording.
+    pub fn from_session(scenario: &str, session: &crate::game::session::GameSession) -> Self {
+        SaveGame {
+            scenario: scenario.to_string(),
+            turn_info: session.turn_info.clone(),
+            rng: session.rng.clone(),
+            units: session.units.clone(),
+            combat_log: session.combat_log.0.clone(),
+            intel: session.intel.clone(),
+            replay: None,
+            setup: None,
+            fog_view: None,
+        }
+    }
+}
@@ -0,0 +1,180 @@
+// Scenarios: everything needed to set up a game on the map
+
+use bevy::prelude::*;
+
+/*
+ * A scenario file ("scenarios/<name>.scenario.ron") names the map image, its terrain file,
+ * the unit definitions and optionally the combat table,
+ * and places both sides' operators by zero-based (col, row):
+ *
+ * (
+ *     name: "River crossing",
+ *     map: "wg/mlx/map/1-8819p-6299p.png",
+ *     terrain: "wg/mlx/map/1-8819p-6299p.terrain.ron",
+ *     opers: "wg/mlx/oper/opers.ron",
+ *     combat_table: "wg/mlx/rule/combat.crt.ron",
+ *     sides: [(side: Red, name: "Red force"), (side: Blue, name: "Blue force")],
+ *     first_side: Red,
+ *     turn_limit: 10,
+ *     units: [(id: 1, type_id: "001", side: Red, col: 20, row: 12)],
+ *     objectives: [(name: "Town", col: 20, row: 14, points: 10, owner: Some(Blue))],
+ *     map_sheet: Some((width: 8819, height: 6299, hex_size: 72., level: 21)),
+ *     geo: Some((origin: (lat: 39.9, lon: 116.4), meter_per_pixel: 0.1389)),
+ * )
+ *
+ * The loader also loads the referenced files, so a scenario is ready to play
+ * once it is loaded with its dependencies. The map image is left to the map, which may show
+ * its tiles instead, see `crate::map::tiles`.
+ */
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct SideInfo {
+    pub side: crate::oper::components::Side,
+    pub name: String,
+}
+
+/// A hex worth victory points to the side holding it at the end of the game.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Objective {
+    pub name: String,
+    pub col: i32,
+    pub row: i32,
+    pub points: u32,
+    /// Side holding the objective when the game starts.
+    #[serde(default)]
+    pub owner: Option<crate::oper::components::Side>,
+}
+
+impl Objective {
+    pub fn hex(&self) -> crate::map::hex::Hex {
+        crate::map::hex::Hex::from_offset(crate::map::hex::OffsetCoord::new(self.col, self.row))
+    }
+}
+
+/*
+ * Size and scale of a scenario's map image, which the hex grid is laid over.
+ * Without it the scenario is taken to be on the default map, see `MapInfo::default`.
+ * Without a `geo` either, a pixel covers the ground of a pixel of the satellite map `level`.
+ */
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct MapSheet {
+    /// Size of the map image, in pixels.
+    pub width: u32,
+    pub height: u32,
+    /// Centre-to-corner radius of a hex, in image pixels.
+    pub hex_size: f32,
+    /// Satellite map level the image was taken at.
+    pub level: u8,
+    /// Numbers printed on the first column and the first row of hexes.
+    #[serde(default = "first_label")]
+    pub label_x: u32,
+    #[serde(default = "first_label")]
+    pub label_y: u32,
+}
+
+fn first_label() -> u32 {
+    1
+}
+
+/// On-disk layout of a scenario file.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct ScenarioFile {
+    pub name: String,
+    pub map: String,
+    pub terrain: String,
+    pub opers: String,
+    #[serde(default = "default_combat_table")]
+    pub combat_table: String,
+    pub sides: Vec<SideInfo>,
+    pub first_side: crate::oper::components::Side,
+    pub turn_limit: u32,
+    pub units: Vec<crate::oper::catalog::OperPlacement>,
+    #[serde(default)]
+    pub objectives: Vec<Objective>,
+    /// The map image, when it is not the default map.
+    #[serde(default)]
+    pub map_sheet: Option<MapSheet>,
+    /// Where the map lies on the ground, see `crate::map::geo`.
+    #[serde(default)]
+    pub geo: Option<crate::map::geo::GeoReference>,
+}
+
+fn default_combat_table() -> String {
+    crate::game::resources::DEFAULT_COMBAT_TABLE.to_string()
+}
+
+impl crate::tools::ron_file::RonFile for ScenarioFile {
+    const WHAT: &'static str = "scenario";
+}
+
+impl ScenarioFile {
+    /// The map of the scenario: its image size, hex size and where it lies on the ground.
+    pub fn map_info(&self) -> crate::map::resources::MapInfo {
+        let mut map_info = crate::map::resources::MapInfo::default();
+        if let Some(sheet) = self.map_sheet {
+            // a satellite map level more halves the ground a pixel covers
+            map_info.meter_per_pixel *=
+                2f32.powi(map_info.satellite_map_level as i32 - sheet.level as i32);
+            map_info.unit_x = sheet.width as f32;
+            map_info.unit_y = sheet.height as f32;
+            map_info.hex_size = sheet.hex_size;
+            map_info.satellite_map_level = sheet.level;
+            map_info.label_x = sheet.label_x;
+            map_info.label_y = sheet.label_y;
+        }
+        if let Some(geo) = self.geo {
+            map_info.origin = Some(geo.origin);
+            map_info.meter_per_pixel = geo.meter_per_pixel;
+        }
+        map_info
+    }
+
+    pub fn side_name(&self, side: crate::oper::components::Side) -> &str {
+        self.sides
+            .iter()
+            .find(|side_info| side_info.side == side)
+            .map_or("", |side_info| side_info.name.as_str())
+    }
+}
+
+#[derive(Asset, TypePath, Debug)]
+pub struct Scenario {
+    pub file: ScenarioFile,
+    #[dependency]
+    pub terrain: Handle<crate::map::terrain::TerrainMap>,
+    #[dependency]
+    pub opers: Handle<crate::oper::catalog::OperCatalog>,
+    #[dependency]
+    pub combat_table: Handle<crate::rule::combat::CombatTable>,
+}
+
+#[derive(Default)]
+pub struct ScenarioLoader;
+
+impl bevy::asset::AssetLoader for ScenarioLoader {
+    type Asset = Scenario;
+    type Settings = ();
+    type Error = crate::tools::ron_file::RonFileError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut bevy::asset::io::Reader,
+        _settings: &'a (),
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<Scenario, crate::tools::ron_file::RonFileError>> {
+        Box::pin(async move {
+            let bytes = crate::tools::ron_file::read_asset::<ScenarioFile>(reader).await?;
+            let file: ScenarioFile = crate::tools::ron_file::RonFile::from_bytes(&bytes)?;
+            Ok(Scenario {
+                terrain: load_context.load(file.terrain.clone()),
+                opers: load_context.load(file.opers.clone()),
+                combat_table: load_context.load(file.combat_table.clone()),
+                file,
+            })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["scenario.ron"]
+    }
+}
@@ -0,0 +1,281 @@
+// Game session: a whole game as plain data, playable without a window or an ECS world
+
+use bevy::prelude::*;
+
+use crate::map::hex::Hex;
+use crate::oper::components::Oper;
+use crate::rule::commands::{CommandError, GameCommand};
+use crate::tools::ron_file::RonFile;
+
+/// One operator of a session and where it stands.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Unit {
+    pub oper: Oper,
+    pub hex: Hex,
+    pub counter: crate::oper::components::OperCounter,
+    /// Already attacked in the current phase.
+    pub fired: bool,
+}
+
+/// The components of a unit on the map, as `units_from_world` copies them out.
+pub type UnitComponents = (
+    Entity,
+    &'static Oper,
+    &'static crate::map::components::HexPosition,
+    &'static crate::oper::components::OperCounter,
+    Has<crate::rule::components::Fired>,
+);
+
+/// The components of a unit on the map, as `write_units_back` copies a unit back into them.
+pub type UnitComponentsMut = (
+    Entity,
+    &'static mut Oper,
+    &'static mut crate::map::components::HexPosition,
+    &'static crate::oper::components::OperCounter,
+    Has<crate::rule::components::Fired>,
+);
+
+/*
+ * The units on the map as a session holds them, ordered by id, and the entity of each.
+ * Takes what a `Query<UnitComponents>` or a `Query<UnitComponentsMut>` iterates over.
+ */
+pub fn units_from_world<'a>(
+    components: impl Iterator<
+        Item = (
+            Entity,
+            &'a Oper,
+            &'a crate::map::components::HexPosition,
+            &'a crate::oper::components::OperCounter,
+            bool,
+        ),
+    >,
+) -> (Vec<Entity>, Vec<Unit>) {
+    let mut units: Vec<(Entity, Unit)> = components
+        .map(|(entity, oper, hex_position, counter, fired)| {
+            (
+                entity,
+                Unit {
+                    oper: oper.clone(),
+                    hex: hex_position.0,
+                    counter: counter.clone(),
+                    fired,
+                },
+            )
+        })
+        .collect();
+    units.sort_by_key(|(_, unit)| unit.oper.id);
+    units.into_iter().unzip()
+}
+
+/*
+ * Copies units back into the components of their entities. Only what changed is written, so that
+ * change detection only sees the units that did change. Returns the units that moved, with the
+ * hex each left and the hex it is in now.
+ */
+pub fn write_units_back(
+    commands: &mut Commands,
+    query: &mut Query<UnitComponentsMut>,
+    units: impl IntoIterator<Item = (Entity, Unit)>,
+) -> Vec<(Entity, Hex, Hex)> {
+    let mut moved = Vec::new();
+    for (entity, unit) in units {
+        let Ok((_, mut oper, mut hex_position, _, has_fired)) = query.get_mut(entity) else {
+            continue;
+        };
+        if *oper != unit.oper {
+            *oper = unit.oper;
+        }
+        if hex_position.0 != unit.hex {
+            moved.push((entity, hex_position.0, unit.hex));
+            hex_position.0 = unit.hex;
+        }
+        match (has_fired, unit.fired) {
+            (false, true) => {
+                commands
+                    .entity(entity)
+                    .insert(crate::rule::components::Fired);
+            }
+            (true, false) => {
+                commands
+                    .entity(entity)
+                    .remove::<crate::rule::components::Fired>();
+            }
+            _ => {}
+        }
+    }
+    moved
+}
+
+/*
+ * A game in progress: the scenario and its rule data, the turn, the dice and every unit.
+ * Sessions are played by applying `GameCommand`s, which run the same rule functions as the
+ * windowed game, so whole games can be simulated in tests and batch runs:
+ *
+ *     let mut session = GameSession::load("assets", "scenarios/river-crossing.scenario.ron", 42)?;
+ *     session.apply(&GameCommand::Move { oper: 1, to: Hex::new(14, 14) })?;
+ *     session.apply(&GameCommand::EndPhase)?;
+ *
+ * `spawn` puts a session into an ECS world, where `crate::rule::plugin::RulesPlugin` plays it.
+ */
+#[derive(Clone, Debug)]
+pub struct GameSession {
+    pub scenario: crate::game::scenario::ScenarioFile,
+    pub rules: crate::rule::resources::RuleBook,
+    pub turn_info: crate::game::turn::TurnInfo,
+    pub rng: crate::rule::resources::GameRng,
+    pub combat_log: crate::rule::resources::CombatLog,
+    pub units: Vec<Unit>,
+    /// What each side knows of the enemy, see `crate::rule::fog`.
+    pub intel: bevy::utils::HashMap<crate::oper::components::Side, crate::rule::fog::Intel>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SessionError {
+    /// A file of the scenario, by asset path, could not be read.
+    #[error("{0}: {1}")]
+    File(String, crate::tools::ron_file::RonFileError),
+}
+
+impl GameSession {
+    /// Starts a game of `scenario` with units built from `catalog` and dice seeded with `seed`.
+    pub fn new(
+        scenario: crate::game::scenario::ScenarioFile,
+        catalog: &crate::oper::catalog::OperCatalog,
+        rules: crate::rule::resources::RuleBook,
+        seed: u64,
+    ) -> Self {
+        let units = scenario
+            .units
+            .iter()
+            .filter_map(|placement| {
+                let unit = catalog.instantiate(placement);
+                if unit.is_none() {
+                    warn!(
+                        "unit {} has unknown type {:?}",
+                        placement.id, placement.type_id
+                    );
+                }
+                unit
+            })
+            .map(|(oper, hex, counter)| Unit {
+                oper,
+                hex,
+                counter,
+                fired: false,
+            })
+            .collect();
+        let mut session = GameSession {
+            turn_info: crate::game::turn::TurnInfo::new(scenario.first_side, scenario.turn_limit),
+            scenario,
+            rules,
+            rng: crate::rule::resources::GameRng::new(seed),
+            combat_log: crate::rule::resources::CombatLog::default(),
+            units,
+            intel: Default::default(),
+        };
+        session.update_intel();
+        session
+    }
+
+    /// Picks up a saved game of `scenario`.
+    pub fn restore(
+        save: crate::game::save::SaveGame,
+        scenario: crate::game::scenario::ScenarioFile,
+        rules: crate::rule::resources::RuleBook,
+    ) -> Self {
+        GameSession {
+            scenario,
+            rules,
+            turn_info: save.turn_info,
+            rng: save.rng,
+            combat_log: crate::rule::resources::CombatLog(save.combat_log),
+            units: save.units,
+            intel: save.intel,
+        }
+    }
+
+    /// Loads a scenario and the files it names from the asset folder `asset_root`.
+    pub fn load(
+        asset_root: impl AsRef<std::path::Path>,
+        scenario_path: &str,
+        seed: u64,
+    ) -> Result<Self, SessionError> {
+        let root = asset_root.as_ref();
+        fn read<T: RonFile>(root: &std::path::Path, path: &str) -> Result<T, SessionError> {
+            T::read(root.join(path)).map_err(|error| SessionError::File(path.to_string(), error))
+        }
+        let scenario: crate::game::scenario::ScenarioFile = read(root, scenario_path)?;
+        let terrain = read(root, &scenario.terrain)?;
+        let catalog: crate::oper::catalog::OperCatalog = read(root, &scenario.opers)?;
+        let combat_table = read(root, &scenario.combat_table)?;
+        let rules = crate::rule::resources::RuleBook {
+            terrain,
+            layout: crate::map::hex::HexLayout::from_map_info(&scenario.map_info()),
+            combat_table,
+            objectives: scenario.objectives.clone(),
+        };
+        Ok(GameSession::new(scenario, &catalog, rules, seed))
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.turn_info.is_over()
+    }
+
+    /// Victory points of each side as things stand, see `crate::game::victory`.
+    pub fn score(&self) -> crate::game::victory::Score {
+        crate::game::victory::score(&self.rules.objectives, &self.units)
+    }
+
+    pub fn unit(&self, id: u32) -> Option<&Unit> {
+        self.units.iter().find(|unit| unit.oper.id == id)
+    }
+
+    /// Looks again at what each side has spotted, after units moved or were destroyed.
+    pub fn update_intel(&mut self) {
+        let units: Vec<(&Oper, Hex)> = self
+            .units
+            .iter()
+            .map(|unit| (&unit.oper, unit.hex))
+            .collect();
+        crate::rule::fog::update_intel(&mut self.intel, &self.rules.terrain, &units);
+    }
+
+    /// Checks and carries out one command, see `crate::rule::commands::execute`.
+    pub fn apply(&mut self, command: &GameCommand) -> Result<(), CommandError> {
+        let executed = crate::rule::commands::execute(
+            &self.rules,
+            &mut self.turn_info,
+            &mut self.rng.rng,
+            &mut self.units,
+            &mut self.intel,
+            command,
+        )?;
+        if let crate::rule::commands::Executed::Attacked(entry) = executed {
+            self.combat_log.0.push(entry);
+        }
+        Ok(())
+    }
+
+    /// Puts the session into an ECS world: its rule resources and one entity per unit.
+    pub fn spawn(self, world: &mut World) {
+        for unit in self.units {
+            let mut entity = world.spawn((
+                unit.oper,
+                unit.counter,
+                crate::map::components::HexPosition(unit.hex),
+                crate::map::entities::MapNC,
+                crate::map::entities::MapMenu,
+            ));
+            if unit.fired {
+                entity.insert(crate::rule::components::Fired);
+            }
+        }
+        world.insert_resource(self.rules);
+        world.insert_resource(self.rng);
+        world.insert_resource(self.combat_log);
+        world.insert_resource(self.turn_info);
+        world
+            .get_resource_or_insert_with(crate::rule::resources::FogOfWar::default)
+            .intel = self.intel;
+    }
+}
@@ -0,0 +1,254 @@
+// Batch simulation: a scenario played over and over by the computer, and what came out of it
+
+use crate::oper::components::Side;
+
+/*
+ * Every run plays the scenario from the start with a computer player on each side; only the
+ * dice change, run `i` rolling them from seed `seed + i`, so any run can be played again.
+ * A game is decided on the turn from which the side ahead at the end of every turn is the
+ * winner (no side ahead for a draw), and stays so until the end.
+ */
+
+/// Losses of one unit in one game.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct UnitLoss {
+    pub id: u32,
+    pub side: Side,
+    /// Name of the unit's type.
+    pub unit_type: String,
+    /// Strength points lost.
+    pub lost: i32,
+    pub destroyed: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct GameResult {
+    pub seed: u64,
+    pub winner: Option<Side>,
+    pub score: crate::game::victory::Score,
+    pub turns: u32,
+    pub decided_turn: u32,
+    pub losses: Vec<UnitLoss>,
+}
+
+/// Average losses of the units of one type and side over every run.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TypeLosses {
+    pub side: Side,
+    pub unit_type: String,
+    /// Units of the type in one game.
+    pub units: usize,
+    /// Strength points lost per game, all units of the type together.
+    pub average_lost: f32,
+    /// Units destroyed per game.
+    pub average_destroyed: f32,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SimulationReport {
+    pub scenario: String,
+    pub runs: usize,
+    pub seed: u64,
+    pub red_win_rate: f32,
+    pub blue_win_rate: f32,
+    pub draw_rate: f32,
+    pub average_decided_turn: f32,
+    pub losses: Vec<TypeLosses>,
+    pub games: Vec<GameResult>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReportError {
+    #[error("could not write report: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// Plays `session` to the end with the computer on both sides.
+pub fn play_game(
+    mut session: crate::game::session::GameSession,
+    profiles: &[(Side, crate::ai::player::AiProfile)],
+) -> GameResult {
+    let seed = session.rng.seed;
+    let mut players: Vec<crate::ai::player::AiPlayer> = profiles
+        .iter()
+        .map(|(side, profile)| crate::ai::player::AiPlayer::new(*side, profile.clone()))
+        .collect();
+    let start = session.units.clone();
+    // side ahead at the end of each turn
+    let mut leaders: Vec<Option<Side>> = Vec::new();
+    while !session.is_over() {
+        let turn = session.turn_info.turn;
+        let side = session.turn_info.side;
+        let command = players
+            .iter_mut()
+            .find(|player| player.side == side)
+            .and_then(|player| {
+                player.next_command(&crate::ai::player::AiView::from_session(&session, side))
+            })
+            .unwrap_or(crate::rule::commands::GameCommand::EndPhase);
+        if session.apply(&command).is_err()
+            && command == crate::rule::commands::GameCommand::EndPhase
+        {
+            break;
+        }
+        if session.turn_info.turn != turn || session.is_over() {
+            leaders.push(session.score().leader());
+        }
+    }
+    let score = session.score();
+    let winner = score.leader();
+    let decided = leaders
+        .iter()
+        .rposition(|leader| *leader != winner)
+        .map_or(0, |last| last + 1);
+    GameResult {
+        seed,
+        winner,
+        score,
+        turns: session.turn_info.turn,
+        decided_turn: decided as u32 + 1,
+        losses: session
+            .units
+            .iter()
+            .zip(start.iter())
+            .map(|(unit, start)| UnitLoss {
+                id: unit.oper.id,
+                side: unit.oper.side,
+                unit_type: unit.oper.name.clone(),
+                lost: start.oper.strength - unit.oper.strength,
+                destroyed: unit.oper.is_destroyed(),
+            })
+            .collect(),
+    }
+}
+
+/// Plays `runs` games of `session`, calling `progress` after each with the number played.
+pub fn simulate(
+    session: &crate::game::session::GameSession,
+    runs: usize,
+    seed: u64,
+    profiles: &[(Side, crate::ai::player::AiProfile)],
+    mut progress: impl FnMut(usize),
+) -> Vec<GameResult> {
+    (0..runs)
+        .map(|run| {
+            let mut game = session.clone();
+            game.rng = crate::rule::resources::GameRng::new(seed.wrapping_add(run as u64));
+            let result = play_game(game, profiles);
+            progress(run + 1);
+            result
+        })
+        .collect()
+}
+
+impl SimulationReport {
+    pub fn new(scenario: &str, seed: u64, games: Vec<GameResult>) -> Self {
+        let runs = games.len();
+        let rate = |count: usize| {
+            if runs == 0 {
+                0.
+            } else {
+                count as f32 / runs as f32
+            }
+        };
+        let wins = |winner: Option<Side>| games.iter().filter(|game| game.winner == winner).count();
+        let mut losses: Vec<TypeLosses> = Vec::new();
+        for game in games.iter() {
+            for loss in game.losses.iter() {
+                let index = match losses.iter().position(|losses| {
+                    losses.side == loss.side && losses.unit_type == loss.unit_type
+                }) {
+                    Some(index) => index,
+                    None => {
+                        losses.push(TypeLosses {
+                            side: loss.side,
+                            unit_type: loss.unit_type.clone(),
+                            units: 0,
+                            average_lost: 0.,
+                            average_destroyed: 0.,
+                        });
+                        losses.len() - 1
+                    }
+                };
+                losses[index].average_lost += loss.lost as f32;
+                losses[index].average_destroyed += loss.destroyed as u32 as f32;
+            }
+        }
+        if let Some(game) = games.first() {
+            for type_losses in losses.iter_mut() {
+                type_losses.units = game
+                    .losses
+                    .iter()
+                    .filter(|loss| {
+                        loss.side == type_losses.side && loss.unit_type == type_losses.unit_type
+                    })
+                    .count();
+                type_losses.average_lost /= runs as f32;
+                type_losses.average_destroyed /= runs as f32;
+            }
+        }
+        losses.sort_by(|a, b| (a.side as u8, &a.unit_type).cmp(&(b.side as u8, &b.unit_type)));
+        SimulationReport {
+            scenario: scenario.to_string(),
+            runs,
+            seed,
+            red_win_rate: rate(wins(Some(Side::Red))),
+            blue_win_rate: rate(wins(Some(Side::Blue))),
+            draw_rate: rate(wins(None)),
+            average_decided_turn: if runs == 0 {
+                0.
+            } else {
+                games
+                    .iter()
+                    .map(|game| game.decided_turn as f32)
+                    .sum::<f32>()
+                    / runs as f32
+            },
+            losses,
+            games,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, ReportError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /*
+     * One statistic per line:
+     *
+     *   statistic,side,unit_type,value
+     *   runs,,,1000
+     *   win_rate,Red,,0.412
+     *   average_lost,Blue,Tank,1.35
+     */
+    pub fn to_csv(&self) -> String {
+        let mut lines = vec!["statistic,side,unit_type,value".to_string()];
+        lines.push(format!("runs,,,{}", self.runs));
+        lines.push(format!("seed,,,{}", self.seed));
+        lines.push(format!("win_rate,Red,,{}", self.red_win_rate));
+        lines.push(format!("win_rate,Blue,,{}", self.blue_win_rate));
+        lines.push(format!("draw_rate,,,{}", self.draw_rate));
+        lines.push(format!(
+            "average_decided_turn,,,{}",
+            self.average_decided_turn
+        ));
+        for losses in self.losses.iter() {
+            // type names are quoted, doubling the quotes they hold
+            let unit_type = format!("\"{}\"", losses.unit_type.replace('"', "\"\""));
+            lines.push(format!(
+                "units,{:?},{},{}",
+                losses.side, unit_type, losses.units
+            ));
+            lines.push(format!(
+                "average_lost,{:?},{},{}",
+                losses.side, unit_type, losses.average_lost
+            ));
+            lines.push(format!(
+                "average_destroyed,{:?},{},{}",
+                losses.side, unit_type, losses.average_destroyed
+            ));
+        }
+        lines.push(String::new());
+        lines.join("\n")
+    }
+}
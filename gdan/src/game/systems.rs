@@ -1,11 +1,1273 @@
 use bevy::math::bounding::*;
 use bevy::prelude::*;
 
+use crate::tools::ron_file::RonFile;
+
 pub fn camera2dbundle(mut commands: Commands) {
     info!("camera2dbundle");
     commands.spawn((Camera2dBundle::default(), crate::game::entities::GameMenu));
 }
 
+pub fn load_scenarios(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    start_scenario: Res<crate::game::resources::StartScenario>,
+) {
+    info!("load_scenarios");
+    commands.insert_resource(crate::game::resources::ScenarioList(
+        asset_server.load_folder("scenarios"),
+    ));
+    commands.insert_resource(crate::game::resources::SelectedScenario(
+        asset_server.load(start_scenario.0.clone()),
+    ));
+}
+
+pub fn scenario_picker_system(
+    interaction_query: Query<
+        &Interaction,
+        (
+            Changed<Interaction>,
+            With<crate::game::entities::ScenarioButton>,
+        ),
+    >,
+    scenario_list: Res<crate::game::resources::ScenarioList>,
+    folders: Res<Assets<bevy::asset::LoadedFolder>>,
+    mut selected_scenario: ResMut<crate::game::resources::SelectedScenario>,
+) {
+    for interaction in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        let Some(folder) = folders.get(&scenario_list.0) else {
+            continue;
+        };
+        let mut handles: Vec<Handle<crate::game::scenario::Scenario>> = folder
+            .handles
+            .iter()
+            .filter_map(|handle| handle.clone().try_typed().ok())
+            .collect();
+        if handles.is_empty() {
+            continue;
+        }
+        handles.sort_by_key(|handle| handle.path().map(|path| path.to_string()));
+        // the one after the current scenario, wrapping around
+        let next = handles
+            .iter()
+            .position(|handle| handle.id() == selected_scenario.0.id())
+            .map_or(0, |index| (index + 1) % handles.len());
+        selected_scenario.0 = handles[next].clone();
+        info!("scenario: {:?}", selected_scenario.0.path());
+    }
+}
+
+pub fn update_scenario_button(
+    selected_scenario: Res<crate::game::resources::SelectedScenario>,
+    scenarios: Res<Assets<crate::game::scenario::Scenario>>,
+    query_button: Query<&Children, With<crate::game::entities::ScenarioButton>>,
+    mut text_query: Query<&mut Text>,
+) {
+    let label = match scenarios.get(&selected_scenario.0) {
+        Some(scenario) => format!("Scenario: {}", scenario.file.name),
+        None => "Scenario: loading...".to_string(),
+    };
+    for children in query_button.iter() {
+        let Ok(mut text) = text_query.get_mut(children[0]) else {
+            continue;
+        };
+        if text.sections[0].value != label {
+            text.sections[0].value = label.clone();
+        }
+    }
+}
+
+/*
+ * The new game screen, entered from the main menu: how the sides are played, the scenario,
+ * whether the fog of war is on, then Start to go to the map, or to the lobby of a network game.
+ */
+pub fn new_game_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    info!("new_game_menu");
+    commands.spawn((
+        Camera2dBundle::default(),
+        crate::game::entities::NewGameMenu,
+    ));
+    let text_style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 40.,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(15.),
+                    ..default()
+                },
+                ..default()
+            },
+            crate::game::entities::NewGameMenu,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section("New game", text_style.clone()),
+                crate::game::entities::NewGameMenu,
+            ));
+            for button in [
+                Some(crate::game::entities::NewGameButton::Mode),
+                Some(crate::game::entities::NewGameButton::Side),
+                Some(crate::game::entities::NewGameButton::Address),
+                None,
+                Some(crate::game::entities::NewGameButton::FogOfWar),
+                Some(crate::game::entities::NewGameButton::Start),
+                Some(crate::game::entities::NewGameButton::Back),
+            ] {
+                let mut entity = parent.spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(420.),
+                            height: Val::Px(65.),
+                            border: UiRect::all(Val::Px(5.)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        border_color: BorderColor(Color::BLACK),
+                        background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                        ..default()
+                    },
+                    crate::game::entities::NewGameMenu,
+                ));
+                match button {
+                    Some(button) => entity.insert(button),
+                    // the scenario button is labelled by `update_scenario_button`
+                    None => entity.insert(crate::game::entities::ScenarioButton),
+                };
+                entity.with_children(|parent| {
+                    parent.spawn((
+                        TextBundle::from_section("", text_style.clone()),
+                        crate::game::entities::NewGameMenu,
+                    ));
+                });
+            }
+        });
+}
+
+pub fn new_game_buttons(
+    interaction_query: Query<
+        (&Interaction, &crate::game::entities::NewGameButton),
+        Changed<Interaction>,
+    >,
+    mut setup: ResMut<crate::game::resources::GameSetup>,
+    mut next_state: ResMut<NextState<crate::MyAppState>>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            crate::game::entities::NewGameButton::Mode => {
+                setup.mode = match setup.mode {
+                    crate::game::resources::GameMode::Solo => {
+                        crate::game::resources::GameMode::HotSeat
+                    }
+                    crate::game::resources::GameMode::HotSeat => {
+                        crate::game::resources::GameMode::Computer
+                    }
+                    crate::game::resources::GameMode::Computer => {
+                        crate::game::resources::GameMode::Host
+                    }
+                    crate::game::resources::GameMode::Host => {
+                        crate::game::resources::GameMode::Join
+                    }
+                    crate::game::resources::GameMode::Join => {
+                        crate::game::resources::GameMode::Solo
+                    }
+                };
+            }
+            crate::game::entities::NewGameButton::Side => {
+                if matches!(
+                    setup.mode,
+                    crate::game::resources::GameMode::Computer
+                        | crate::game::resources::GameMode::Host
+                ) {
+                    setup.side = setup.side.opponent();
+                }
+            }
+            crate::game::entities::NewGameButton::Address => {}
+            crate::game::entities::NewGameButton::FogOfWar => {
+                setup.fog_of_war = !setup.fog_of_war;
+            }
+            crate::game::entities::NewGameButton::Start => {
+                info!("new game {:?}", *setup);
+                if setup.mode.is_network() {
+                    next_state.set(crate::MyAppState::Lobby);
+                } else {
+                    next_state.set(crate::MyAppState::MapMenu);
+                }
+            }
+            crate::game::entities::NewGameButton::Back => {
+                next_state.set(crate::MyAppState::MainMenu);
+            }
+        }
+    }
+}
+
+pub fn update_new_game_text(
+    setup: Res<crate::game::resources::GameSetup>,
+    query_button: Query<(&crate::game::entities::NewGameButton, &Children)>,
+    mut text_query: Query<&mut Text>,
+) {
+    for (button, children) in query_button.iter() {
+        let label = match button {
+            crate::game::entities::NewGameButton::Mode => match setup.mode {
+                crate::game::resources::GameMode::Solo => "Players: solo".to_string(),
+                crate::game::resources::GameMode::HotSeat => "Players: hot seat".to_string(),
+                crate::game::resources::GameMode::Computer => {
+                    "Players: against the computer".to_string()
+                }
+                crate::game::resources::GameMode::Host => "Players: host a game".to_string(),
+                crate::game::resources::GameMode::Join => "Players: join a game".to_string(),
+            },
+            crate::game::entities::NewGameButton::Side => match setup.mode {
+                crate::game::resources::GameMode::Computer
+                | crate::game::resources::GameMode::Host => format!("Your side: {:?}", setup.side),
+                crate::game::resources::GameMode::Join => "Your side: given by host".to_string(),
+                _ => "Your side: both".to_string(),
+            },
+            crate::game::entities::NewGameButton::Address => match setup.mode {
+                crate::game::resources::GameMode::Host | crate::game::resources::GameMode::Join => {
+                    format!("Address: {}_", setup.address)
+                }
+                _ => "Address: local game".to_string(),
+            },
+            crate::game::entities::NewGameButton::FogOfWar => {
+                if setup.fog_view() == crate::rule::fog::FogView::All {
+                    "Fog of war: off".to_string()
+                } else {
+                    "Fog of war: on".to_string()
+                }
+            }
+            crate::game::entities::NewGameButton::Start => "Start".to_string(),
+            crate::game::entities::NewGameButton::Back => "Back".to_string(),
+        };
+        let Ok(mut text) = text_query.get_mut(children[0]) else {
+            continue;
+        };
+        if text.sections[0].value != label {
+            text.sections[0].value = label;
+        }
+    }
+}
+
+/// The keyboard types the address of a network game on the new game screen.
+pub fn typing_address(setup: Res<crate::game::resources::GameSetup>) -> bool {
+    setup.mode.is_network()
+}
+
+/// Types the address of a network game: host and port to join, or the port to host on.
+pub fn edit_network_address(
+    mut characters: EventReader<ReceivedCharacter>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut setup: ResMut<crate::game::resources::GameSetup>,
+) {
+    if !setup.mode.is_network() {
+        characters.clear();
+        return;
+    }
+    for event in characters.read() {
+        for character in event.char.chars() {
+            if character.is_ascii_alphanumeric() || matches!(character, '.' | ':' | '-') {
+                setup.address.push(character);
+            }
+        }
+    }
+    if keyboard_input.just_pressed(KeyCode::Backspace) {
+        setup.address.pop();
+    }
+}
+
+pub fn despawn_new_game_menu(
+    mut commands: Commands,
+    query: Query<Entity, With<crate::game::entities::NewGameMenu>>,
+) {
+    info!("despawn_new_game_menu");
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Shows the map through the fog of war picked for the game, and lets the computer play its side.
+pub fn apply_game_setup(
+    mut commands: Commands,
+    setup: Res<crate::game::resources::GameSetup>,
+    mut fog: ResMut<crate::rule::resources::FogOfWar>,
+) {
+    info!("apply_game_setup");
+    fog.view = setup.fog_view();
+    if setup.mode == crate::game::resources::GameMode::Computer {
+        commands.insert_resource(crate::ai::resources::AiPlayers::new(vec![
+            crate::ai::player::AiPlayer::new(
+                setup.side.opponent(),
+                crate::ai::player::AiProfile::default(),
+            ),
+        ]));
+    } else {
+        commands.remove_resource::<crate::ai::resources::AiPlayers>();
+    }
+}
+
+/*
+ * Starts a game of the selected scenario once it is loaded with the files it names:
+ * the units are spawned and the first turn begins, or the saved game waiting to be loaded
+ * picks up where it was left, shown for the side it was shown for, or the replay waiting to be
+ * played starts paused on its first step. Games played from here on are recorded.
+ */
+#[allow(clippy::too_many_arguments)]
+pub fn start_game(
+    mut commands: Commands,
+    turn_info: Res<crate::game::turn::TurnInfo>,
+    hex_grid: Res<crate::map::resources::HexGrid>,
+    selected_scenario: Res<crate::game::resources::SelectedScenario>,
+    scenarios: Res<Assets<crate::game::scenario::Scenario>>,
+    terrains: Res<Assets<crate::map::terrain::TerrainMap>>,
+    catalogs: Res<Assets<crate::oper::catalog::OperCatalog>>,
+    combat_tables: Res<Assets<crate::rule::combat::CombatTable>>,
+    mut pending_load: ResMut<crate::game::resources::PendingLoad>,
+    mut pending_replay: ResMut<crate::game::resources::PendingReplay>,
+    mut recording: ResMut<crate::game::resources::Recording>,
+    mut next_replay_state: ResMut<NextState<crate::game::entities::ReplayState>>,
+    asset_server: Res<AssetServer>,
+) {
+    if turn_info.phase != crate::game::entities::GamePhase::Inactive {
+        return;
+    }
+    let Some(scenario) = scenarios.get(&selected_scenario.0) else {
+        return;
+    };
+    let (Some(terrain), Some(catalog), Some(combat_table)) = (
+        terrains.get(&scenario.terrain),
+        catalogs.get(&scenario.opers),
+        combat_tables.get(&scenario.combat_table),
+    ) else {
+        return;
+    };
+    let rules = crate::rule::resources::RuleBook {
+        terrain: terrain.clone(),
+        layout: hex_grid.0,
+        combat_table: combat_table.clone(),
+        objectives: scenario.file.objectives.clone(),
+    };
+    let scenario_path = asset_server
+        .get_path(selected_scenario.0.id())
+        .map(|path| path.path().to_string_lossy().into_owned());
+    // a save is only carried on on its own scenario, another one is started afresh
+    let save = pending_load.0.take().filter(|save| {
+        let same = scenario_path.as_ref() == Some(&save.scenario);
+        if !same {
+            warn!("the saved game of {} is not carried on here", save.scenario);
+        }
+        same
+    });
+    let mut fog_view = None;
+    let session = if let Some(save) = save {
+        info!("start_game, restoring the saved game");
+        recording.0 = save.replay.clone();
+        fog_view = save.fog_view;
+        crate::game::session::GameSession::restore(save, scenario.file.clone(), rules)
+    } else if let Some(replay) = pending_replay.0.take() {
+        info!("start_game, replaying {} commands", replay.commands.len());
+        let session = crate::game::session::GameSession::new(
+            scenario.file.clone(),
+            catalog,
+            rules,
+            replay.seed,
+        );
+        commands.insert_resource(crate::game::resources::ReplayPlayer::new(
+            replay,
+            session.clone(),
+        ));
+        next_replay_state.set(crate::game::entities::ReplayState::Paused);
+        recording.0 = None;
+        session
+    } else {
+        let seed = rand::random();
+        info!("start_game, combat dice seed {}", seed);
+        recording.0 = scenario_path.map(|path| crate::game::replay::Replay::new(&path, seed));
+        crate::game::session::GameSession::new(scenario.file.clone(), catalog, rules, seed)
+    };
+    commands.add(move |world: &mut World| {
+        session.spawn(world);
+        if let Some(view) = fog_view {
+            world
+                .resource_mut::<crate::rule::resources::FogOfWar>()
+                .view = view;
+        }
+    });
+}
+
+pub fn stop_turns(
+    mut commands: Commands,
+    mut turn_info: ResMut<crate::game::turn::TurnInfo>,
+    mut next_replay_state: ResMut<NextState<crate::game::entities::ReplayState>>,
+    mut next_hand_off_state: ResMut<NextState<crate::game::entities::HandOffState>>,
+) {
+    info!("stop_turns");
+    *turn_info = crate::game::turn::TurnInfo::default();
+    commands.remove_resource::<crate::game::resources::ReplayPlayer>();
+    next_replay_state.set(crate::game::entities::ReplayState::Off);
+    next_hand_off_state.set(crate::game::entities::HandOffState::Off);
+}
+
+/*
+ * In hot-seat games, hides the map behind the hand-off screen whenever the side to play changes,
+ * the first side of a game included.
+ * Until the next player is ready the map stays in the fog of the side that ended, so that nothing
+ * of what the next side sees shows behind the screen, or in the frame before it comes up.
+ */
+pub fn start_hand_off(
+    setup: Res<crate::game::resources::GameSetup>,
+    turn_info: Res<crate::game::turn::TurnInfo>,
+    replay_state: Res<State<crate::game::entities::ReplayState>>,
+    mut fog: ResMut<crate::rule::resources::FogOfWar>,
+    mut last_side: Local<Option<crate::oper::components::Side>>,
+    mut next_state: ResMut<NextState<crate::game::entities::HandOffState>>,
+) {
+    if matches!(
+        turn_info.phase,
+        crate::game::entities::GamePhase::Inactive | crate::game::entities::GamePhase::GameOver
+    ) {
+        *last_side = None;
+        return;
+    }
+    if *last_side == Some(turn_info.side) {
+        return;
+    }
+    let ended = last_side.replace(turn_info.side);
+    if setup.mode == crate::game::resources::GameMode::HotSeat
+        && *replay_state.get() == crate::game::entities::ReplayState::Off
+    {
+        fog.view =
+            crate::rule::fog::FogView::Side(ended.unwrap_or_else(|| turn_info.side.opponent()));
+        next_state.set(crate::game::entities::HandOffState::Waiting);
+    }
+}
+
+/// The hand-off screen covers the whole window and takes every click until the player is ready.
+pub fn hand_off_screen(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    turn_info: Res<crate::game::turn::TurnInfo>,
+) {
+    info!("hand_off_screen");
+    let text_style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 40.,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(20.),
+                    ..default()
+                },
+                background_color: Color::rgb(0.05, 0.05, 0.05).into(),
+                focus_policy: bevy::ui::FocusPolicy::Block,
+                z_index: ZIndex::Global(100),
+                ..default()
+            },
+            crate::game::entities::HandOffScreen,
+            crate::map::entities::MapMenu,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    format!(
+                        "{:?} player, your turn\nTurn {} of {}\n\n{:?} player, please look away",
+                        turn_info.side,
+                        turn_info.turn,
+                        turn_info.turn_limit,
+                        turn_info.side.opponent()
+                    ),
+                    text_style.clone(),
+                )
+                .with_text_justify(JustifyText::Center),
+                crate::map::entities::MapMenu,
+            ));
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(200.),
+                            height: Val::Px(65.),
+                            border: UiRect::all(Val::Px(5.)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        border_color: BorderColor(Color::BLACK),
+                        background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                        ..default()
+                    },
+                    crate::game::entities::HandOffButton,
+                    crate::map::entities::MapMenu,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextBundle::from_section("Ready", text_style),
+                        crate::map::entities::MapMenu,
+                    ));
+                });
+        });
+}
+
+/// Enter or the "Ready" button shows the map to the player whose turn it is.
+pub fn end_hand_off(
+    setup: Res<crate::game::resources::GameSetup>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    interaction_query: Query<
+        &Interaction,
+        (
+            Changed<Interaction>,
+            With<crate::game::entities::HandOffButton>,
+        ),
+    >,
+    mut fog: ResMut<crate::rule::resources::FogOfWar>,
+    mut next_state: ResMut<NextState<crate::game::entities::HandOffState>>,
+) {
+    if keyboard.just_pressed(KeyCode::Enter)
+        || interaction_query
+            .iter()
+            .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        // the side to play takes over the map
+        fog.view = setup.fog_view();
+        next_state.set(crate::game::entities::HandOffState::Off);
+    }
+}
+
+pub fn despawn_hand_off_screen(
+    mut commands: Commands,
+    query: Query<Entity, With<crate::game::entities::HandOffScreen>>,
+) {
+    info!("despawn_hand_off_screen");
+    for entity in query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Mirrors the phase recorded in `TurnInfo` into the `GamePhase` state.
+/// The side to play gives its orders on this machine: always, except in network games.
+pub fn local_turn(
+    setup: Res<crate::game::resources::GameSetup>,
+    turn_info: Res<crate::game::turn::TurnInfo>,
+) -> bool {
+    setup.plays(turn_info.side)
+}
+
+pub fn sync_game_phase(
+    turn_info: Res<crate::game::turn::TurnInfo>,
+    state: Res<State<crate::game::entities::GamePhase>>,
+    mut next_state: ResMut<NextState<crate::game::entities::GamePhase>>,
+) {
+    if *state.get() != turn_info.phase {
+        next_state.set(turn_info.phase);
+    }
+}
+
+/*
+ * The turn panel sits in the top right corner of the map:
+ * the turn, the side to play and its phase, a button to end the phase and the save/load buttons.
+ */
+pub fn turn_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+    info!("turn_hud");
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(10.),
+                    right: Val::Px(10.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::End,
+                    row_gap: Val::Px(10.),
+                    ..default()
+                },
+                ..default()
+            },
+            crate::map::entities::MapMenu,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                        font_size: 24.,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_text_justify(JustifyText::Right),
+                crate::game::entities::TurnText,
+                crate::map::entities::MapMenu,
+            ));
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(200.),
+                            height: Val::Px(50.),
+                            border: UiRect::all(Val::Px(5.)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        border_color: BorderColor(Color::BLACK),
+                        background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                        ..default()
+                    },
+                    crate::game::entities::EndPhaseButton,
+                    crate::map::entities::MapMenu,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextBundle::from_section(
+                            "End phase",
+                            TextStyle {
+                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                font_size: 32.,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                            },
+                        ),
+                        crate::map::entities::MapMenu,
+                    ));
+                });
+            parent
+                .spawn((
+                    NodeBundle {
+                        style: Style {
+                            column_gap: Val::Px(10.),
+                            ..default()
+                        },
+                        ..default()
+                    },
+                    crate::map::entities::MapMenu,
+                ))
+                .with_children(|parent| {
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    width: Val::Px(95.),
+                                    height: Val::Px(40.),
+                                    border: UiRect::all(Val::Px(5.)),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                },
+                                border_color: BorderColor(Color::BLACK),
+                                background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                                ..default()
+                            },
+                            crate::game::entities::SaveButton,
+                            crate::map::entities::MapMenu,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn((
+                                TextBundle::from_section(
+                                    "Save",
+                                    TextStyle {
+                                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                        font_size: 24.,
+                                        color: Color::rgb(0.9, 0.9, 0.9),
+                                    },
+                                ),
+                                crate::map::entities::MapMenu,
+                            ));
+                        });
+                    parent
+                        .spawn((
+                            ButtonBundle {
+                                style: Style {
+                                    width: Val::Px(95.),
+                                    height: Val::Px(40.),
+                                    border: UiRect::all(Val::Px(5.)),
+                                    justify_content: JustifyContent::Center,
+                                    align_items: AlignItems::Center,
+                                    ..default()
+                                },
+                                border_color: BorderColor(Color::BLACK),
+                                background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                                ..default()
+                            },
+                            crate::game::entities::LoadButton,
+                            crate::map::entities::MapMenu,
+                        ))
+                        .with_children(|parent| {
+                            parent.spawn((
+                                TextBundle::from_section(
+                                    "Load",
+                                    TextStyle {
+                                        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                        font_size: 24.,
+                                        color: Color::rgb(0.9, 0.9, 0.9),
+                                    },
+                                ),
+                                crate::map::entities::MapMenu,
+                            ));
+                        });
+                });
+        });
+}
+
+pub fn update_turn_text(
+    turn_info: Res<crate::game::turn::TurnInfo>,
+    selected_scenario: Res<crate::game::resources::SelectedScenario>,
+    scenarios: Res<Assets<crate::game::scenario::Scenario>>,
+    mut query_text: Query<&mut Text, With<crate::game::entities::TurnText>>,
+) {
+    let Some(scenario) = scenarios.get(&selected_scenario.0) else {
+        return;
+    };
+    let label = match turn_info.phase {
+        crate::game::entities::GamePhase::Inactive => "".to_string(),
+        crate::game::entities::GamePhase::GameOver => {
+            format!(
+                "Turn {}/{}\nGame over",
+                turn_info.turn, turn_info.turn_limit
+            )
+        }
+        phase => format!(
+            "Turn {}/{}\n{} to play\n{:?} phase",
+            turn_info.turn,
+            turn_info.turn_limit,
+            scenario.file.side_name(turn_info.side),
+            phase
+        ),
+    };
+    for mut text in query_text.iter_mut() {
+        if text.sections[0].value != label {
+            text.sections[0].value = label.clone();
+        }
+    }
+}
+
+/// Hover and press colours of the map buttons.
+#[allow(clippy::type_complexity)]
+pub fn hud_button_colors(
+    mut interaction_query: Query<
+        (&Interaction, &mut BackgroundColor, &mut BorderColor),
+        (Changed<Interaction>, With<Button>),
+    >,
+) {
+    for (interaction, mut color, mut border_color) in interaction_query.iter_mut() {
+        match *interaction {
+            Interaction::Pressed => {
+                *color = Color::rgb(0.35, 0.75, 0.35).into();
+            }
+            Interaction::Hovered => {
+                *color = Color::rgb(0.25, 0.25, 0.25).into();
+                border_color.0 = Color::WHITE;
+            }
+            Interaction::None => {
+                *color = Color::rgb(0.15, 0.15, 0.15).into();
+                border_color.0 = Color::BLACK;
+            }
+        }
+    }
+}
+
+/// Space or the "End phase" button ends the current phase.
+pub fn end_phase_system(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    interaction_query: Query<
+        &Interaction,
+        (
+            Changed<Interaction>,
+            With<crate::game::entities::EndPhaseButton>,
+        ),
+    >,
+    mut game_commands: EventWriter<crate::rule::commands::GameCommand>,
+) {
+    if keyboard.just_pressed(KeyCode::Space)
+        || interaction_query
+            .iter()
+            .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        game_commands.send(crate::rule::commands::GameCommand::EndPhase);
+    }
+}
+
+/// F5 or the "Save" button writes the game in progress, and how it is played, to `save_path`.
+#[allow(clippy::too_many_arguments)]
+pub fn save_game_system(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    interaction_query: Query<
+        &Interaction,
+        (
+            Changed<Interaction>,
+            With<crate::game::entities::SaveButton>,
+        ),
+    >,
+    asset_server: Res<AssetServer>,
+    selected_scenario: Res<crate::game::resources::SelectedScenario>,
+    turn_info: Res<crate::game::turn::TurnInfo>,
+    rng: Res<crate::rule::resources::GameRng>,
+    combat_log: Res<crate::rule::resources::CombatLog>,
+    recording: Res<crate::game::resources::Recording>,
+    fog: Res<crate::rule::resources::FogOfWar>,
+    setup: Res<crate::game::resources::GameSetup>,
+    query_oper: Query<crate::game::session::UnitComponents>,
+) {
+    if !keyboard.just_pressed(KeyCode::F5)
+        && !interaction_query
+            .iter()
+            .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+    let Some(scenario) = asset_server.get_path(selected_scenario.0.id()) else {
+        return;
+    };
+    if turn_info.phase == crate::game::entities::GamePhase::Inactive {
+        return;
+    }
+    let (_, units) = crate::game::session::units_from_world(query_oper.iter());
+    let save = crate::game::save::SaveGame {
+        scenario: scenario.path().to_string_lossy().into_owned(),
+        turn_info: turn_info.clone(),
+        rng: rng.clone(),
+        units,
+        combat_log: combat_log.0.clone(),
+        intel: fog.intel.clone(),
+        replay: recording.0.clone(),
+        setup: Some(setup.clone()),
+        fog_view: Some(fog.view),
+    };
+    let path = crate::game::resources::save_path();
+    match save.write(&path) {
+        Ok(()) => info!("game saved to {}", path.display()),
+        Err(error) => warn!("{}", error),
+    }
+}
+
+/*
+ * F9 or a "Load" button reads `save_path` and plays it again: the saved scenario is selected,
+ * and the game goes back through the main menu to set up the map from scratch,
+ * where `start_game` restores the saved state and the options it was played with.
+ * A save of a scenario missing from the asset folder is not loaded.
+ */
+#[allow(clippy::too_many_arguments)]
+pub fn load_game_system(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    interaction_query: Query<
+        &Interaction,
+        (
+            Changed<Interaction>,
+            With<crate::game::entities::LoadButton>,
+        ),
+    >,
+    asset_server: Res<AssetServer>,
+    asset_folder: Res<crate::editor::resources::AssetFolder>,
+    mut selected_scenario: ResMut<crate::game::resources::SelectedScenario>,
+    mut pending_load: ResMut<crate::game::resources::PendingLoad>,
+    mut setup: ResMut<crate::game::resources::GameSetup>,
+    mut next_state: ResMut<NextState<crate::MyAppState>>,
+) {
+    if !keyboard.just_pressed(KeyCode::F9)
+        && !interaction_query
+            .iter()
+            .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+    let path = crate::game::resources::save_path();
+    match crate::game::save::SaveGame::read(&path) {
+        Ok(save) if !asset_folder.0.join(&save.scenario).is_file() => {
+            warn!(
+                "{} is a game of {}, not found",
+                path.display(),
+                save.scenario
+            );
+        }
+        Ok(save) => {
+            info!("loading {}", path.display());
+            if let Some(saved) = &save.setup {
+                *setup = saved.clone();
+            }
+            selected_scenario.0 = asset_server.load(save.scenario.clone());
+            pending_load.0 = Some(save);
+            next_state.set(crate::MyAppState::MainMenu);
+        }
+        Err(error) => warn!("{}", error),
+    }
+}
+
+/// Carries on to the map when a saved game or a replay is waiting to be loaded.
+pub fn resume_saved_game(
+    pending_load: Res<crate::game::resources::PendingLoad>,
+    pending_replay: Res<crate::game::resources::PendingReplay>,
+    mut next_state: ResMut<NextState<crate::MyAppState>>,
+) {
+    if pending_load.0.is_some() || pending_replay.0.is_some() {
+        next_state.set(crate::MyAppState::MapMenu);
+        info!("AppState::MapMenu");
+    }
+}
+
+/// Adds the commands given on the map to the recording of the game.
+pub fn record_game_commands(
+    mut game_commands: EventReader<crate::rule::commands::GameCommand>,
+    mut recording: ResMut<crate::game::resources::Recording>,
+) {
+    let Some(replay) = recording.0.as_mut() else {
+        game_commands.clear();
+        return;
+    };
+    replay.commands.extend(game_commands.read().cloned());
+}
+
+/// Writes the recording of the game to `REPLAY_PATH`, when the game ends or the map is left.
+pub fn write_replay(
+    recording: Res<crate::game::resources::Recording>,
+    replay_state: Res<State<crate::game::entities::ReplayState>>,
+) {
+    if *replay_state.get() != crate::game::entities::ReplayState::Off {
+        return;
+    }
+    let Some(replay) = recording.0.as_ref() else {
+        return;
+    };
+    if replay.commands.is_empty() {
+        return;
+    }
+    match replay.write(crate::game::resources::REPLAY_PATH) {
+        Ok(()) => info!(
+            "{} commands recorded to {}",
+            replay.commands.len(),
+            crate::game::resources::REPLAY_PATH
+        ),
+        Err(error) => warn!("{}", error),
+    }
+}
+
+/// F10 or the "Replay" button replays the last game recorded, the same way a game is loaded.
+pub fn load_replay_system(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    interaction_query: Query<
+        &Interaction,
+        (
+            Changed<Interaction>,
+            With<crate::game::entities::ReplayButton>,
+        ),
+    >,
+    asset_server: Res<AssetServer>,
+    mut selected_scenario: ResMut<crate::game::resources::SelectedScenario>,
+    mut pending_replay: ResMut<crate::game::resources::PendingReplay>,
+    mut next_state: ResMut<NextState<crate::MyAppState>>,
+) {
+    if !keyboard.just_pressed(KeyCode::F10)
+        && !interaction_query
+            .iter()
+            .any(|interaction| *interaction == Interaction::Pressed)
+    {
+        return;
+    }
+    match crate::game::replay::Replay::read(crate::game::resources::REPLAY_PATH) {
+        Ok(replay) => {
+            info!("replaying {}", crate::game::resources::REPLAY_PATH);
+            selected_scenario.0 = asset_server.load(replay.scenario.clone());
+            pending_replay.0 = Some(replay);
+            next_state.set(crate::MyAppState::MainMenu);
+        }
+        Err(error) => warn!("{}", error),
+    }
+}
+
+/*
+ * The replay panel sits at the bottom of the map: go to the start, one step back, play/pause,
+ * one step forward, go to the end, then the progress bar and the step shown.
+ */
+pub fn replay_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+    info!("replay_hud");
+    let text_style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 24.,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(10.),
+                    left: Val::Percent(20.),
+                    width: Val::Percent(60.),
+                    align_items: AlignItems::Center,
+                    column_gap: Val::Px(10.),
+                    padding: UiRect::all(Val::Px(5.)),
+                    ..default()
+                },
+                background_color: Color::rgba(0., 0., 0., 0.5).into(),
+                ..default()
+            },
+            crate::map::entities::MapMenu,
+        ))
+        .with_children(|parent| {
+            for (control, label) in [
+                (crate::game::entities::ReplayControl::Start, "|<"),
+                (crate::game::entities::ReplayControl::Back, "<"),
+                (crate::game::entities::ReplayControl::PlayPause, "Play"),
+                (crate::game::entities::ReplayControl::Step, ">"),
+                (crate::game::entities::ReplayControl::End, ">|"),
+            ] {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(
+                                    if control == crate::game::entities::ReplayControl::PlayPause {
+                                        80.
+                                    } else {
+                                        45.
+                                    },
+                                ),
+                                height: Val::Px(40.),
+                                border: UiRect::all(Val::Px(5.)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            border_color: BorderColor(Color::BLACK),
+                            background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                            ..default()
+                        },
+                        control,
+                        crate::map::entities::MapMenu,
+                    ))
+                    .with_children(|parent| {
+                        let mut text = parent.spawn((
+                            TextBundle::from_section(label, text_style.clone()),
+                            crate::map::entities::MapMenu,
+                        ));
+                        if control == crate::game::entities::ReplayControl::PlayPause {
+                            text.insert(crate::game::entities::ReplayPlayLabel);
+                        }
+                    });
+            }
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            flex_grow: 1.,
+                            height: Val::Px(16.),
+                            ..default()
+                        },
+                        background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                        ..default()
+                    },
+                    bevy::ui::RelativeCursorPosition::default(),
+                    crate::game::entities::ReplaySeekBar,
+                    crate::map::entities::MapMenu,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        NodeBundle {
+                            style: Style {
+                                width: Val::Percent(0.),
+                                height: Val::Percent(100.),
+                                ..default()
+                            },
+                            background_color: Color::rgb(0.35, 0.75, 0.35).into(),
+                            ..default()
+                        },
+                        crate::game::entities::ReplaySeekFill,
+                        crate::map::entities::MapMenu,
+                    ));
+                });
+            parent.spawn((
+                TextBundle::from_section("", text_style),
+                crate::game::entities::ReplayText,
+                crate::map::entities::MapMenu,
+            ));
+        });
+}
+
+/*
+ * Replay controls, on the panel buttons or the keyboard: P play/pause, "." one step forward,
+ * "," one step back, Home and End to the start and the end. A click on the progress bar seeks
+ * to that point of the game.
+ */
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+pub fn replay_controls(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    control_query: Query<
+        (&Interaction, &crate::game::entities::ReplayControl),
+        Changed<Interaction>,
+    >,
+    seek_query: Query<
+        (&Interaction, &bevy::ui::RelativeCursorPosition),
+        (
+            Changed<Interaction>,
+            With<crate::game::entities::ReplaySeekBar>,
+        ),
+    >,
+    mut player: ResMut<crate::game::resources::ReplayPlayer>,
+    replay_state: Res<State<crate::game::entities::ReplayState>>,
+    mut next_replay_state: ResMut<NextState<crate::game::entities::ReplayState>>,
+    mut game_commands: EventWriter<crate::rule::commands::GameCommand>,
+) {
+    use crate::game::entities::{ReplayControl, ReplayState};
+    let mut controls: Vec<ReplayControl> = [
+        (KeyCode::KeyP, ReplayControl::PlayPause),
+        (KeyCode::Period, ReplayControl::Step),
+        (KeyCode::Comma, ReplayControl::Back),
+        (KeyCode::Home, ReplayControl::Start),
+        (KeyCode::End, ReplayControl::End),
+    ]
+    .into_iter()
+    .filter(|(key, _)| keyboard.just_pressed(*key))
+    .map(|(_, control)| control)
+    .collect();
+    controls.extend(
+        control_query
+            .iter()
+            .filter(|(interaction, _)| **interaction == Interaction::Pressed)
+            .map(|(_, control)| *control),
+    );
+    for control in controls {
+        match control {
+            ReplayControl::PlayPause => {
+                if *replay_state.get() == ReplayState::Playing {
+                    next_replay_state.set(ReplayState::Paused);
+                } else {
+                    if player.is_at_end() {
+                        player.seek = Some(0);
+                    }
+                    player.timer.reset();
+                    next_replay_state.set(ReplayState::Playing);
+                }
+            }
+            ReplayControl::Step => {
+                next_replay_state.set(ReplayState::Paused);
+                if let Some(command) = player.step() {
+                    game_commands.send(command);
+                }
+            }
+            ReplayControl::Back => {
+                next_replay_state.set(ReplayState::Paused);
+                player.seek = Some(player.next.saturating_sub(1));
+            }
+            ReplayControl::Start => player.seek = Some(0),
+            ReplayControl::End => player.seek = Some(player.len()),
+        }
+    }
+    for (interaction, cursor) in seek_query.iter() {
+        if let (Interaction::Pressed, Some(position)) = (interaction, cursor.normalized) {
+            let step = (position.x.clamp(0., 1.) * player.len() as f32).round() as usize;
+            player.seek = Some(step);
+        }
+    }
+}
+
+/// Carries out the commands of a playing replay one at a time, and pauses at its end.
+pub fn play_replay(
+    time: Res<Time>,
+    mut player: ResMut<crate::game::resources::ReplayPlayer>,
+    mut next_replay_state: ResMut<NextState<crate::game::entities::ReplayState>>,
+    mut game_commands: EventWriter<crate::rule::commands::GameCommand>,
+) {
+    if player.seek.is_some() || !player.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    match player.step() {
+        Some(command) => {
+            game_commands.send(command);
+        }
+        None => next_replay_state.set(crate::game::entities::ReplayState::Paused),
+    }
+}
+
+/*
+ * Seeks the replay: the game is set back to its start, the commands up to the step sought are
+ * played on a headless session and the units on the map are replaced by the session's.
+ */
+pub fn seek_replay(world: &mut World) {
+    let Some(step) = world
+        .get_resource_mut::<crate::game::resources::ReplayPlayer>()
+        .and_then(|mut player| player.seek.take())
+    else {
+        return;
+    };
+    let mut player = world.resource_mut::<crate::game::resources::ReplayPlayer>();
+    let step = step.min(player.len());
+    player.next = step;
+    player.timer.reset();
+    let mut session = player.start.clone();
+    player.replay.play(&mut session, step);
+    info!("seek_replay, step {}", step);
+
+    // commands sent but not carried out yet belong to the step left behind
+    world
+        .resource_mut::<Events<crate::rule::commands::GameCommand>>()
+        .clear();
+    let opers: Vec<Entity> = world
+        .query_filtered::<Entity, With<crate::oper::components::Oper>>()
+        .iter(world)
+        .collect();
+    for entity in opers {
+        world.entity_mut(entity).despawn_recursive();
+    }
+    *world.resource_mut::<crate::rule::resources::OperSelection>() =
+        crate::rule::resources::OperSelection::default();
+    session.spawn(world);
+}
+
+/// Shows the step of the replay on its panel.
+#[allow(clippy::type_complexity)]
+pub fn update_replay_text(
+    player: Res<crate::game::resources::ReplayPlayer>,
+    replay_state: Res<State<crate::game::entities::ReplayState>>,
+    mut query_text: Query<
+        (&mut Text, Has<crate::game::entities::ReplayPlayLabel>),
+        Or<(
+            With<crate::game::entities::ReplayText>,
+            With<crate::game::entities::ReplayPlayLabel>,
+        )>,
+    >,
+    mut query_fill: Query<&mut Style, With<crate::game::entities::ReplaySeekFill>>,
+) {
+    let step = format!("{} / {}", player.next, player.len());
+    let play = match replay_state.get() {
+        crate::game::entities::ReplayState::Playing => "Pause",
+        _ => "Play",
+    };
+    for (mut text, is_play_label) in query_text.iter_mut() {
+        let label = if is_play_label { play } else { step.as_str() };
+        if text.sections[0].value != label {
+            text.sections[0].value = label.to_string();
+        }
+    }
+    let progress = if player.is_empty() {
+        100.
+    } else {
+        100. * player.next as f32 / player.len() as f32
+    };
+    for mut style in query_fill.iter_mut() {
+        style.width = Val::Percent(progress);
+    }
+}
+
 pub fn game_setup(mut commands: Commands, loader: Res<AssetServer>) {
     commands.spawn((
         SpatialBundle {
@@ -161,7 +1423,7 @@ pub fn render_volumes(
 
 pub fn update_text(
     mut text: Query<&mut Text>,
-    cur_state: Res<State<crate::game::entities::GameState>>,
+    cur_state: Res<State<crate::game::entities::IntersectionTest>>,
 ) {
     // if !cur_state.is_changed() {
     //     return;
@@ -172,7 +1434,7 @@ pub fn update_text(
     text.clear();
 
     text.push_str("Intersection test:\n");
-    use crate::game::entities::GameState::*;
+    use crate::game::entities::IntersectionTest::*;
     for &state in &[AabbSweep, CircleSweep, RayCast, AabbCast, CircleCast] {
         let s = if **cur_state == state { "*" } else { " " };
         text.push_str(&format!(" {s} {state:?} {s}\n"));
@@ -255,14 +1517,14 @@ pub fn update_volumes(
 
 pub fn update_test_state(
     keycode: Res<ButtonInput<KeyCode>>,
-    cur_state: Res<State<crate::game::entities::GameState>>,
-    mut state: ResMut<NextState<crate::game::entities::GameState>>,
+    cur_state: Res<State<crate::game::entities::IntersectionTest>>,
+    mut state: ResMut<NextState<crate::game::entities::IntersectionTest>>,
 ) {
     if !keycode.just_pressed(KeyCode::Space) {
         return;
     }
     info!("update_test_state");
-    use crate::game::entities::GameState::*;
+    use crate::game::entities::IntersectionTest::*;
     let next = match **cur_state {
         AabbSweep => CircleSweep,
         CircleSweep => RayCast,
@@ -0,0 +1,73 @@
+// Turn sequence: whose turn it is and which phase of it is being played
+
+use crate::game::entities::GamePhase;
+use crate::oper::components::Side;
+
+/*
+ * A game is played in turns, up to the scenario's turn limit.
+ * In every turn each side plays its own movement, shooting, close combat and end phases,
+ * the scenario's first side before the other:
+ *
+ *   turn 1: Red Movement -> Red Shooting -> Red CloseCombat -> Red End
+ *        -> Blue Movement -> ... -> Blue End
+ *   turn 2: Red Movement -> ...
+ *
+ * The game is over once the last side has finished the last turn.
+ * `TurnInfo` is the authoritative record of the turn sequence, the `GamePhase` state only mirrors
+ * its phase so systems can be gated with `run_if(in_state(..))`.
+ */
+#[derive(
+    bevy::ecs::system::Resource,
+    Clone,
+    Debug,
+    Default,
+    PartialEq,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct TurnInfo {
+    /// Current turn, starting at 1.
+    pub turn: u32,
+    pub turn_limit: u32,
+    /// Side that plays first in every turn.
+    pub first_side: Side,
+    /// Side whose phase is being played.
+    pub side: Side,
+    pub phase: GamePhase,
+}
+
+impl TurnInfo {
+    pub fn new(first_side: Side, turn_limit: u32) -> Self {
+        TurnInfo {
+            turn: 1,
+            turn_limit,
+            first_side,
+            side: first_side,
+            phase: GamePhase::Movement,
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.phase == GamePhase::GameOver
+    }
+
+    /// Ends the current phase and moves on to the next one, or to the next side or turn.
+    pub fn next_phase(&mut self) {
+        self.phase = match self.phase {
+            GamePhase::Inactive | GamePhase::GameOver => return,
+            GamePhase::Movement => GamePhase::Shooting,
+            GamePhase::Shooting => GamePhase::CloseCombat,
+            GamePhase::CloseCombat => GamePhase::End,
+            GamePhase::End if self.side == self.first_side => {
+                self.side = self.side.opponent();
+                GamePhase::Movement
+            }
+            GamePhase::End if self.turn >= self.turn_limit => GamePhase::GameOver,
+            GamePhase::End => {
+                self.turn += 1;
+                self.side = self.first_side;
+                GamePhase::Movement
+            }
+        };
+    }
+}
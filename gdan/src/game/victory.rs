@@ -0,0 +1,69 @@
+// Victory: the points each side scores and who wins
+
+use crate::oper::components::Side;
+
+/*
+ * Each side scores the points of the objectives it holds, plus one point for every strength
+ * point it destroyed. An objective is held by the side with a unit still in the game standing on
+ * it, or else by the side holding it when the game started. The side with more points wins;
+ * equal points are a draw.
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Score {
+    pub red: u32,
+    pub blue: u32,
+}
+
+impl Score {
+    pub fn get(&self, side: Side) -> u32 {
+        match side {
+            Side::Red => self.red,
+            Side::Blue => self.blue,
+        }
+    }
+
+    fn add(&mut self, side: Side, points: u32) {
+        match side {
+            Side::Red => self.red += points,
+            Side::Blue => self.blue += points,
+        }
+    }
+
+    /// The side ahead on points, `None` on a draw.
+    pub fn leader(&self) -> Option<Side> {
+        match self.red.cmp(&self.blue) {
+            std::cmp::Ordering::Greater => Some(Side::Red),
+            std::cmp::Ordering::Less => Some(Side::Blue),
+            std::cmp::Ordering::Equal => None,
+        }
+    }
+}
+
+/// Side holding `objective`, see the module comment.
+pub fn holder(
+    objective: &crate::game::scenario::Objective,
+    units: &[crate::game::session::Unit],
+) -> Option<Side> {
+    units
+        .iter()
+        .find(|unit| unit.hex == objective.hex() && !unit.oper.is_destroyed())
+        .map(|unit| unit.oper.side)
+        .or(objective.owner)
+}
+
+pub fn score(
+    objectives: &[crate::game::scenario::Objective],
+    units: &[crate::game::session::Unit],
+) -> Score {
+    let mut score = Score::default();
+    for objective in objectives.iter() {
+        if let Some(side) = holder(objective, units) {
+            score.add(side, objective.points);
+        }
+    }
+    for unit in units.iter() {
+        let lost = (unit.oper.max_strength - unit.oper.strength).max(0) as u32;
+        score.add(unit.oper.side.opponent(), lost);
+    }
+    score
+}
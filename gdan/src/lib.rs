@@ -0,0 +1,43 @@
+// The game as a library: everything but the window and its menus, so that games can be
+// simulated and tested without a display
+
+pub mod ai;
+pub mod cli;
+pub mod editor;
+pub mod game;
+pub mod map;
+pub mod net;
+pub mod oper;
+pub mod rule;
+pub mod scene;
+pub mod tools;
+
+use bevy::prelude::*;
+
+// We can create our own gizmo config group!
+#[derive(Default, Reflect, GizmoConfigGroup)]
+pub struct MyRoundGizmos {}
+
+#[derive(
+    bevy::ecs::schedule::States, clap::ValueEnum, Default, Debug, Clone, PartialEq, Eq, Hash,
+)]
+pub enum MyAppState {
+    MainMenu,
+    MapMenu,
+    #[value(name = "map3d")]
+    Map3D,
+    OperMenu,
+    #[value(name = "oper3d")]
+    Oper3D,
+    RuleMenu,
+    SceneMenu,
+    #[default]
+    #[value(name = "scene3d")]
+    Scene3D,
+    GameMenu,
+    GameSetup,
+    MapEditor,
+    // entered from the new game screen only, with the network mode chosen there
+    #[value(skip)]
+    Lobby,
+}
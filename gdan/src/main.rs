@@ -1,33 +1,11 @@
-pub mod game;
-pub mod map;
-pub mod oper;
-pub mod rule;
-pub mod scene;
-pub mod tools;
+use gdan::{ai, cli, editor, game, map, net, oper, rule, scene, tools};
+use gdan::{MyAppState, MyRoundGizmos};
 
 use bevy::prelude::*;
 
 #[derive(bevy::ecs::component::Component)]
 pub struct MainMenu;
 
-// We can create our own gizmo config group!
-#[derive(Default, Reflect, GizmoConfigGroup)]
-pub struct MyRoundGizmos {}
-
-#[derive(bevy::ecs::schedule::States, Default, Debug, Clone, PartialEq, Eq, Hash)]
-pub enum MyAppState {
-    MainMenu,
-    MapMenu,
-    Map3D,
-    OperMenu,
-    Oper3D,
-    RuleMenu,
-    SceneMenu,
-    #[default]
-    Scene3D,
-    GameMenu,
-}
-
 /*
  * All app logic in Bevy uses the Entity Component System paradigm, which is often shortened to ECS.
  * ECS is a software pattern that involves breaking your program up into Entities, Components, and Systems.
@@ -37,6 +15,25 @@ pub enum MyAppState {
  * Bevy ECS uses normal Rust datatypes for all of these concepts.
  */
 fn main() {
+    let cli: crate::cli::Cli = clap::Parser::parse();
+    if let Some(command) = cli.command {
+        let result = match command {
+            crate::cli::Command::Simulate(args) => crate::cli::simulate(args),
+            crate::cli::Command::Tile(args) => crate::cli::tile(args),
+        };
+        if let Err(error) = result {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let save = match cli.launch.read_save() {
+        Ok(save) => save,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
+        }
+    };
     /*
      * what sort of data does our App really store?
      * Looking at the docs linked, we find three fields: world, schedule, and runner.
@@ -45,7 +42,19 @@ fn main() {
      * The runner interprets the schedule to control the broad execution strategy.
      */
     App::new()
-        .add_plugins(bevy::DefaultPlugins.set(ImagePlugin::default_nearest()))
+        .add_plugins(
+            bevy::DefaultPlugins
+                .set(ImagePlugin::default_nearest())
+                .set(bevy::window::WindowPlugin {
+                    primary_window: Some(cli.launch.window()),
+                    ..Default::default()
+                })
+                .set(bevy::asset::AssetPlugin {
+                    file_path: cli.launch.assets.clone(),
+                    ..Default::default()
+                }),
+        )
+        .add_plugins(crate::rule::plugin::RulesPlugin)
         .insert_resource(crate::map::resources::GreetTimer(
             bevy::time::Timer::from_seconds(5.0, bevy::time::TimerMode::Repeating),
         ))
@@ -53,11 +62,70 @@ fn main() {
         // Specify the initial value:
         // .insert_state(MyAppState::Main)
         // Or use the default (if the type impls Default):
-        .init_state::<MyAppState>()
-        .init_state::<crate::game::entities::GameState>()
+        // .init_state::<MyAppState>()
+        .insert_state(cli.launch.initial_state())
+        .init_state::<crate::game::entities::IntersectionTest>()
+        .init_state::<crate::game::entities::ReplayState>()
+        .init_state::<crate::game::entities::HandOffState>()
         .init_gizmo_group::<crate::MyRoundGizmos>()
+        .init_resource::<crate::map::resources::MapInfo>()
+        .init_resource::<crate::map::resources::HexGrid>()
+        .init_resource::<crate::map::resources::HexOverlay>()
+        .init_gizmo_group::<crate::map::resources::HexGridGizmos>()
+        .init_resource::<crate::map::resources::HoveredHex>()
+        .init_resource::<crate::map::resources::SelectedHex>()
+        .init_resource::<crate::map::resources::MapCameraControls>()
+        .add_event::<crate::map::events::HexClicked>()
+        .init_resource::<crate::rule::resources::OperSelection>()
+        .init_resource::<crate::rule::resources::LosDrag>()
+        .init_asset::<crate::map::terrain::TerrainMap>()
+        .init_asset_loader::<crate::map::terrain::TerrainMapLoader>()
+        .init_resource::<crate::map::resources::MapImage>()
+        .init_resource::<crate::map::resources::MapTerrain>()
+        .init_resource::<crate::map::resources::MapTiles>()
+        .init_asset::<crate::map::tiles::TilePyramid>()
+        .init_asset_loader::<crate::map::tiles::TilePyramidLoader>()
+        .init_asset::<crate::map::terrain3d::Heightmap>()
+        .init_asset_loader::<crate::map::terrain3d::HeightmapLoader>()
+        .insert_resource(crate::game::resources::StartScenario(
+            cli.launch
+                .scenario(save.as_ref())
+                .unwrap_or(crate::game::resources::DEFAULT_SCENARIO.to_string()),
+        ))
+        // a save given on the command line is played on as it was set up
+        .insert_resource::<crate::game::resources::GameSetup>(
+            save.as_ref()
+                .and_then(|save| save.setup.clone())
+                .unwrap_or_default(),
+        )
+        .insert_resource(crate::game::resources::PendingLoad(save))
+        .init_resource::<crate::game::resources::PendingReplay>()
+        .init_resource::<crate::game::resources::Recording>()
+        // the asset folder as the asset server finds it, next to the executable or the manifest
+        .insert_resource(crate::editor::resources::AssetFolder(
+            bevy::asset::io::file::FileAssetReader::get_base_path().join(&cli.launch.assets),
+        ))
+        .init_asset::<crate::game::scenario::Scenario>()
+        .init_asset_loader::<crate::game::scenario::ScenarioLoader>()
+        .init_asset::<crate::oper::catalog::OperCatalog>()
+        .init_asset_loader::<crate::oper::catalog::OperCatalogLoader>()
+        .init_asset::<crate::rule::combat::CombatTable>()
+        .init_asset_loader::<crate::rule::combat::CombatTableLoader>()
         // .add_systems(Startup, ().chain())
+        .add_systems(Startup, (crate::game::systems::load_scenarios,).chain())
         .add_systems(Update, close_on_esc)
+        /*
+         * The hex grid is shared by every state, so units and rules can use hex coordinates anywhere.
+         */
+        .add_systems(
+            Update,
+            (
+                crate::map::systems::sync_hex_grid
+                    .run_if(resource_changed::<crate::map::resources::MapInfo>),
+                crate::map::systems::hex_position_to_transform,
+            )
+                .chain(),
+        )
         /*
          * MainMenu
          * Note that we have used .chain() on the systems.
@@ -69,11 +137,111 @@ fn main() {
         )
         .add_systems(
             Update,
-            (w_game_system,)
+            (
+                w_game_system,
+                crate::game::systems::scenario_picker_system,
+                crate::game::systems::update_scenario_button,
+                crate::game::systems::load_game_system,
+                crate::game::systems::load_replay_system,
+                crate::game::systems::resume_saved_game,
+            )
                 .chain()
                 .run_if(in_state(MyAppState::MainMenu)),
         )
         .add_systems(OnExit(MyAppState::MainMenu), (despawn_main_menu,))
+        /*
+         * GameSetup, the new game screen
+         */
+        .add_systems(
+            OnEnter(MyAppState::GameSetup),
+            crate::game::systems::new_game_menu,
+        )
+        .add_systems(
+            Update,
+            (
+                back_main_menu.run_if(not(crate::game::systems::typing_address)),
+                crate::game::systems::hud_button_colors,
+                crate::game::systems::new_game_buttons,
+                crate::game::systems::edit_network_address,
+                crate::game::systems::scenario_picker_system,
+                crate::game::systems::update_scenario_button,
+                crate::game::systems::update_new_game_text,
+            )
+                .chain()
+                .run_if(in_state(MyAppState::GameSetup)),
+        )
+        .add_systems(
+            OnExit(MyAppState::GameSetup),
+            crate::game::systems::despawn_new_game_menu,
+        )
+        /*
+         * Lobby of a network game. The host runs the rules and sends the state to its client,
+         * which sends its orders back; see `crate::net`.
+         */
+        .add_systems(
+            OnEnter(MyAppState::Lobby),
+            (
+                crate::net::systems::start_network,
+                crate::net::systems::lobby_menu,
+            )
+                .chain(),
+        )
+        .add_systems(
+            Update,
+            (
+                back_main_menu,
+                crate::game::systems::hud_button_colors,
+                crate::net::systems::lobby_buttons,
+                crate::net::systems::host_start_game
+                    .run_if(resource_exists::<crate::net::resources::NetHost>),
+                crate::net::systems::update_lobby_text,
+            )
+                .chain()
+                .run_if(in_state(MyAppState::Lobby)),
+        )
+        .add_systems(
+            OnExit(MyAppState::Lobby),
+            crate::net::systems::despawn_lobby_menu,
+        )
+        .add_systems(
+            Update,
+            (
+                crate::net::systems::host_receive
+                    .before(crate::rule::systems::apply_game_commands)
+                    .run_if(resource_exists::<crate::net::resources::NetHost>),
+                crate::net::systems::client_receive
+                    .run_if(resource_exists::<crate::net::resources::NetClient>),
+            ),
+        )
+        .add_systems(
+            OnEnter(MyAppState::MainMenu),
+            crate::net::systems::stop_network,
+        )
+        .add_systems(
+            OnEnter(MyAppState::GameSetup),
+            crate::net::systems::stop_network,
+        )
+        .add_systems(
+            OnEnter(MyAppState::MapMenu),
+            crate::net::systems::network_hud.run_if(
+                resource_exists::<crate::net::resources::NetHost>
+                    .or_else(resource_exists::<crate::net::resources::NetClient>),
+            ),
+        )
+        .add_systems(
+            Update,
+            (
+                crate::net::systems::host_sync_state
+                    .after(crate::rule::systems::apply_game_commands)
+                    .run_if(resource_exists::<crate::net::resources::NetHost>),
+                crate::net::systems::client_send_commands
+                    .run_if(resource_exists::<crate::net::resources::NetClient>),
+                crate::net::systems::client_apply_states
+                    .run_if(resource_exists::<crate::net::resources::NetClient>),
+                crate::net::systems::update_net_status_text,
+            )
+                .run_if(in_state(MyAppState::MapMenu)),
+        )
         /*
          * MapMenu
          */
@@ -84,6 +252,10 @@ fn main() {
                 crate::map::systems::camera2dbundle,
                 crate::map::systems::map_menu,
                 crate::map::systems::add_map,
+                crate::map::systems::minimap,
+                crate::map::systems::status_bar,
+                crate::game::systems::turn_hud,
+                crate::rule::systems::combat_log_hud,
             )
                 .chain(),
         )
@@ -91,15 +263,267 @@ fn main() {
             Update,
             (
                 back_main_menu,
+                crate::map::systems::toggle_hex_overlay,
+                crate::map::systems::update_hex_labels,
                 crate::map::systems::draw_hexagon_2d,
-                crate::map::systems::map2d_scale_wander,
-                crate::map::systems::add_oper,
+                (
+                    crate::map::systems::minimap_navigate,
+                    crate::map::systems::map2d_scale_wander,
+                    crate::map::systems::update_minimap_viewport,
+                    crate::map::systems::stream_map_tiles,
+                    crate::map::systems::update_minimap_image
+                        .run_if(resource_changed::<crate::map::resources::MapImage>),
+                )
+                    .chain(),
+                crate::map::systems::pick_hex
+                    .run_if(in_state(crate::game::entities::HandOffState::Off)),
+                crate::map::systems::draw_hexsides,
+                crate::map::systems::draw_hex_highlight,
+                crate::map::systems::draw_objectives,
+                crate::oper::systems::add_oper_sprites,
+                crate::rule::systems::select_and_move_oper
+                    .run_if(in_state(crate::game::entities::GamePhase::Movement))
+                    .run_if(in_state(crate::game::entities::ReplayState::Off))
+                    .run_if(crate::game::systems::local_turn)
+                    .run_if(not(crate::map::systems::ruler_active)),
+                crate::rule::systems::shade_reachable_hexes,
+                crate::rule::systems::draw_move_preview,
+                crate::rule::systems::animate_move_path
+                    .after(crate::map::systems::hex_position_to_transform),
+                crate::rule::systems::los_drag
+                    .run_if(in_state(crate::game::entities::HandOffState::Off))
+                    .run_if(not(crate::map::systems::ruler_active)),
+                crate::rule::systems::draw_line_of_sight,
             )
                 .chain()
                 .run_if(in_state(MyAppState::MapMenu)),
         )
+        /*
+         * Status bar and ruler of the map
+         */
+        .add_systems(
+            Update,
+            (
+                crate::map::systems::ruler_points
+                    .after(crate::map::systems::pick_hex)
+                    .run_if(in_state(crate::game::entities::HandOffState::Off)),
+                crate::map::systems::draw_ruler,
+                crate::map::systems::update_status_bar,
+            )
+                .chain()
+                .run_if(in_state(MyAppState::MapMenu)),
+        )
+        /*
+         * Turn sequence, played on the map.
+         */
+        .add_systems(
+            Update,
+            (
+                crate::game::systems::start_game,
+                crate::game::systems::hud_button_colors,
+                crate::game::systems::end_phase_system
+                    .run_if(in_state(crate::game::entities::ReplayState::Off))
+                    .run_if(in_state(crate::game::entities::HandOffState::Off))
+                    .run_if(crate::game::systems::local_turn),
+                crate::game::systems::save_game_system
+                    .run_if(in_state(crate::game::entities::ReplayState::Off))
+                    .run_if(not(resource_exists::<crate::rule::resources::RemoteRules>)),
+                crate::game::systems::load_game_system,
+                crate::game::systems::update_turn_text,
+            )
+                .chain()
+                .run_if(in_state(MyAppState::MapMenu)),
+        )
+        /*
+         * Recording of the game, and replays: the commands then come from the replay.
+         */
+        .add_systems(
+            Update,
+            (
+                crate::rule::systems::apply_selection_commands,
+                crate::game::systems::record_game_commands
+                    .run_if(in_state(crate::game::entities::ReplayState::Off)),
+                crate::game::systems::load_replay_system,
+                crate::game::systems::replay_hud
+                    .run_if(resource_added::<crate::game::resources::ReplayPlayer>),
+                crate::game::systems::update_replay_text
+                    .run_if(resource_exists::<crate::game::resources::ReplayPlayer>),
+            )
+                .run_if(in_state(MyAppState::MapMenu)),
+        )
+        .add_systems(
+            Update,
+            (
+                crate::game::systems::replay_controls,
+                crate::game::systems::play_replay
+                    .run_if(in_state(crate::game::entities::ReplayState::Playing)),
+                crate::game::systems::seek_replay,
+            )
+                .chain()
+                .run_if(resource_exists::<crate::game::resources::ReplayPlayer>)
+                .run_if(in_state(MyAppState::MapMenu)),
+        )
+        /*
+         * Fog of war: the map only shows what the side it is shown for has spotted.
+         * Hot-seat games hide the map between the turns of the two players.
+         */
+        .add_systems(
+            OnEnter(MyAppState::MapMenu),
+            crate::game::systems::apply_game_setup,
+        )
+        .add_systems(
+            Update,
+            (
+                crate::game::systems::start_hand_off
+                    .after(crate::rule::systems::apply_game_commands)
+                    .run_if(resource_changed::<crate::game::turn::TurnInfo>),
+                crate::game::systems::end_hand_off
+                    .run_if(in_state(crate::game::entities::HandOffState::Waiting)),
+            )
+                .chain()
+                .run_if(in_state(MyAppState::MapMenu)),
+        )
+        .add_systems(
+            OnEnter(crate::game::entities::HandOffState::Waiting),
+            crate::game::systems::hand_off_screen,
+        )
+        .add_systems(
+            OnExit(crate::game::entities::HandOffState::Waiting),
+            crate::game::systems::despawn_hand_off_screen,
+        )
+        .add_systems(
+            Update,
+            (
+                crate::rule::systems::cycle_fog_view
+                    .run_if(in_state(crate::game::entities::HandOffState::Off)),
+                crate::oper::systems::update_oper_visibility,
+                crate::oper::systems::update_fog_ghosts.run_if(
+                    resource_changed::<crate::rule::resources::FogOfWar>
+                        .or_else(resource_changed::<crate::game::turn::TurnInfo>),
+                ),
+            )
+                .chain()
+                .after(crate::game::systems::end_hand_off)
+                .run_if(in_state(MyAppState::MapMenu)),
+        )
+        .add_systems(
+            OnEnter(crate::game::entities::GamePhase::GameOver),
+            crate::game::systems::write_replay,
+        )
+        .add_systems(
+            Update,
+            crate::rule::systems::update_selection_reachable
+                .after(crate::rule::systems::apply_game_commands)
+                .run_if(in_state(crate::game::entities::GamePhase::Movement))
+                .run_if(in_state(MyAppState::MapMenu)),
+        )
+        .add_systems(
+            OnExit(crate::game::entities::GamePhase::Movement),
+            crate::rule::systems::clear_oper_selection,
+        )
+        /*
+         * The computer gives the orders of its side through game commands, like a player.
+         */
+        .add_systems(
+            Update,
+            crate::ai::systems::ai_play
+                .before(crate::rule::systems::apply_game_commands)
+                .run_if(resource_exists::<crate::ai::resources::AiPlayers>)
+                .run_if(in_state(crate::game::entities::ReplayState::Off))
+                .run_if(in_state(MyAppState::MapMenu)),
+        )
+        /*
+         * Combat, in the shooting and close combat phases.
+         */
+        .add_systems(
+            Update,
+            (
+                crate::rule::systems::select_and_fire
+                    .after(crate::map::systems::pick_hex)
+                    .run_if(in_state(crate::game::entities::ReplayState::Off))
+                    .run_if(crate::game::systems::local_turn)
+                    .run_if(not(crate::map::systems::ruler_active)),
+                crate::rule::systems::draw_fire_targets,
+            )
+                .chain()
+                .run_if(
+                    in_state(crate::game::entities::GamePhase::Shooting)
+                        .or_else(in_state(crate::game::entities::GamePhase::CloseCombat)),
+                )
+                .run_if(in_state(MyAppState::MapMenu)),
+        )
+        .add_systems(
+            Update,
+            (
+                crate::oper::systems::update_oper_sprites,
+                crate::rule::systems::update_combat_log_text
+                    .run_if(resource_changed::<crate::rule::resources::CombatLog>),
+            )
+                .run_if(in_state(MyAppState::MapMenu)),
+        )
+        .add_systems(
+            OnExit(crate::game::entities::GamePhase::Shooting),
+            crate::rule::systems::clear_oper_selection,
+        )
+        .add_systems(
+            OnExit(crate::game::entities::GamePhase::CloseCombat),
+            crate::rule::systems::clear_oper_selection,
+        )
         .add_systems(
             OnExit(MyAppState::MapMenu),
+            (
+                crate::map::systems::despawn_map_menu,
+                crate::game::systems::write_replay,
+                crate::game::systems::stop_turns,
+            )
+                .chain(),
+        )
+        /*
+         * MapEditor, painting the terrain file and the objectives of the selected scenario
+         * over the 2D map
+         */
+        .add_systems(
+            OnEnter(MyAppState::MapEditor),
+            (
+                crate::map::systems::init_map,
+                crate::map::systems::camera2dbundle,
+                crate::editor::systems::map_editor_menu,
+                crate::map::systems::minimap,
+                crate::map::systems::status_bar,
+            )
+                .chain(),
+        )
+        .add_systems(
+            Update,
+            (
+                crate::editor::systems::load_map_editor
+                    .run_if(crate::editor::systems::map_editor_loading),
+                crate::map::systems::toggle_hex_overlay,
+                crate::map::systems::update_hex_labels,
+                crate::map::systems::draw_hexagon_2d,
+                (
+                    crate::map::systems::minimap_navigate,
+                    crate::map::systems::map2d_scale_wander,
+                    crate::map::systems::update_minimap_viewport,
+                    crate::map::systems::stream_map_tiles,
+                    crate::map::systems::update_minimap_image
+                        .run_if(resource_changed::<crate::map::resources::MapImage>),
+                )
+                    .chain(),
+                crate::map::systems::pick_hex,
+                crate::editor::systems::map_editor_keys,
+                crate::editor::systems::map_editor_paint,
+                crate::editor::systems::save_map_editor,
+                crate::editor::systems::leave_map_editor,
+                crate::editor::systems::draw_map_editor,
+                crate::editor::systems::update_map_editor_text,
+                crate::map::systems::update_status_bar,
+            )
+                .chain()
+                .run_if(in_state(MyAppState::MapEditor)),
+        )
+        .add_systems(
+            OnExit(MyAppState::MapEditor),
             (crate::map::systems::despawn_map_menu,),
         )
         /*
@@ -108,6 +532,7 @@ fn main() {
         .add_systems(
             OnEnter(MyAppState::Map3D),
             (
+                crate::map::systems::init_terrain3d,
                 crate::map::systems::camera3dbundle,
                 crate::map::systems::map_menu,
                 crate::map::systems::add_map,
@@ -120,6 +545,8 @@ fn main() {
                 back_main_menu,
                 crate::map::systems::map_menu_system,
                 crate::map::systems::map3d_scale_wander,
+                crate::map::systems::add_terrain3d.run_if(crate::map::systems::terrain3d_pending),
+                crate::map::systems::draw_hexsides_3d,
                 crate::map::systems::draw_line_collection,
             )
                 .chain()
@@ -242,15 +669,17 @@ fn main() {
                 crate::game::systems::update_test_state,
                 crate::game::systems::render_oper,
                 crate::game::systems::aabb_intersection_system
-                    .run_if(in_state(crate::game::entities::GameState::AabbSweep)),
-                crate::game::systems::circle_intersection_system
-                    .run_if(in_state(crate::game::entities::GameState::CircleSweep)),
+                    .run_if(in_state(crate::game::entities::IntersectionTest::AabbSweep)),
+                crate::game::systems::circle_intersection_system.run_if(in_state(
+                    crate::game::entities::IntersectionTest::CircleSweep,
+                )),
                 crate::game::systems::ray_cast_system
-                    .run_if(in_state(crate::game::entities::GameState::RayCast)),
+                    .run_if(in_state(crate::game::entities::IntersectionTest::RayCast)),
                 crate::game::systems::aabb_cast_system
-                    .run_if(in_state(crate::game::entities::GameState::AabbCast)),
-                crate::game::systems::bounding_circle_cast_system
-                    .run_if(in_state(crate::game::entities::GameState::CircleCast)),
+                    .run_if(in_state(crate::game::entities::IntersectionTest::AabbCast)),
+                crate::game::systems::bounding_circle_cast_system.run_if(in_state(
+                    crate::game::entities::IntersectionTest::CircleCast,
+                )),
                 crate::game::systems::render_volumes,
             )
                 .chain()
@@ -260,6 +689,9 @@ fn main() {
             OnExit(MyAppState::GameMenu),
             (crate::game::systems::despawn_game_menu,),
         )
+        .add_plugins(crate::tools::debug_systems::DebugOverlayPlugin {
+            enabled: cli.launch.debug,
+        })
         .run();
     /*
      * Controlling data in terms of specific resources or components and
@@ -289,6 +721,8 @@ fn w_game_setup(
                     height: bevy::ui::Val::Percent(100.0),
                     align_items: bevy::ui::AlignItems::Center,
                     justify_content: bevy::ui::JustifyContent::Center,
+                    align_content: bevy::ui::AlignContent::Center,
+                    flex_wrap: bevy::ui::FlexWrap::Wrap,
                     ..default()
                 },
                 ..default()
@@ -296,6 +730,42 @@ fn w_game_setup(
             MainMenu,
         ))
         .with_children(|parent| {
+            /*
+             * new game Button, goes to the game setup screen
+             */
+            parent
+                .spawn((
+                    bevy::ui::node_bundles::ButtonBundle {
+                        style: bevy::ui::Style {
+                            width: bevy::ui::Val::Px(200.0),
+                            height: bevy::ui::Val::Px(65.0),
+                            border: bevy::ui::UiRect::all(bevy::ui::Val::Px(5.0)),
+                            // horizontally center child text
+                            justify_content: bevy::ui::JustifyContent::Center,
+                            // vertically center child text
+                            align_items: bevy::ui::AlignItems::Center,
+                            ..default()
+                        },
+                        border_color: bevy::ui::BorderColor(bevy::render::color::Color::BLACK),
+                        background_color: bevy::render::color::Color::rgb(0.15, 0.15, 0.15).into(),
+                        ..default()
+                    },
+                    MainMenu,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        bevy::ui::node_bundles::TextBundle::from_section(
+                            "New game",
+                            bevy::text::TextStyle {
+                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                font_size: 40.0,
+                                color: bevy::render::color::Color::rgb(0.9, 0.9, 0.9),
+                            },
+                        ),
+                        MainMenu,
+                    ));
+                });
+
             /*
              * map Button
              */
@@ -332,6 +802,42 @@ fn w_game_setup(
                     ));
                 });
 
+            /*
+             * editor Button, paints the terrain of the selected scenario's map
+             */
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(150.0),
+                            height: Val::Px(65.0),
+                            border: UiRect::all(Val::Px(5.0)),
+                            // horizontally center child text
+                            justify_content: JustifyContent::Center,
+                            // vertically center child text
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        border_color: BorderColor(Color::BLACK),
+                        background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                        ..default()
+                    },
+                    MainMenu,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextBundle::from_section(
+                            "Editor",
+                            TextStyle {
+                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                font_size: 40.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                            },
+                        ),
+                        MainMenu,
+                    ));
+                });
+
             /*
              * oper Button
              */
@@ -439,6 +945,117 @@ fn w_game_setup(
                         MainMenu,
                     ));
                 });
+
+            /*
+             * load Button, carries on with the saved game
+             */
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(150.0),
+                            height: Val::Px(65.0),
+                            border: UiRect::all(Val::Px(5.0)),
+                            // horizontally center child text
+                            justify_content: JustifyContent::Center,
+                            // vertically center child text
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        border_color: BorderColor(Color::BLACK),
+                        background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                        ..default()
+                    },
+                    crate::game::entities::LoadButton,
+                    MainMenu,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextBundle::from_section(
+                            "Load",
+                            TextStyle {
+                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                font_size: 40.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                            },
+                        ),
+                        MainMenu,
+                    ));
+                });
+
+            /*
+             * replay Button, plays back the last game recorded
+             */
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(150.0),
+                            height: Val::Px(65.0),
+                            border: UiRect::all(Val::Px(5.0)),
+                            // horizontally center child text
+                            justify_content: JustifyContent::Center,
+                            // vertically center child text
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        border_color: BorderColor(Color::BLACK),
+                        background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                        ..default()
+                    },
+                    crate::game::entities::ReplayButton,
+                    MainMenu,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextBundle::from_section(
+                            "Replay",
+                            TextStyle {
+                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                font_size: 40.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                            },
+                        ),
+                        MainMenu,
+                    ));
+                });
+
+            /*
+             * scenario Button, click to pick the next scenario
+             */
+            parent
+                .spawn((
+                    ButtonBundle {
+                        style: Style {
+                            width: Val::Px(420.0),
+                            height: Val::Px(65.0),
+                            border: UiRect::all(Val::Px(5.0)),
+                            // horizontally center child text
+                            justify_content: JustifyContent::Center,
+                            // vertically center child text
+                            align_items: AlignItems::Center,
+                            ..default()
+                        },
+                        border_color: BorderColor(Color::BLACK),
+                        background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                        ..default()
+                    },
+                    crate::game::entities::ScenarioButton,
+                    MainMenu,
+                ))
+                .with_children(|parent| {
+                    parent.spawn((
+                        TextBundle::from_section(
+                            "Scenario: loading...",
+                            TextStyle {
+                                font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+                                font_size: 28.0,
+                                color: Color::rgb(0.9, 0.9, 0.9),
+                            },
+                        ),
+                        MainMenu,
+                    ));
+                });
         });
 }
 
@@ -465,10 +1082,18 @@ fn w_game_system(
 
                 match state.get() {
                     MyAppState::MainMenu => {
+                        if text.sections[0].value == "New game" {
+                            next_state.set(MyAppState::GameSetup);
+                            info!("AppState::GameSetup");
+                        }
                         if text.sections[0].value == "Map".to_string() {
                             next_state.set(MyAppState::MapMenu);
                             info!("AppState::MapMenu");
                         }
+                        if text.sections[0].value == "Editor" {
+                            next_state.set(MyAppState::MapEditor);
+                            info!("AppState::MapEditor");
+                        }
                         if text.sections[0].value == "Oper".to_string() {
                             next_state.set(MyAppState::OperMenu);
                             info!("AppState::OperMenu");
@@ -1,7 +1,15 @@
 // Components: Rust structs that implement the Component trait
-// use bevy::ecs::component::Component;
+use bevy::ecs::component::Component;
 
 /*
  * Components are the data associated with entities.
  * To create a new component type, simply define a Rust struct or enum, and derive the Component trait.
  */
+
+/// Places an entity on the map grid; its transform follows the hex centre.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HexPosition(pub crate::map::hex::Hex);
+
+/// Label of a hex in sight, see `crate::map::systems::update_hex_labels`.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HexLabel(pub crate::map::hex::Hex);
@@ -50,3 +50,15 @@ pub struct MapCamera3d;
 
 #[derive(bevy::ecs::component::Component)]
 pub struct Map3d;
+
+/// The whole map in small, in a corner of the 2D map; click or drag on it to go there.
+#[derive(bevy::ecs::component::Component)]
+pub struct Minimap;
+
+/// Outline of the part of the map the camera shows, on the minimap.
+#[derive(bevy::ecs::component::Component)]
+pub struct MinimapViewport;
+
+/// Status bar of the 2D map: the coordinates under the cursor and the ruler's measures.
+#[derive(bevy::ecs::component::Component)]
+pub struct StatusBarText;
@@ -0,0 +1,11 @@
+// Events: messages sent between systems with EventWriter/EventReader
+
+use bevy::prelude::*;
+
+/// A mouse button clicked a hex of the 2D map: pressed, or for the buttons that drag the map,
+/// let go without dragging it.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct HexClicked {
+    pub hex: crate::map::hex::Hex,
+    pub button: MouseButton,
+}
@@ -0,0 +1,71 @@
+// Georeferencing: where the map image lies on the ground
+
+use bevy::math::Vec2;
+
+/*
+ * A map is tied to the ground by the latitude and longitude of the south-west (bottom left)
+ * corner of its image, and the metres one image pixel covers. Map coordinates are world
+ * coordinates: x metres east and y metres north of that corner.
+ *
+ * Maps cover a few kilometres, so the ground is taken as flat around the corner:
+ * a degree of latitude is always as long, a degree of longitude shrinks with the corner's latitude.
+ */
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct GeoPoint {
+    /// Degrees north of the equator, south is negative.
+    pub lat: f64,
+    /// Degrees east of Greenwich, west is negative.
+    pub lon: f64,
+}
+
+const EARTH_RADIUS_METERS: f64 = 6_371_000.;
+
+impl GeoPoint {
+    /// The point `east` and `north` metres away.
+    pub fn offset(&self, east: f64, north: f64) -> GeoPoint {
+        let lat = self.lat + (north / EARTH_RADIUS_METERS).to_degrees();
+        let lon =
+            self.lon + (east / (EARTH_RADIUS_METERS * self.lat.to_radians().cos())).to_degrees();
+        GeoPoint { lat, lon }
+    }
+}
+
+impl std::fmt::Display for GeoPoint {
+    /// e.g. "39.90421°N 116.40739°E"
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:.5}°{} {:.5}°{}",
+            self.lat.abs(),
+            if self.lat < 0. { 'S' } else { 'N' },
+            self.lon.abs(),
+            if self.lon < 0. { 'W' } else { 'E' }
+        )
+    }
+}
+
+/// Ground position of a map image, as given in a scenario file:
+/// `geo: Some((origin: (lat: 39.9, lon: 116.4), meter_per_pixel: 0.1389))`.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct GeoReference {
+    /// South-west corner of the image.
+    pub origin: GeoPoint,
+    pub meter_per_pixel: f32,
+}
+
+impl GeoReference {
+    /// Metres east and north of the origin of a world point.
+    pub fn meters(&self, point: Vec2) -> Vec2 {
+        point * self.meter_per_pixel
+    }
+
+    pub fn to_geo(&self, point: Vec2) -> GeoPoint {
+        let meters = self.meters(point);
+        self.origin.offset(meters.x as f64, meters.y as f64)
+    }
+
+    /// Length on the ground of a line between two world points, in metres.
+    pub fn distance(&self, from: Vec2, to: Vec2) -> f32 {
+        from.distance(to) * self.meter_per_pixel
+    }
+}
@@ -0,0 +1,459 @@
+// Hex grid: coordinates and the layout that places them on the map image
+
+use bevy::math::Vec2;
+
+/*
+ * The grid uses flat-topped hexagons, the usual layout of hex-and-counter wargame maps.
+ * Hexes are stored in axial coordinates (q, r); the third cube coordinate is s = -q - r.
+ * Axial/cube coordinates make distance, rings and lines simple arithmetic,
+ * while offset coordinates (col, row) are what players read on the printed map.
+ * The offset layout is "odd-q": every odd column is shoved half a hex down.
+ * See https://www.redblobgames.com/grids/hexagons/ for the maths.
+ */
+#[derive(
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    serde::Serialize,
+    serde::Deserialize,
+)]
+pub struct Hex {
+    pub q: i32,
+    pub r: i32,
+}
+
+/// The six neighbours of a flat-topped hex, clockwise from the top.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum HexDirection {
+    North,
+    NorthEast,
+    SouthEast,
+    South,
+    SouthWest,
+    NorthWest,
+}
+
+impl HexDirection {
+    pub const ALL: [HexDirection; 6] = [
+        HexDirection::North,
+        HexDirection::NorthEast,
+        HexDirection::SouthEast,
+        HexDirection::South,
+        HexDirection::SouthWest,
+        HexDirection::NorthWest,
+    ];
+
+    /// Axial offset of the neighbour in this direction.
+    pub const fn offset(self) -> Hex {
+        match self {
+            HexDirection::North => Hex::new(0, -1),
+            HexDirection::NorthEast => Hex::new(1, -1),
+            HexDirection::SouthEast => Hex::new(1, 0),
+            HexDirection::South => Hex::new(0, 1),
+            HexDirection::SouthWest => Hex::new(-1, 1),
+            HexDirection::NorthWest => Hex::new(-1, 0),
+        }
+    }
+
+    pub const fn opposite(self) -> HexDirection {
+        match self {
+            HexDirection::North => HexDirection::South,
+            HexDirection::NorthEast => HexDirection::SouthWest,
+            HexDirection::SouthEast => HexDirection::NorthWest,
+            HexDirection::South => HexDirection::North,
+            HexDirection::SouthWest => HexDirection::NorthEast,
+            HexDirection::NorthWest => HexDirection::SouthEast,
+        }
+    }
+}
+
+impl Hex {
+    pub const ZERO: Hex = Hex::new(0, 0);
+
+    pub const fn new(q: i32, r: i32) -> Self {
+        Hex { q, r }
+    }
+
+    /// Builds a hex from cube coordinates, which must satisfy q + r + s == 0.
+    pub fn from_cube(q: i32, r: i32, s: i32) -> Self {
+        debug_assert_eq!(q + r + s, 0, "cube coordinates must sum to zero");
+        Hex { q, r }
+    }
+
+    pub const fn s(self) -> i32 {
+        -self.q - self.r
+    }
+
+    pub const fn cube(self) -> (i32, i32, i32) {
+        (self.q, self.r, self.s())
+    }
+
+    pub const fn neighbor(self, direction: HexDirection) -> Hex {
+        let d = direction.offset();
+        Hex::new(self.q + d.q, self.r + d.r)
+    }
+
+    pub fn neighbors(self) -> [Hex; 6] {
+        HexDirection::ALL.map(|direction| self.neighbor(direction))
+    }
+
+    /// Direction to an adjacent hex, `None` if `other` is not a neighbour.
+    pub fn direction_to(self, other: Hex) -> Option<HexDirection> {
+        HexDirection::ALL
+            .into_iter()
+            .find(|direction| self.neighbor(*direction) == other)
+    }
+
+    /// Distance from the origin, in hexes.
+    pub fn length(self) -> i32 {
+        (self.q.abs() + self.r.abs() + self.s().abs()) / 2
+    }
+
+    pub fn distance(self, other: Hex) -> i32 {
+        (self - other).length()
+    }
+
+    /// All hexes exactly `radius` steps away, clockwise starting from the south-west corner.
+    pub fn ring(self, radius: u32) -> Vec<Hex> {
+        if radius == 0 {
+            return vec![self];
+        }
+        let mut hexes = Vec::with_capacity(6 * radius as usize);
+        let mut hex = self + HexDirection::SouthWest.offset() * radius as i32;
+        for direction in HexDirection::ALL {
+            for _ in 0..radius {
+                hexes.push(hex);
+                hex = hex.neighbor(direction);
+            }
+        }
+        hexes
+    }
+
+    /// All hexes within `radius` steps, ordered ring by ring outwards from `self`.
+    pub fn spiral(self, radius: u32) -> Vec<Hex> {
+        let mut hexes = vec![self];
+        for k in 1..=radius {
+            hexes.extend(self.ring(k));
+        }
+        hexes
+    }
+
+    /// Hexes crossed by a straight line between the two hex centres, both ends included.
+    pub fn line_to(self, other: Hex) -> Vec<Hex> {
+        let n = self.distance(other);
+        /*
+         * Nudge the end points slightly so a line running exactly along a hex edge
+         * always picks the same side instead of flickering between the two hexes.
+         * The line is drawn from the origin and moved back to `self` afterwards: the nudge must
+         * not be lost to f32 precision on hexes far from the origin, and the same line must
+         * cross the same hexes wherever it lies on the map.
+         */
+        let nudge = 1e-4;
+        let d = other - self;
+        let a = FractionalHex::new(nudge, nudge);
+        let b = FractionalHex::new(d.q as f32 + nudge, d.r as f32 + nudge);
+        if n == 0 {
+            return vec![self];
+        }
+        (0..=n)
+            .map(|i| self + a.lerp(b, i as f32 / n as f32).round())
+            .collect()
+    }
+
+    pub fn to_offset(self) -> OffsetCoord {
+        OffsetCoord {
+            col: self.q,
+            row: self.r + (self.q - (self.q & 1)) / 2,
+        }
+    }
+
+    pub fn from_offset(offset: OffsetCoord) -> Self {
+        Hex::new(offset.col, offset.row - (offset.col - (offset.col & 1)) / 2)
+    }
+}
+
+impl std::ops::Add for Hex {
+    type Output = Hex;
+    fn add(self, rhs: Hex) -> Hex {
+        Hex::new(self.q + rhs.q, self.r + rhs.r)
+    }
+}
+
+impl std::ops::Sub for Hex {
+    type Output = Hex;
+    fn sub(self, rhs: Hex) -> Hex {
+        Hex::new(self.q - rhs.q, self.r - rhs.r)
+    }
+}
+
+impl std::ops::Mul<i32> for Hex {
+    type Output = Hex;
+    fn mul(self, rhs: i32) -> Hex {
+        Hex::new(self.q * rhs, self.r * rhs)
+    }
+}
+
+/// A point in axial space that is not necessarily a hex centre.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FractionalHex {
+    pub q: f32,
+    pub r: f32,
+}
+
+impl FractionalHex {
+    pub const fn new(q: f32, r: f32) -> Self {
+        FractionalHex { q, r }
+    }
+
+    pub fn s(self) -> f32 {
+        -self.q - self.r
+    }
+
+    pub fn lerp(self, other: FractionalHex, t: f32) -> FractionalHex {
+        FractionalHex::new(
+            self.q + (other.q - self.q) * t,
+            self.r + (other.r - self.r) * t,
+        )
+    }
+
+    /// Rounds to the hex that contains this point.
+    pub fn round(self) -> Hex {
+        let mut q = self.q.round();
+        let mut r = self.r.round();
+        let s = self.s().round();
+        let q_diff = (q - self.q).abs();
+        let r_diff = (r - self.r).abs();
+        let s_diff = (s - self.s()).abs();
+        if q_diff > r_diff && q_diff > s_diff {
+            q = -r - s;
+        } else if r_diff > s_diff {
+            r = -q - s;
+        }
+        Hex::new(q as i32, r as i32)
+    }
+}
+
+/// Column/row position as printed on the map, "odd-q" layout.
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub struct OffsetCoord {
+    pub col: i32,
+    pub row: i32,
+}
+
+impl OffsetCoord {
+    pub const fn new(col: i32, row: i32) -> Self {
+        OffsetCoord { col, row }
+    }
+}
+
+/*
+ * The layout ties hex coordinates to world coordinates.
+ * The map sprite is drawn with its bottom-left corner at the world origin and
+ * one world unit per image pixel, so world (0, unit_y) is the top-left corner of the image.
+ * Hex (col 0, row 0) sits in that top-left corner and rows grow downwards, like the printed map.
+ */
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HexLayout {
+    /// Centre-to-corner radius of a hex, in world units.
+    pub size: f32,
+    /// World position of the centre of hex (0, 0).
+    pub origin: Vec2,
+    pub cols: i32,
+    pub rows: i32,
+}
+
+impl HexLayout {
+    pub fn from_map_info(map_info: &crate::map::resources::MapInfo) -> Self {
+        let size = map_info.hex_size;
+        let height = size * 3f32.sqrt();
+        HexLayout {
+            size,
+            origin: Vec2::new(size, map_info.unit_y - height / 2.),
+            // cover the whole image, partial hexes on the right and bottom edges included
+            cols: ((map_info.unit_x - size / 2.) / (size * 1.5)).ceil() as i32,
+            rows: (map_info.unit_y / height).ceil() as i32,
+        }
+    }
+
+    /// Distance between the centres of two adjacent hexes.
+    pub fn hex_spacing(&self) -> f32 {
+        self.size * 3f32.sqrt()
+    }
+
+    pub fn hex_to_world(&self, hex: Hex) -> Vec2 {
+        let x = self.size * 1.5 * hex.q as f32;
+        let y = self.size * 3f32.sqrt() * (hex.r as f32 + hex.q as f32 / 2.);
+        // world y points up, map rows go down
+        Vec2::new(self.origin.x + x, self.origin.y - y)
+    }
+
+    pub fn world_to_fractional_hex(&self, point: Vec2) -> FractionalHex {
+        let x = (point.x - self.origin.x) / self.size;
+        let y = (self.origin.y - point.y) / self.size;
+        let q = 2. / 3. * x;
+        let r = -1. / 3. * x + 3f32.sqrt() / 3. * y;
+        FractionalHex::new(q, r)
+    }
+
+    pub fn world_to_hex(&self, point: Vec2) -> Hex {
+        self.world_to_fractional_hex(point).round()
+    }
+
+    /// The six corners of a hex in world coordinates, starting at the east corner.
+    pub fn corners(&self, hex: Hex) -> [Vec2; 6] {
+        let center = self.hex_to_world(hex);
+        std::array::from_fn(|i| {
+            let angle = std::f32::consts::FRAC_PI_3 * i as f32;
+            center + Vec2::new(angle.cos(), angle.sin()) * self.size
+        })
+    }
+
+    /// The two corners of the edge a hex shares with its neighbour in `direction`.
+    pub fn edge(&self, hex: Hex, direction: HexDirection) -> [Vec2; 2] {
+        // corners go anticlockwise from the east one, the north-east edge first
+        let first = match direction {
+            HexDirection::NorthEast => 0,
+            HexDirection::North => 1,
+            HexDirection::NorthWest => 2,
+            HexDirection::SouthWest => 3,
+            HexDirection::South => 4,
+            HexDirection::SouthEast => 5,
+        };
+        let corners = self.corners(hex);
+        [corners[first], corners[(first + 1) % 6]]
+    }
+
+    /// The hex edge nearest to a world point: the hex the point is in and the side it is closest to.
+    pub fn nearest_edge(&self, point: Vec2) -> (Hex, HexDirection) {
+        let hex = self.world_to_hex(point);
+        let center = self.hex_to_world(hex);
+        let direction = HexDirection::ALL
+            .into_iter()
+            .min_by(|a, b| {
+                let middle = |direction: HexDirection| {
+                    (center + self.hex_to_world(hex.neighbor(direction))) / 2.
+                };
+                point
+                    .distance_squared(middle(*a))
+                    .total_cmp(&point.distance_squared(middle(*b)))
+            })
+            .unwrap_or(HexDirection::North);
+        (hex, direction)
+    }
+
+    /// Whether the hex lies on the map.
+    pub fn contains(&self, hex: Hex) -> bool {
+        let offset = hex.to_offset();
+        (0..self.cols).contains(&offset.col) && (0..self.rows).contains(&offset.row)
+    }
+
+    /// Every hex on the map, column by column.
+    pub fn hexes(&self) -> impl Iterator<Item = Hex> + '_ {
+        (0..self.cols).flat_map(move |col| {
+            (0..self.rows).map(move |row| Hex::from_offset(OffsetCoord::new(col, row)))
+        })
+    }
+
+    /// Hexes on the map that overlap the world rectangle `min`..`max`, e.g. the camera view.
+    pub fn hexes_in_rect(&self, min: Vec2, max: Vec2) -> impl Iterator<Item = Hex> + '_ {
+        let col_width = self.size * 1.5;
+        let row_height = self.hex_spacing();
+        let col_min = (((min.x - self.origin.x) / col_width).floor() as i32 - 1).max(0);
+        let col_max = (((max.x - self.origin.x) / col_width).ceil() as i32 + 1).min(self.cols - 1);
+        let row_min = (((self.origin.y - max.y) / row_height).floor() as i32 - 1).max(0);
+        let row_max = (((self.origin.y - min.y) / row_height).ceil() as i32 + 1).min(self.rows - 1);
+        (col_min..=col_max).flat_map(move |col| {
+            (row_min..=row_max).map(move |row| Hex::from_offset(OffsetCoord::new(col, row)))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distance() {
+        assert_eq!(Hex::ZERO.distance(Hex::ZERO), 0);
+        assert_eq!(Hex::new(3, -1).distance(Hex::ZERO), 3);
+        assert_eq!(Hex::new(-2, -1).distance(Hex::new(2, -3)), 4);
+        assert_eq!(Hex::new(5, 7).distance(Hex::new(-4, 2)), 14);
+        for direction in HexDirection::ALL {
+            assert_eq!(
+                Hex::new(4, 9).distance(Hex::new(4, 9).neighbor(direction)),
+                1
+            );
+        }
+    }
+
+    #[test]
+    fn neighbors() {
+        let hex = Hex::new(2, -5);
+        let neighbors = hex.neighbors();
+        for (direction, neighbor) in HexDirection::ALL.into_iter().zip(neighbors) {
+            assert_eq!(hex.direction_to(neighbor), Some(direction));
+            assert_eq!(neighbor.neighbor(direction.opposite()), hex);
+        }
+        assert_eq!(hex.direction_to(hex), None);
+        assert_eq!(hex.direction_to(Hex::new(4, -5)), None);
+        assert_eq!(hex.ring(2).len(), 12);
+        assert_eq!(hex.spiral(2).len(), 19);
+    }
+
+    #[test]
+    fn offset_round_trip() {
+        for col in -7..40 {
+            for row in -7..40 {
+                let offset = OffsetCoord::new(col, row);
+                assert_eq!(Hex::from_offset(offset).to_offset(), offset);
+            }
+        }
+        // odd columns are shoved half a hex down
+        assert_eq!(
+            Hex::from_offset(OffsetCoord::new(0, 0)).neighbor(HexDirection::SouthEast),
+            Hex::from_offset(OffsetCoord::new(1, 0))
+        );
+        assert_eq!(
+            Hex::from_offset(OffsetCoord::new(1, 0)).neighbor(HexDirection::SouthEast),
+            Hex::from_offset(OffsetCoord::new(2, 1))
+        );
+    }
+
+    #[test]
+    fn line_to() {
+        let (from, to) = (Hex::new(-3, 1), Hex::new(4, -2));
+        let line = from.line_to(to);
+        assert_eq!(line.len() as i32, from.distance(to) + 1);
+        assert_eq!(line.first(), Some(&from));
+        assert_eq!(line.last(), Some(&to));
+        assert!(line.windows(2).all(|pair| pair[0].distance(pair[1]) == 1));
+        assert_eq!(from.line_to(from), vec![from]);
+    }
+
+    #[test]
+    fn line_to_same_wherever_on_the_map() {
+        // (2, -1) and (1, 1) run along hex edges, where the nudge picks the side
+        for d in [
+            Hex::new(2, -1),
+            Hex::new(1, 1),
+            Hex::new(6, -3),
+            Hex::new(5, 2),
+        ] {
+            for n in [1, 3, 7] {
+                let line = Hex::ZERO.line_to(d * n);
+                for shift in [Hex::new(40, -20), Hex::new(-35, 70), Hex::new(500, 300)] {
+                    let shifted: Vec<Hex> = line.iter().map(|hex| *hex + shift).collect();
+                    assert_eq!(shift.line_to(d * n + shift), shifted);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,147 @@
+// Hexside layer: roads, bridges, rivers and walls on the edges between hexes
+
+use crate::map::hex::{Hex, HexDirection};
+
+/*
+ * Features lie on hexsides, the edges two adjacent hexes share, and are given in the terrain file
+ * by either hex and the direction of the other one:
+ *
+ *   hexsides: [
+ *       (col: 20, row: 14, side: SouthEast, features: [Road]),
+ *       (col: 16, row: 18, side: NorthEast, features: [Bridge, River]),
+ *   ],
+ *
+ * A road or a bridge crosses the hexside, joining the centres of its two hexes: the roads of a map
+ * are a graph of hexes. A river or a wall runs along the hexside, between the two hexes.
+ * A bridge carries a road over the river of its hexside.
+ */
+#[derive(
+    Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, serde::Serialize, serde::Deserialize,
+)]
+pub enum HexsideFeature {
+    Road,
+    Bridge,
+    River,
+    Wall,
+}
+
+impl HexsideFeature {
+    /// Roads and bridges are crossed from hex to hex, rivers and walls run along the edge.
+    pub fn crosses(self) -> bool {
+        matches!(self, HexsideFeature::Road | HexsideFeature::Bridge)
+    }
+}
+
+/// The edge between two adjacent hexes, the same whichever of them it is seen from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Hexside {
+    pub hex: Hex,
+    /// North, NorthEast or SouthEast: the other three are those of the neighbour.
+    pub direction: HexDirection,
+}
+
+impl Hexside {
+    pub fn new(hex: Hex, direction: HexDirection) -> Self {
+        match direction {
+            HexDirection::North | HexDirection::NorthEast | HexDirection::SouthEast => {
+                Hexside { hex, direction }
+            }
+            _ => Hexside {
+                hex: hex.neighbor(direction),
+                direction: direction.opposite(),
+            },
+        }
+    }
+
+    /// The hexside two hexes share, `None` when they are not adjacent.
+    pub fn between(from: Hex, to: Hex) -> Option<Self> {
+        from.direction_to(to)
+            .map(|direction| Hexside::new(from, direction))
+    }
+
+    /// The hexside through which a straight line from `from` enters `to`.
+    pub fn entering(from: Hex, to: Hex) -> Option<Self> {
+        let line = from.line_to(to);
+        match line.len() {
+            0 | 1 => None,
+            n => Hexside::between(line[n - 2], to),
+        }
+    }
+
+    pub fn hexes(self) -> [Hex; 2] {
+        [self.hex, self.hex.neighbor(self.direction)]
+    }
+}
+
+/// Features of the hexsides of a map, sorted and without repeats.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HexsideMap {
+    pub features: bevy::utils::HashMap<Hexside, Vec<HexsideFeature>>,
+}
+
+impl HexsideMap {
+    pub fn get(&self, hexside: Hexside) -> &[HexsideFeature] {
+        self.features.get(&hexside).map_or(&[], |features| features)
+    }
+
+    /// Features between two hexes, none when they are not adjacent.
+    pub fn between(&self, from: Hex, to: Hex) -> &[HexsideFeature] {
+        Hexside::between(from, to).map_or(&[], |hexside| self.get(hexside))
+    }
+
+    pub fn has(&self, from: Hex, to: Hex, feature: HexsideFeature) -> bool {
+        self.between(from, to).contains(&feature)
+    }
+
+    /// Features of the hexside through which fire or sight from `from` comes into `to`.
+    pub fn entering(&self, from: Hex, to: Hex) -> &[HexsideFeature] {
+        Hexside::entering(from, to).map_or(&[], |hexside| self.get(hexside))
+    }
+
+    /// Adds a feature to a hexside; false when it was there already.
+    pub fn add(&mut self, hexside: Hexside, feature: HexsideFeature) -> bool {
+        let features = self.features.entry(hexside).or_default();
+        if features.contains(&feature) {
+            return false;
+        }
+        features.push(feature);
+        features.sort();
+        true
+    }
+
+    /// Takes a feature off a hexside; false when it was not there.
+    pub fn remove(&mut self, hexside: Hexside, feature: HexsideFeature) -> bool {
+        let Some(features) = self.features.get_mut(&hexside) else {
+            return false;
+        };
+        let count = features.len();
+        features.retain(|other| *other != feature);
+        let removed = features.len() != count;
+        if features.is_empty() {
+            self.features.remove(&hexside);
+        }
+        removed
+    }
+
+    /// Neighbours a road or a bridge leads to from `hex`.
+    pub fn road_neighbors(&self, hex: Hex) -> impl Iterator<Item = Hex> + '_ {
+        HexDirection::ALL
+            .into_iter()
+            .map(move |direction| hex.neighbor(direction))
+            .filter(move |neighbor| {
+                self.between(hex, *neighbor)
+                    .iter()
+                    .any(|feature| feature.crosses())
+            })
+    }
+}
+
+/// One hexside of a terrain file.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct HexsideEntry {
+    pub col: i32,
+    pub row: i32,
+    /// Direction of the other hex.
+    pub side: HexDirection,
+    pub features: Vec<HexsideFeature>,
+}
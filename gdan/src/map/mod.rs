@@ -1,4 +1,11 @@
 pub mod components;
 pub mod entities;
+pub mod events;
+pub mod geo;
+pub mod hex;
+pub mod hexside;
 pub mod resources;
 pub mod systems;
+pub mod terrain;
+pub mod terrain3d;
+pub mod tiles;
@@ -17,6 +17,133 @@ pub struct MapInfo {
     pub label_y: u32,
     pub satellite_map_level: u8,
     pub meter_per_pixel: f32,
+    /// South-west corner of the map image on the ground, when the scenario gives it.
+    pub origin: Option<crate::map::geo::GeoPoint>,
+    /// Centre-to-corner radius of a map hex, in image pixels.
+    pub hex_size: f32,
+}
+
+impl Default for MapInfo {
+    fn default() -> Self {
+        MapInfo {
+            scale: 1.,
+            unit_x: 8819.,
+            unit_y: 6299.,
+            label_x: 1,
+            label_y: 1,
+            // level 21: 10.meter/72.pixel
+            // level 22: 5.meter/72.pixel
+            satellite_map_level: 21,
+            meter_per_pixel: 10. / 72.,
+            origin: None,
+            hex_size: 72.,
+        }
+    }
+}
+
+impl MapInfo {
+    /// The "ccrr" label printed on a hex, numbered from `label_x`/`label_y`.
+    pub fn hex_label(&self, hex: crate::map::hex::Hex) -> String {
+        let offset = hex.to_offset();
+        format!(
+            "{:02}{:02}",
+            offset.col + self.label_x as i32,
+            offset.row + self.label_y as i32
+        )
+    }
+
+    /// Ground position of the map; without an origin, distances are still right.
+    pub fn geo(&self) -> crate::map::geo::GeoReference {
+        crate::map::geo::GeoReference {
+            origin: self.origin.unwrap_or_default(),
+            meter_per_pixel: self.meter_per_pixel,
+        }
+    }
+}
+
+/// The hex layout of the current map, kept in step with `MapInfo` and available in every state.
+#[derive(bevy::ecs::system::Resource)]
+pub struct HexGrid(pub crate::map::hex::HexLayout);
+
+impl bevy::ecs::world::FromWorld for HexGrid {
+    fn from_world(world: &mut bevy::ecs::world::World) -> Self {
+        let map_info = world.get_resource_or_insert_with(MapInfo::default);
+        HexGrid(crate::map::hex::HexLayout::from_map_info(&map_info))
+    }
+}
+
+pub const DEFAULT_MAP_IMAGE: &str = "wg/mlx/map/1-8819p-6299p.png";
+
+/// Picture of the whole map currently shown: the map image, or the smallest level of its tiles.
+/// The default handle until the tile pyramid is found or found missing.
+#[derive(bevy::ecs::system::Resource, Default)]
+pub struct MapImage(pub bevy::asset::Handle<bevy::render::texture::Image>);
+
+/// Tiles of the map currently shown, see `crate::map::tiles`.
+#[derive(bevy::ecs::system::Resource, Default)]
+pub struct MapTiles {
+    /// Asset path of the map image.
+    pub map_image: String,
+    pub pyramid: bevy::asset::Handle<crate::map::tiles::TilePyramid>,
+    /// Tile sprites on the map.
+    pub tiles: bevy::utils::HashMap<crate::map::tiles::TileKey, bevy::ecs::entity::Entity>,
+}
+
+/// Terrain layer of the map currently shown, loaded alongside the map image.
+#[derive(bevy::ecs::system::Resource, Default)]
+pub struct MapTerrain(pub bevy::asset::Handle<crate::map::terrain::TerrainMap>);
+
+/// The hex under the mouse cursor on the 2D map, if any.
+#[derive(bevy::ecs::system::Resource, Default)]
+pub struct HoveredHex(pub Option<crate::map::hex::Hex>);
+
+/// The hex last picked with the left mouse button; a right click clears it.
+#[derive(bevy::ecs::system::Resource, Default)]
+pub struct SelectedHex(pub Option<crate::map::hex::Hex>);
+
+/// How the 2D map camera pans and zooms, see `crate::map::systems::map2d_scale_wander`.
+#[derive(bevy::ecs::system::Resource)]
+pub struct MapCameraControls {
+    /// Keyboard panning speed, in screen pixels per second.
+    pub pan_speed: f32,
+    /// Closest zoom, in map image pixels per screen pixel.
+    pub min_scale: f32,
+    pub max_scale: f32,
+    /// Zoom factor of one scroll wheel line.
+    pub line_zoom: f32,
+    /// Zoom factor of one pixel of touchpad scrolling.
+    pub pixel_zoom: f32,
+}
+
+impl Default for MapCameraControls {
+    fn default() -> Self {
+        MapCameraControls {
+            pan_speed: 600.,
+            min_scale: 1.,
+            max_scale: 5.,
+            line_zoom: 1.25,
+            pixel_zoom: 1.005,
+        }
+    }
+}
+
+/// Ground of the 3D map, see `crate::map::terrain3d`; built once its heightmap is found or not.
+#[derive(bevy::ecs::system::Resource, Default)]
+pub struct Terrain3d {
+    pub map_image: String,
+    pub heightmap: bevy::asset::Handle<crate::map::terrain3d::Heightmap>,
+    /// Elevations used without a heightmap.
+    pub terrain: bevy::asset::Handle<crate::map::terrain::TerrainMap>,
+    /// Its coarsest level is draped over the ground: the whole image is too big for a texture.
+    pub pyramid: bevy::asset::Handle<crate::map::tiles::TilePyramid>,
+    pub field: Option<crate::map::terrain3d::HeightField>,
+}
+
+/// Points laid with the ruler on the 2D map, in world coordinates; 'R' switches the ruler on and off.
+#[derive(bevy::ecs::system::Resource, Default)]
+pub struct Ruler {
+    pub active: bool,
+    pub points: Vec<bevy::math::Vec2>,
 }
 
 /// We will store the world position of the mouse cursor here.
@@ -47,3 +174,23 @@ pub struct Circle001Coords {
     pub x: f32,
     pub y: f32,
 }
+
+// Gizmo group for the hex overlay, so it can be switched on and off on its own.
+#[derive(Default, bevy::reflect::Reflect, bevy::gizmos::config::GizmoConfigGroup)]
+pub struct HexGridGizmos {}
+
+#[derive(bevy::ecs::system::Resource)]
+pub struct HexOverlay {
+    pub visible: bool,
+    /// Labels are hidden when the camera is zoomed out further than this projection scale.
+    pub label_max_scale: f32,
+}
+
+impl Default for HexOverlay {
+    fn default() -> Self {
+        HexOverlay {
+            visible: true,
+            label_max_scale: 2.5,
+        }
+    }
+}
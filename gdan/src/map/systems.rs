@@ -2,7 +2,12 @@
 
 use bevy::prelude::*;
 
-pub fn init_map(mut commands: Commands) {
+pub fn init_map(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    selected_scenario: Res<crate::game::resources::SelectedScenario>,
+    scenarios: Res<Assets<crate::game::scenario::Scenario>>,
+) {
     info!("init_map");
     /*
      * accessing resources using Res/ResMut
@@ -10,26 +15,45 @@ pub fn init_map(mut commands: Commands) {
      * creating/destroying entities, components, and resources using Commands (Commands)
      * sending/receiving events using EventWriter/EventReader
      */
-    let label_x: u32 = 1;
-    let label_y: u32 = 1;
-
-    let unit_x: f32 = 8819.;
-    let unit_y: f32 = 6299.;
-
-    commands.insert_resource(crate::map::resources::MapInfo {
-        scale: 1.,
-        unit_x,
-        unit_y,
-        label_x,
-        label_y,
-        // level 21: 10.meter/72.pixel
-        // level 22: 5.meter/72.pixel
-        satellite_map_level: 21,
-        meter_per_pixel: 10. / 72.,
+    let mut map_info = crate::map::resources::MapInfo::default();
+
+    // the rules read the ground from the terrain file, never from the picture
+    let map_image = match scenarios.get(&selected_scenario.0) {
+        Some(scenario) => {
+            map_info = scenario.file.map_info();
+            commands.insert_resource(crate::map::resources::MapTerrain(scenario.terrain.clone()));
+            scenario.file.map.clone()
+        }
+        None => {
+            warn!("scenario not loaded, showing the default map");
+            let map_image = crate::map::resources::DEFAULT_MAP_IMAGE;
+            commands.insert_resource(crate::map::resources::MapTerrain(
+                asset_server.load(crate::map::terrain::terrain_path(map_image)),
+            ));
+            map_image.to_string()
+        }
+    };
+    commands.insert_resource(map_info);
+    // the picture is chosen by `stream_map_tiles` once the tile pyramid is found or not
+    commands.insert_resource(crate::map::resources::MapImage::default());
+    commands.insert_resource(crate::map::resources::MapTiles {
+        pyramid: asset_server.load(crate::map::tiles::pyramid_path(&map_image)),
+        map_image,
+        tiles: bevy::utils::HashMap::new(),
     });
 
+    commands.insert_resource(crate::map::resources::Ruler::default());
+
     commands.insert_resource(crate::map::resources::Camera2dCoords(Vec2::new(0., 0.)));
 
+    commands.insert_resource(crate::map::resources::HoveredHex(None));
+
+    commands.insert_resource(crate::map::resources::SelectedHex(None));
+
+    commands.insert_resource(crate::rule::resources::OperSelection::default());
+
+    commands.insert_resource(crate::rule::resources::LosDrag(None));
+
     commands.insert_resource(crate::map::resources::Camera3dCoords(Vec3::new(
         // label_x as f32 / 2. * unit_x - unit_x / 4.,
         // label_y as f32 / 2. * unit_y - unit_y,
@@ -57,6 +81,30 @@ pub fn init_map(mut commands: Commands) {
     });
 }
 
+/// Rebuilds the hex layout whenever the map description changes.
+pub fn sync_hex_grid(
+    map_info: Res<crate::map::resources::MapInfo>,
+    mut hex_grid: ResMut<crate::map::resources::HexGrid>,
+) {
+    info!("sync_hex_grid");
+    hex_grid.0 = crate::map::hex::HexLayout::from_map_info(&map_info);
+}
+
+/// Moves entities placed on the grid to the centre of their hex, keeping their depth.
+pub fn hex_position_to_transform(
+    hex_grid: Res<crate::map::resources::HexGrid>,
+    mut query: Query<
+        (&crate::map::components::HexPosition, &mut Transform),
+        Changed<crate::map::components::HexPosition>,
+    >,
+) {
+    for (hex_position, mut transform) in query.iter_mut() {
+        let center = hex_grid.0.hex_to_world(hex_position.0);
+        transform.translation.x = center.x;
+        transform.translation.y = center.y;
+    }
+}
+
 pub fn camera2dbundle(
     mut commands: Commands,
     map_info: Res<crate::map::resources::MapInfo>,
@@ -144,7 +192,16 @@ pub fn map_menu(
         crate::MyAppState::MapMenu => {
             commands.spawn((
                 TextBundle::from_section(
-                    "show map",
+                    "show map\n \
+                    WASD or the arrows to pan, drag with the middle or right button, scroll to zoom\n \
+                    press 'G' to toggle the hex grid, 'R' to measure distances\n \
+                    left click a unit to select it, left click a shaded hex to move it\n \
+                    right click to cancel\n \
+                    drag from a unit to check its line of sight\n \
+                    press 'V' to change the side shown through the fog of war\n \
+                    shooting and close combat: select a unit, then click an enemy to attack it\n \
+                    press Space to end the phase, F5 to save, F9 to load\n \
+                    F10 to replay the last game: P play/pause, ',' '.' step, Home/End",
                     TextStyle {
                         font: asset_server.load("fonts/FiraMono-Medium.ttf"),
                         font_size: 24.,
@@ -265,38 +322,11 @@ pub fn map_menu_system(
     }
 }
 
-pub fn add_map(
-    mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    state: Res<State<crate::MyAppState>>,
-    map_info: Res<crate::map::resources::MapInfo>,
-) {
+pub fn add_map(mut commands: Commands, state: Res<State<crate::MyAppState>>) {
     info!("add_map");
     match state.get() {
         crate::MyAppState::MapMenu => {
-            // for x in 0..map_info.label_x {
-            // for y in 0..map_info.label_y {
-            commands.spawn((
-                SpriteBundle {
-                    texture: asset_server.load(
-                        // "wg/ncly/level21/".to_string()
-                        //     + (x + 1).to_string().as_str()
-                        //     + "-"
-                        //     + (y + 1).to_string().as_str()
-                        //     + ".png",
-                        "wg/mlx/map/1-8819p-6299p.png",
-                        // "branding/bevy_bird_dark.png",
-                    ),
-                    transform: Transform::from_xyz(map_info.unit_x / 2., map_info.unit_y / 2., 0.),
-                    ..default()
-                },
-                crate::map::entities::MapNC,
-                crate::map::entities::MapMenu,
-            ));
-            // }
-            // }
+            // the map picture, or the tiles of it in sight, are added by `stream_map_tiles`
 
             // commands.spawn((
             //     SpriteBundle {
@@ -323,35 +353,16 @@ pub fn add_map(
         }
 
         crate::MyAppState::Map3D => {
-            // plane
-            commands.spawn((
-                PbrBundle {
-                    mesh: meshes.add(Plane3d::default().mesh().size(5.0, 5.0)),
-                    material: materials.add(Color::rgb(0.3, 0.5, 0.3)),
-                    ..default()
-                },
-                crate::map::entities::MapMenu,
-            ));
+            // the ground and the units are added by `add_terrain3d` once the heights are known
 
-            // cube
+            // light, low from the south-west so that slopes show
             commands.spawn((
-                PbrBundle {
-                    mesh: meshes.add(Cuboid::new(1.0, 1.0, 1.0)),
-                    material: materials.add(Color::rgb(0.8, 0.7, 0.6)),
-                    transform: Transform::from_xyz(0.0, 0.5, 0.0),
-                    ..default()
-                },
-                crate::map::entities::MapMenu,
-            ));
-
-            // light
-            commands.spawn((
-                PointLightBundle {
-                    point_light: PointLight {
+                DirectionalLightBundle {
+                    directional_light: DirectionalLight {
                         shadows_enabled: true,
                         ..default()
                     },
-                    transform: Transform::from_xyz(4.0, 8.0, 4.0),
+                    transform: Transform::from_xyz(-4.0, 3.0, 4.0).looking_at(Vec3::ZERO, Vec3::Y),
                     ..default()
                 },
                 crate::map::entities::MapMenu,
@@ -387,84 +398,1031 @@ pub fn add_map(
 }
 
 pub fn draw_hexagon_2d(
+    mut gizmos: Gizmos<crate::map::resources::HexGridGizmos>,
+    hex_grid: Res<crate::map::resources::HexGrid>,
+    query_camera: Query<
+        (&OrthographicProjection, &GlobalTransform),
+        With<crate::map::entities::MapCamera2d>,
+    >,
+) {
+    let Ok((projection, camera_transform)) = query_camera.get_single() else {
+        return;
+    };
+    /*
+     * Only the hexes inside the camera view are drawn.
+     * Gizmo lines keep their width in screen pixels, so the grid stays sharp at every zoom level.
+     */
+    let center = camera_transform.translation().truncate();
+    let min = center + projection.area.min;
+    let max = center + projection.area.max;
+    for hex in hex_grid.0.hexes_in_rect(min, max) {
+        let corners = hex_grid.0.corners(hex);
+        gizmos.linestrip_2d(
+            corners.into_iter().chain(std::iter::once(corners[0])),
+            Color::rgba(0., 0., 0., 0.6),
+        );
+    }
+}
+
+pub fn toggle_hex_overlay(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    mut hex_overlay: ResMut<crate::map::resources::HexOverlay>,
+    mut config_store: ResMut<GizmoConfigStore>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyG) {
+        hex_overlay.visible = !hex_overlay.visible;
+        let (config, _) = config_store.config_mut::<crate::map::resources::HexGridGizmos>();
+        config.enabled = hex_overlay.visible;
+        info!("hex overlay: {}", hex_overlay.visible);
+    }
+}
+
+/*
+ * Hex labels exist only for the hexes in sight: they are spawned as hexes come into view and
+ * despawned as they leave it, so a big map never holds thousands of text entities.
+ * Far out the labels are too small to read and only cost fill rate, so there are none.
+ */
+pub fn update_hex_labels(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    map_info: Res<crate::map::resources::MapInfo>,
+    hex_grid: Res<crate::map::resources::HexGrid>,
+    hex_overlay: Res<crate::map::resources::HexOverlay>,
+    query_camera: Query<
+        (Ref<OrthographicProjection>, Ref<GlobalTransform>),
+        With<crate::map::entities::MapCamera2d>,
+    >,
+    query_labels: Query<(Entity, &crate::map::components::HexLabel)>,
+) {
+    let Ok((projection, camera_transform)) = query_camera.get_single() else {
+        return;
+    };
+    if !hex_overlay.is_changed()
+        && !hex_grid.is_changed()
+        && !projection.is_changed()
+        && !camera_transform.is_changed()
+    {
+        return;
+    }
+    let layout = &hex_grid.0;
+    let center = camera_transform.translation().truncate();
+    let in_view: bevy::utils::HashSet<crate::map::hex::Hex> =
+        if hex_overlay.visible && projection.scale <= hex_overlay.label_max_scale {
+            layout
+                .hexes_in_rect(center + projection.area.min, center + projection.area.max)
+                .collect()
+        } else {
+            Default::default()
+        };
+    let mut labelled = bevy::utils::HashSet::new();
+    for (entity, label) in query_labels.iter() {
+        // a new layout moves every hex, its labels are made again
+        if in_view.contains(&label.0) && !hex_grid.is_changed() {
+            labelled.insert(label.0);
+        } else {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+    let font = asset_server.load("fonts/FiraMono-Medium.ttf");
+    for hex in in_view.difference(&labelled) {
+        let center = layout.hex_to_world(*hex);
+        commands.spawn((
+            Text2dBundle {
+                text: Text::from_section(
+                    map_info.hex_label(*hex),
+                    TextStyle {
+                        font: font.clone(),
+                        font_size: layout.size / 4.,
+                        color: Color::BLACK,
+                    },
+                ),
+                // just below the top edge of the hex, above the sprites
+                transform: Transform::from_xyz(center.x, center.y + layout.size * 0.65, 2.),
+                ..default()
+            },
+            crate::map::components::HexLabel(*hex),
+            crate::map::entities::MapHexagon,
+            crate::map::entities::MapMenu,
+        ));
+    }
+}
+
+/// Buttons that pan the map when dragged; they only click a hex when let go where they were pressed.
+pub const DRAG_BUTTONS: [MouseButton; 2] = [MouseButton::Middle, MouseButton::Right];
+/// How far in pixels the cursor may move between press and release of a click.
+const CLICK_SLOP: f32 = 4.;
+
+#[allow(clippy::too_many_arguments)]
+pub fn pick_hex(
+    query_camera: Query<(&Camera, &GlobalTransform), With<crate::map::entities::MapCamera2d>>,
+    windows: Query<&Window, With<bevy::window::PrimaryWindow>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    query_interaction: Query<&Interaction>,
+    hex_grid: Res<crate::map::resources::HexGrid>,
+    map_info: Res<crate::map::resources::MapInfo>,
+    mut camera2d_coords: ResMut<crate::map::resources::Camera2dCoords>,
+    mut hovered_hex: ResMut<crate::map::resources::HoveredHex>,
+    mut selected_hex: ResMut<crate::map::resources::SelectedHex>,
+    mut hex_clicked: EventWriter<crate::map::events::HexClicked>,
+    mut pressed_at: Local<bevy::utils::HashMap<MouseButton, Vec2>>,
+) {
+    let (Ok((camera, camera_transform)), Ok(window)) =
+        (query_camera.get_single(), windows.get_single())
+    else {
+        return;
+    };
+    let cursor = window.cursor_position();
+    for button in buttons.get_just_pressed() {
+        if let (true, Some(cursor)) = (DRAG_BUTTONS.contains(button), cursor) {
+            pressed_at.insert(*button, cursor);
+        }
+    }
+
+    // Calculate a world position based on the cursor's position.
+    let Some((cursor, point)) = cursor.and_then(|cursor_position| {
+        camera
+            .viewport_to_world_2d(camera_transform, cursor_position)
+            .map(|point| (cursor_position, point))
+    }) else {
+        if hovered_hex.0.is_some() {
+            hovered_hex.0 = None;
+        }
+        return;
+    };
+    camera2d_coords.0 = point;
+
+    let hex = hex_grid.0.world_to_hex(point);
+    let hovered = hex_grid.0.contains(hex).then_some(hex);
+    if hovered_hex.0 != hovered {
+        hovered_hex.0 = hovered;
+    }
+
+    // clicks on buttons belong to the UI, not to the map below it
+    if query_interaction
+        .iter()
+        .any(|interaction| *interaction != Interaction::None)
+    {
+        return;
+    }
+    let Some(hex) = hovered else {
+        return;
+    };
+    // a drag of the map is no click
+    let clicks: Vec<MouseButton> = buttons
+        .get_just_pressed()
+        .filter(|button| !DRAG_BUTTONS.contains(button))
+        .chain(buttons.get_just_released().filter(|button| {
+            pressed_at
+                .remove(*button)
+                .is_some_and(|from| from.distance(cursor) <= CLICK_SLOP)
+        }))
+        .copied()
+        .collect();
+    for button in clicks {
+        match button {
+            MouseButton::Left => selected_hex.0 = Some(hex),
+            MouseButton::Right => selected_hex.0 = None,
+            _ => (),
+        }
+        info!("hex {} clicked with {:?}", map_info.hex_label(hex), button);
+        hex_clicked.send(crate::map::events::HexClicked { hex, button });
+    }
+}
+
+pub fn draw_hex_highlight(
     mut gizmos: Gizmos,
-    circle_001_coords: Res<crate::map::resources::Circle001Coords>,
+    hex_grid: Res<crate::map::resources::HexGrid>,
+    hovered_hex: Res<crate::map::resources::HoveredHex>,
+    selected_hex: Res<crate::map::resources::SelectedHex>,
 ) {
-    gizmos.circle_2d(
-        bevy::prelude::Vec2 {
-            x: circle_001_coords.x,
-            y: circle_001_coords.y,
-        },
-        10.,
-        Color::WHITE,
+    let mut outline = |hex: crate::map::hex::Hex, inset: f32, color: Color| {
+        let center = hex_grid.0.hex_to_world(hex);
+        let corners = hex_grid
+            .0
+            .corners(hex)
+            .map(|corner| center + (corner - center) * inset);
+        gizmos.linestrip_2d(
+            corners.into_iter().chain(std::iter::once(corners[0])),
+            color,
+        );
+    };
+    if let Some(hex) = selected_hex.0 {
+        for inset in [0.98, 0.94, 0.9] {
+            outline(hex, inset, Color::YELLOW);
+        }
+    }
+    if let Some(hex) = hovered_hex.0 {
+        outline(hex, 0.96, Color::WHITE);
+    }
+}
+
+pub const ROAD_COLOR: Color = Color::rgb(0.55, 0.35, 0.1);
+pub const RIVER_COLOR: Color = Color::rgb(0.1, 0.45, 1.);
+pub const WALL_COLOR: Color = Color::rgb(0.25, 0.25, 0.25);
+
+/*
+ * Hexside features as lines over the 2D map, for those with a hex between `min` and `max`:
+ * roads and bridges from hex centre to hex centre, the bridge railed where it crosses the edge,
+ * rivers along the edge, walls as a zigzag along it.
+ */
+pub fn draw_hexside_features(
+    gizmos: &mut Gizmos,
+    layout: &crate::map::hex::HexLayout,
+    hexsides: &crate::map::hexside::HexsideMap,
+    min: Vec2,
+    max: Vec2,
+) {
+    let margin = Vec2::splat(layout.size * 2.);
+    let view = Rect::from_corners(min - margin, max + margin);
+    for (hexside, features) in hexsides.features.iter() {
+        let [hex, neighbor] = hexside.hexes();
+        let (center, other) = (layout.hex_to_world(hex), layout.hex_to_world(neighbor));
+        if !view.contains(center) && !view.contains(other) {
+            continue;
+        }
+        let [corner, other_corner] = layout.edge(hexside.hex, hexside.direction);
+        for feature in features.iter() {
+            match feature {
+                crate::map::hexside::HexsideFeature::Road => {
+                    gizmos.line_2d(center, other, ROAD_COLOR);
+                }
+                crate::map::hexside::HexsideFeature::Bridge => {
+                    gizmos.line_2d(center, other, ROAD_COLOR);
+                    let middle = (center + other) / 2.;
+                    let along = (other - center).normalize_or_zero() * layout.size * 0.3;
+                    let across = along.perp() / 2.;
+                    for side in [across, -across] {
+                        gizmos.line_2d(middle + side - along, middle + side + along, WALL_COLOR);
+                    }
+                }
+                crate::map::hexside::HexsideFeature::River => {
+                    gizmos.line_2d(corner, other_corner, RIVER_COLOR);
+                    // a second line just inside each hex, so that rivers stand out from the grid
+                    for center in [center, other] {
+                        let inside = |point: Vec2| center + (point - center) * 0.96;
+                        gizmos.line_2d(inside(corner), inside(other_corner), RIVER_COLOR);
+                    }
+                }
+                crate::map::hexside::HexsideFeature::Wall => {
+                    let teeth = 6;
+                    let across = (other - center).normalize_or_zero() * layout.size * 0.06;
+                    gizmos.linestrip_2d(
+                        (0..=teeth * 2).map(|i| {
+                            let point = corner.lerp(other_corner, i as f32 / (teeth * 2) as f32);
+                            if i % 2 == 0 {
+                                point - across
+                            } else {
+                                point + across
+                            }
+                        }),
+                        WALL_COLOR,
+                    );
+                }
+            }
+        }
+    }
+}
+
+pub fn draw_hexsides(
+    mut gizmos: Gizmos,
+    hex_grid: Res<crate::map::resources::HexGrid>,
+    map_terrain: Res<crate::map::resources::MapTerrain>,
+    terrains: Res<Assets<crate::map::terrain::TerrainMap>>,
+    query_camera: Query<
+        (&OrthographicProjection, &GlobalTransform),
+        With<crate::map::entities::MapCamera2d>,
+    >,
+) {
+    let (Some(terrain), Ok((projection, camera_transform))) =
+        (terrains.get(&map_terrain.0), query_camera.get_single())
+    else {
+        return;
+    };
+    let center = camera_transform.translation().truncate();
+    draw_hexside_features(
+        &mut gizmos,
+        &hex_grid.0,
+        &terrain.hexsides,
+        center + projection.area.min,
+        center + projection.area.max,
     );
 }
 
+pub fn draw_objectives(
+    mut gizmos: Gizmos,
+    hex_grid: Res<crate::map::resources::HexGrid>,
+    selected_scenario: Res<crate::game::resources::SelectedScenario>,
+    scenarios: Res<Assets<crate::game::scenario::Scenario>>,
+) {
+    let Some(scenario) = scenarios.get(&selected_scenario.0) else {
+        return;
+    };
+    for objective in scenario.file.objectives.iter() {
+        let color = match objective.owner {
+            Some(crate::oper::components::Side::Red) => Color::RED,
+            Some(crate::oper::components::Side::Blue) => Color::BLUE,
+            None => Color::WHITE,
+        };
+        let center = hex_grid.0.hex_to_world(objective.hex());
+        for radius in [0.75, 0.8] {
+            gizmos
+                .circle_2d(center, hex_grid.0.size * radius, color)
+                .segments(48);
+        }
+    }
+}
+
+/*
+ * The 2D map camera:
+ * WASD or the arrow keys pan at the same speed on screen whatever the zoom,
+ * dragging with the middle or right mouse button pans the map along with the cursor,
+ * the scroll wheel, touchpad scrolling and pinching zoom in and out around the cursor.
+ * The view never leaves the map; a map smaller than the window is centred in it.
+ */
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
 pub fn map2d_scale_wander(
-    mut query_camera_projection: Query<
-        &mut OrthographicProjection,
-        With<crate::map::entities::MapMenu>,
-    >,
-    mut query_camera2d_transform: Query<
-        &mut Transform,
+    mut query_camera: Query<
+        (&mut Transform, &mut OrthographicProjection),
         (
             With<crate::map::entities::MapCamera2d>,
             Without<crate::map::entities::MapCamera3d>,
         ),
     >,
+    windows: Query<&Window, With<bevy::window::PrimaryWindow>>,
+    query_interaction: Query<&Interaction>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
     mut scroll_evr: EventReader<bevy::input::mouse::MouseWheel>,
-    // q_windows: Query<&Window, With<bevy::window::PrimaryWindow>>,
-    // mouse_coords: ResMut<crate::map::resources::MouseCoords>,
-    map_info: ResMut<crate::map::resources::MapInfo>,
+    mut magnify_evr: EventReader<bevy::input::touchpad::TouchpadMagnify>,
+    time: Res<Time>,
+    controls: Res<crate::map::resources::MapCameraControls>,
+    map_info: Res<crate::map::resources::MapInfo>,
+    mut drag_from: Local<Option<Vec2>>,
+    mut shortcut_keys: Local<bevy::utils::HashSet<KeyCode>>,
 ) {
+    let (Ok((mut transform, mut projection)), Ok(window)) =
+        (query_camera.get_single_mut(), windows.get_single())
+    else {
+        return;
+    };
     /*
      * The cursor position and any other window (screen-space) coordinates follow the same conventions as UI.
      */
-    let mut projection = query_camera_projection.single_mut();
+    let half_window = Vec2::new(window.width(), window.height()) / 2.;
+    let cursor = window.cursor_position();
+    // cursor offset from the window centre, y up as in the world
+    let anchor = cursor.map_or(Vec2::ZERO, |cursor| {
+        Vec2::new(cursor.x - half_window.x, half_window.y - cursor.y)
+    });
+
+    let mut zoom = 1.;
     for ev in scroll_evr.read() {
-        match ev.unit {
-            bevy::input::mouse::MouseScrollUnit::Line => {
-                if ev.y > 0. && projection.scale > 1. {
-                    projection.scale /= 1.25;
-                } else if ev.y < 0. && projection.scale < 5. {
-                    projection.scale *= 1.25;
-                }
+        zoom *= match ev.unit {
+            bevy::input::mouse::MouseScrollUnit::Line => controls.line_zoom.powf(-ev.y),
+            bevy::input::mouse::MouseScrollUnit::Pixel => controls.pixel_zoom.powf(-ev.y),
+        };
+    }
+    for ev in magnify_evr.read() {
+        zoom /= 1. + ev.0;
+    }
+    let scale = (projection.scale * zoom).clamp(controls.min_scale, controls.max_scale);
+    if scale != projection.scale {
+        // the point under the cursor stays under it
+        let point = transform.translation.truncate() + anchor * projection.scale;
+        let center = point - anchor * scale;
+        transform.translation.x = center.x;
+        transform.translation.y = center.y;
+        projection.scale = scale;
+    }
+
+    // Ctrl+S and the like are shortcuts, not panning, until the key is let go, Ctrl or not
+    if keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        shortcut_keys.extend(keyboard_input.get_pressed().copied());
+    }
+    shortcut_keys.retain(|key| keyboard_input.pressed(*key));
+    let pressed = |key: KeyCode| keyboard_input.pressed(key) && !shortcut_keys.contains(&key);
+    let mut direction = Vec2::ZERO;
+    if pressed(KeyCode::KeyW) || pressed(KeyCode::ArrowUp) {
+        direction.y += 1.;
+    }
+    if pressed(KeyCode::KeyS) || pressed(KeyCode::ArrowDown) {
+        direction.y -= 1.;
+    }
+    if pressed(KeyCode::KeyD) || pressed(KeyCode::ArrowRight) {
+        direction.x += 1.;
+    }
+    if pressed(KeyCode::KeyA) || pressed(KeyCode::ArrowLeft) {
+        direction.x -= 1.;
+    }
+    let mut pan = direction.normalize_or_zero()
+        * controls.pan_speed
+        * time.delta_seconds()
+        * projection.scale;
+
+    // drags starting on a button belong to the UI, not to the map below it
+    if buttons.any_just_pressed(DRAG_BUTTONS)
+        && query_interaction
+            .iter()
+            .all(|interaction| *interaction == Interaction::None)
+    {
+        *drag_from = cursor;
+    }
+    if !buttons.any_pressed(DRAG_BUTTONS) {
+        *drag_from = None;
+    }
+    if let (Some(from), Some(to)) = (*drag_from, cursor) {
+        pan += Vec2::new(from.x - to.x, to.y - from.y) * projection.scale;
+        *drag_from = Some(to);
+    }
+
+    let center = transform.translation.truncate() + pan;
+    let half_view = half_window * projection.scale;
+    let clamp = |center: f32, half_view: f32, size: f32| {
+        if 2. * half_view >= size {
+            size / 2.
+        } else {
+            center.clamp(half_view, size - half_view)
+        }
+    };
+    let x = clamp(center.x, half_view.x, map_info.unit_x);
+    let y = clamp(center.y, half_view.y, map_info.unit_y);
+    if transform.translation.x != x || transform.translation.y != y {
+        transform.translation.x = x;
+        transform.translation.y = y;
+    }
+}
+
+/// The minimap sits in the bottom right corner of the map, above the network line.
+pub fn minimap(
+    mut commands: Commands,
+    map_info: Res<crate::map::resources::MapInfo>,
+    map_image: Res<crate::map::resources::MapImage>,
+) {
+    info!("minimap");
+    let width = 240.;
+    commands
+        .spawn((
+            ImageBundle {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    right: Val::Px(10.),
+                    bottom: Val::Px(40.),
+                    width: Val::Px(width),
+                    height: Val::Px(width * map_info.unit_y / map_info.unit_x),
+                    overflow: Overflow::clip(),
+                    ..default()
+                },
+                image: UiImage::new(map_image.0.clone()),
+                focus_policy: bevy::ui::FocusPolicy::Block,
+                ..default()
+            },
+            Interaction::default(),
+            bevy::ui::RelativeCursorPosition::default(),
+            crate::map::entities::Minimap,
+            crate::map::entities::MapMenu,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                NodeBundle {
+                    style: Style {
+                        position_type: PositionType::Absolute,
+                        border: UiRect::all(Val::Px(2.)),
+                        ..default()
+                    },
+                    border_color: Color::YELLOW.into(),
+                    ..default()
+                },
+                crate::map::entities::MinimapViewport,
+                crate::map::entities::MapMenu,
+            ));
+        });
+}
+
+/// Centres the camera on the point of the minimap pressed, as long as the button is held.
+pub fn minimap_navigate(
+    query_minimap: Query<
+        (&Interaction, &bevy::ui::RelativeCursorPosition),
+        With<crate::map::entities::Minimap>,
+    >,
+    mut query_camera: Query<&mut Transform, With<crate::map::entities::MapCamera2d>>,
+    map_info: Res<crate::map::resources::MapInfo>,
+) {
+    for (interaction, cursor) in query_minimap.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        // (0, 0) is the top left corner of the minimap, (1, 1) its bottom right corner
+        let (Some(point), Ok(mut transform)) = (cursor.normalized, query_camera.get_single_mut())
+        else {
+            continue;
+        };
+        let point = point.clamp(Vec2::ZERO, Vec2::ONE);
+        transform.translation.x = point.x * map_info.unit_x;
+        transform.translation.y = (1. - point.y) * map_info.unit_y;
+    }
+}
+
+pub fn update_minimap_viewport(
+    query_camera: Query<
+        (&Transform, &OrthographicProjection),
+        With<crate::map::entities::MapCamera2d>,
+    >,
+    windows: Query<&Window, With<bevy::window::PrimaryWindow>>,
+    map_info: Res<crate::map::resources::MapInfo>,
+    mut query_viewport: Query<&mut Style, With<crate::map::entities::MinimapViewport>>,
+) {
+    let (Ok((transform, projection)), Ok(window)) =
+        (query_camera.get_single(), windows.get_single())
+    else {
+        return;
+    };
+    let map = Vec2::new(map_info.unit_x, map_info.unit_y);
+    let half_view = Vec2::new(window.width(), window.height()) / 2. * projection.scale;
+    let center = transform.translation.truncate();
+    // in map fractions, y down as on the minimap
+    let min = ((center - half_view) / map).clamp(Vec2::ZERO, Vec2::ONE);
+    let max = ((center + half_view) / map).clamp(Vec2::ZERO, Vec2::ONE);
+    for mut style in query_viewport.iter_mut() {
+        let viewport = Style {
+            left: Val::Percent(min.x * 100.),
+            top: Val::Percent((1. - max.y) * 100.),
+            width: Val::Percent((max.x - min.x) * 100.),
+            height: Val::Percent((max.y - min.y) * 100.),
+            ..style.clone()
+        };
+        style.set_if_neq(viewport);
+    }
+}
+
+/// The status bar sits in the bottom left corner of the map, below the combat log.
+pub fn status_bar(mut commands: Commands, asset_server: Res<AssetServer>) {
+    info!("status_bar");
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                font_size: 18.,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(10.),
+            bottom: Val::Px(10.),
+            ..default()
+        }),
+        crate::map::entities::StatusBarText,
+        crate::map::entities::MapMenu,
+    ));
+}
+
+pub fn ruler_active(ruler: Res<crate::map::resources::Ruler>) -> bool {
+    ruler.active
+}
+
+/*
+ * 'R' takes the ruler out and puts it away again. While it is out, left clicks lay points on the
+ * map instead of giving orders, and Backspace takes the last point back.
+ */
+pub fn ruler_points(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    buttons: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window, With<bevy::window::PrimaryWindow>>,
+    query_interaction: Query<&Interaction>,
+    camera2d_coords: Res<crate::map::resources::Camera2dCoords>,
+    mut ruler: ResMut<crate::map::resources::Ruler>,
+) {
+    if keyboard_input.just_pressed(KeyCode::KeyR) {
+        ruler.active = !ruler.active;
+        ruler.points.clear();
+        info!("ruler: {}", ruler.active);
+    }
+    if !ruler.active {
+        return;
+    }
+    if keyboard_input.just_pressed(KeyCode::Backspace) {
+        ruler.points.pop();
+    }
+    // clicks on buttons belong to the UI, not to the map below it
+    let on_map = windows
+        .get_single()
+        .is_ok_and(|window| window.cursor_position().is_some())
+        && query_interaction
+            .iter()
+            .all(|interaction| *interaction == Interaction::None);
+    if buttons.just_pressed(MouseButton::Left) && on_map {
+        ruler.points.push(camera2d_coords.0);
+    }
+}
+
+/// The ruler's legs, with a last one following the cursor.
+pub fn draw_ruler(
+    mut gizmos: Gizmos,
+    ruler: Res<crate::map::resources::Ruler>,
+    camera2d_coords: Res<crate::map::resources::Camera2dCoords>,
+    hex_grid: Res<crate::map::resources::HexGrid>,
+) {
+    if !ruler.active {
+        return;
+    }
+    let radius = hex_grid.0.size / 8.;
+    for point in ruler.points.iter() {
+        gizmos.circle_2d(*point, radius, Color::ORANGE);
+    }
+    gizmos.linestrip_2d(ruler.points.iter().copied(), Color::ORANGE);
+    if let Some(last) = ruler.points.last() {
+        gizmos.line_2d(*last, camera2d_coords.0, Color::rgba(1., 0.65, 0., 0.5));
+    }
+}
+
+/*
+ * The status bar reads, with a georeferenced map:
+ *
+ *   hex 1415  E 1234 m N 5678 m  39.91234°N 116.41234°E
+ *   ruler: 2 legs 1520 m 11 hexes, straight 1190 m 9 hexes
+ *
+ * Metres are counted east and north from the bottom left corner of the map. Hexes are counted
+ * between the hexes the points are in, leg by leg.
+ */
+pub fn update_status_bar(
+    map_info: Res<crate::map::resources::MapInfo>,
+    hex_grid: Res<crate::map::resources::HexGrid>,
+    hovered_hex: Res<crate::map::resources::HoveredHex>,
+    camera2d_coords: Res<crate::map::resources::Camera2dCoords>,
+    ruler: Res<crate::map::resources::Ruler>,
+    mut query_text: Query<&mut Text, With<crate::map::entities::StatusBarText>>,
+) {
+    let geo = map_info.geo();
+    let point = camera2d_coords.0;
+    let mut lines = Vec::new();
+    if let Some(hex) = hovered_hex.0 {
+        let meters = geo.meters(point);
+        let mut line = format!(
+            "hex {}  E {:.0} m N {:.0} m",
+            map_info.hex_label(hex),
+            meters.x,
+            meters.y
+        );
+        if map_info.origin.is_some() {
+            line += &format!("  {}", geo.to_geo(point));
+        }
+        lines.push(line);
+    }
+    if ruler.active {
+        let hexes = |from: Vec2, to: Vec2| {
+            hex_grid
+                .0
+                .world_to_hex(from)
+                .distance(hex_grid.0.world_to_hex(to))
+        };
+        lines.push(match (ruler.points.first(), ruler.points.last()) {
+            (Some(first), Some(last)) if ruler.points.len() > 1 => {
+                let legs = ruler.points.windows(2);
+                format!(
+                    "ruler: {} legs {:.0} m {} hexes, straight {:.0} m {} hexes",
+                    legs.len(),
+                    legs.clone()
+                        .map(|leg| geo.distance(leg[0], leg[1]))
+                        .sum::<f32>(),
+                    legs.map(|leg| hexes(leg[0], leg[1])).sum::<i32>(),
+                    geo.distance(*first, *last),
+                    hexes(*first, *last)
+                )
             }
-            bevy::input::mouse::MouseScrollUnit::Pixel => {
-                // println!(
-                //     "Scroll (pixel units): vertical: {}, horizontal: {}",
-                //     ev.y, ev.x
-                // );
-                // map_info.scale -= 0.1;
+            _ => {
+                "ruler: click to lay points, Backspace to take one back, R to put away".to_string()
             }
+        });
+    }
+    let label = lines.join("\n");
+    for mut text in query_text.iter_mut() {
+        if text.sections[0].value != label {
+            text.sections[0].value = label.clone();
         }
     }
+}
 
-    let mut transform = query_camera2d_transform.single_mut();
-
-    if (keyboard_input.pressed(KeyCode::KeyW) || keyboard_input.pressed(KeyCode::ArrowUp))
-        && transform.translation.y < map_info.unit_y
-    {
-        transform.translation.y += 10.;
+/*
+ * Shows the map on the 2D map: the tiles in sight at the level the zoom needs when the map
+ * image has a tile pyramid, otherwise the whole map image as one sprite.
+ * The coarsest level always lies under the others. Every frame the tiles well out of sight go,
+ * and so do those of other levels, but for the coarser tiles already there under a tile that is
+ * still loading.
+ */
+#[allow(clippy::too_many_arguments)]
+pub fn stream_map_tiles(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    pyramids: Res<Assets<crate::map::tiles::TilePyramid>>,
+    mut map_tiles: ResMut<crate::map::resources::MapTiles>,
+    mut map_image: ResMut<crate::map::resources::MapImage>,
+    map_info: Res<crate::map::resources::MapInfo>,
+    query_camera: Query<
+        (&Transform, &OrthographicProjection),
+        With<crate::map::entities::MapCamera2d>,
+    >,
+    windows: Query<&Window, With<bevy::window::PrimaryWindow>>,
+    query_texture: Query<&Handle<Image>>,
+) {
+    let Some(pyramid) = pyramids.get(&map_tiles.pyramid) else {
+        if map_image.0 == Handle::default()
+            && asset_server.load_state(&map_tiles.pyramid) == bevy::asset::LoadState::Failed
+        {
+            info!(
+                "no tiles for {}, showing the whole image",
+                map_tiles.map_image
+            );
+            map_image.0 = asset_server.load(map_tiles.map_image.clone());
+            commands.spawn((
+                SpriteBundle {
+                    texture: map_image.0.clone(),
+                    transform: Transform::from_xyz(map_info.unit_x / 2., map_info.unit_y / 2., 0.),
+                    ..default()
+                },
+                crate::map::entities::MapNC,
+                crate::map::entities::MapMenu,
+            ));
+        }
+        return;
+    };
+    if map_image.0 == Handle::default() {
+        map_image.0 = asset_server.load(crate::map::tiles::tile_path(
+            &map_tiles.map_image,
+            crate::map::tiles::TileKey {
+                level: pyramid.min_level,
+                col: 1,
+                row: 1,
+            },
+        ));
+    }
+    let (Ok((transform, projection)), Ok(window)) =
+        (query_camera.get_single(), windows.get_single())
+    else {
+        return;
+    };
+    // a quarter of the view around it is loaded ahead, and half of it is kept
+    let center = transform.translation.truncate();
+    let half_window = Vec2::new(window.width(), window.height()) * projection.scale;
+    let view = Rect::from_center_half_size(center, half_window * 0.75);
+    let kept_view = Rect::from_center_half_size(center, half_window);
+    let level = pyramid.level_for_scale(projection.scale);
+    let mut in_sight = pyramid.tiles_in(pyramid.min_level, view);
+    in_sight.extend(pyramid.tiles_in(level, view));
+    for key in in_sight.iter() {
+        if map_tiles.tiles.contains_key(key) {
+            continue;
+        }
+        let rect = pyramid.tile_rect(*key);
+        let entity = commands
+            .spawn((
+                SpriteBundle {
+                    sprite: Sprite {
+                        custom_size: Some(rect.size()),
+                        ..default()
+                    },
+                    texture: asset_server
+                        .load(crate::map::tiles::tile_path(&map_tiles.map_image, *key)),
+                    // finer levels above coarser ones, all below the hex grid
+                    transform: Transform::from_xyz(
+                        rect.center().x,
+                        rect.center().y,
+                        key.level as f32 * 0.001,
+                    ),
+                    ..default()
+                },
+                crate::map::entities::MapNC,
+                crate::map::entities::MapMenu,
+            ))
+            .id();
+        map_tiles.tiles.insert(*key, entity);
     }
+    let loaded = |entity: Entity| {
+        query_texture.get(entity).is_ok_and(|texture| {
+            asset_server.load_state(texture) != bevy::asset::LoadState::Loading
+        })
+    };
+    let mut kept: bevy::utils::HashSet<crate::map::tiles::TileKey> =
+        pyramid.tiles_in(level, kept_view).into_iter().collect();
+    for key in in_sight.iter() {
+        if loaded(map_tiles.tiles[key]) {
+            continue;
+        }
+        // the nearest coarser tile already there stands in for it
+        let mut parent = key.parent();
+        while let Some(coarser) = parent.filter(|coarser| coarser.level > pyramid.min_level) {
+            if map_tiles.tiles.contains_key(&coarser) {
+                kept.insert(coarser);
+                break;
+            }
+            parent = coarser.parent();
+        }
+    }
+    map_tiles.tiles.retain(|key, entity| {
+        let keep = key.level == pyramid.min_level || kept.contains(key);
+        if !keep {
+            commands.entity(*entity).despawn();
+        }
+        keep
+    });
+}
 
-    if (keyboard_input.pressed(KeyCode::KeyS) || keyboard_input.pressed(KeyCode::ArrowDown))
-        && transform.translation.y > 0.
-    {
-        transform.translation.y -= 10.;
+/// Shows the picture of the whole map on the minimap once it is chosen.
+pub fn update_minimap_image(
+    map_image: Res<crate::map::resources::MapImage>,
+    mut query_minimap: Query<&mut UiImage, With<crate::map::entities::Minimap>>,
+) {
+    for mut image in query_minimap.iter_mut() {
+        image.texture = map_image.0.clone();
     }
+}
 
-    if (keyboard_input.pressed(KeyCode::KeyD) || keyboard_input.pressed(KeyCode::ArrowRight))
-        && transform.translation.x < map_info.unit_x
-    {
-        transform.translation.x += 10.;
+/// The 3D map shows the map of the 2D one, or the default map when opened on its own.
+pub fn init_terrain3d(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    map_tiles: Res<crate::map::resources::MapTiles>,
+    map_terrain: Res<crate::map::resources::MapTerrain>,
+) {
+    info!("init_terrain3d");
+    let map_image = if map_tiles.map_image.is_empty() {
+        crate::map::resources::DEFAULT_MAP_IMAGE.to_string()
+    } else {
+        map_tiles.map_image.clone()
+    };
+    let terrain = if map_terrain.0 == Handle::default() {
+        asset_server.load(crate::map::terrain::terrain_path(&map_image))
+    } else {
+        map_terrain.0.clone()
+    };
+    commands.insert_resource(crate::map::resources::Terrain3d {
+        heightmap: asset_server.load(crate::map::terrain3d::heightmap_path(&map_image)),
+        terrain,
+        pyramid: asset_server.load(crate::map::tiles::pyramid_path(&map_image)),
+        map_image,
+        field: None,
+    });
+}
+
+pub fn terrain3d_pending(terrain3d: Res<crate::map::resources::Terrain3d>) -> bool {
+    terrain3d.field.is_none()
+}
+
+/*
+ * Builds the ground of the 3D map from its heightmap, or from the terrain file without one,
+ * drapes the coarsest tile of the map image over it and stands the scenario's units on it where
+ * they start. Without a tile pyramid the ground is left bare.
+ */
+#[allow(clippy::too_many_arguments)]
+pub fn add_terrain3d(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    asset_server: Res<AssetServer>,
+    heightmaps: Res<Assets<crate::map::terrain3d::Heightmap>>,
+    terrains: Res<Assets<crate::map::terrain::TerrainMap>>,
+    pyramids: Res<Assets<crate::map::tiles::TilePyramid>>,
+    scenarios: Res<Assets<crate::game::scenario::Scenario>>,
+    map_info: Res<crate::map::resources::MapInfo>,
+    hex_grid: Res<crate::map::resources::HexGrid>,
+    selected_scenario: Res<crate::game::resources::SelectedScenario>,
+    mut terrain3d: ResMut<crate::map::resources::Terrain3d>,
+) {
+    let map_size = Vec2::new(map_info.unit_x, map_info.unit_y);
+    let failed = |id: bevy::asset::UntypedAssetId| {
+        asset_server.load_state(id) == bevy::asset::LoadState::Failed
+    };
+    let texture = match pyramids.get(&terrain3d.pyramid) {
+        Some(pyramid) => Some(asset_server.load(crate::map::tiles::tile_path(
+            &terrain3d.map_image,
+            crate::map::tiles::TileKey {
+                level: pyramid.min_level,
+                col: 1,
+                row: 1,
+            },
+        ))),
+        None if failed(terrain3d.pyramid.id().untyped()) => {
+            info!(
+                "no tiles for {}, the 3D map is left bare until `gdan tile` cuts them",
+                terrain3d.map_image
+            );
+            None
+        }
+        None => return,
+    };
+    let field = if let Some(heightmap) = heightmaps.get(&terrain3d.heightmap) {
+        crate::map::terrain3d::HeightField::from_heightmap(heightmap, map_size)
+    } else if failed(terrain3d.heightmap.id().untyped()) {
+        let default_terrain = crate::map::terrain::TerrainMap::default();
+        let terrain = match terrains.get(&terrain3d.terrain) {
+            Some(terrain) => terrain,
+            None if failed(terrain3d.terrain.id().untyped()) => &default_terrain,
+            None => return,
+        };
+        info!("no heightmap, raising the 3D map from the terrain elevations");
+        crate::map::terrain3d::HeightField::from_terrain(terrain, &hex_grid.0, map_size)
+    } else {
+        return;
+    };
+    let layout = crate::map::terrain3d::Layout3d::new(&map_info);
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(field.mesh(&layout)),
+            material: materials.add(StandardMaterial {
+                base_color_texture: texture,
+                perceptual_roughness: 1.,
+                ..default()
+            }),
+            ..default()
+        },
+        crate::map::entities::Map3d,
+        crate::map::entities::MapMenu,
+    ));
+    if let Some(scenario) = scenarios.get(&selected_scenario.0) {
+        let width = hex_grid.0.size * layout.units_per_pixel;
+        let mesh = meshes.add(Cuboid::new(width, width / 2., width));
+        let red = materials.add(Color::RED);
+        let blue = materials.add(Color::BLUE);
+        for unit in scenario.file.units.iter() {
+            let hex = crate::map::hex::Hex::from_offset(crate::map::hex::OffsetCoord::new(
+                unit.col, unit.row,
+            ));
+            let point = hex_grid.0.hex_to_world(hex);
+            let ground = layout.to_3d(point, field.height_at(point));
+            commands.spawn((
+                PbrBundle {
+                    mesh: mesh.clone(),
+                    material: match unit.side {
+                        crate::oper::components::Side::Red => red.clone(),
+                        crate::oper::components::Side::Blue => blue.clone(),
+                    },
+                    transform: Transform::from_translation(ground + Vec3::Y * width / 4.),
+                    ..default()
+                },
+                crate::map::entities::Map3d,
+                crate::map::entities::MapMenu,
+            ));
+        }
     }
+    terrain3d.field = Some(field);
+}
 
-    if (keyboard_input.pressed(KeyCode::KeyA) || keyboard_input.pressed(KeyCode::ArrowLeft))
-        && transform.translation.x > 0.
-    {
-        transform.translation.x -= 10.;
+/*
+ * Hexside features over the 3D map, draped on the ground a little above it: roads and rivers
+ * follow the ground, a bridge runs straight between its ends, a wall stands as a low fence.
+ */
+pub fn draw_hexsides_3d(
+    mut gizmos: Gizmos,
+    hex_grid: Res<crate::map::resources::HexGrid>,
+    map_info: Res<crate::map::resources::MapInfo>,
+    map_terrain: Res<crate::map::resources::MapTerrain>,
+    terrains: Res<Assets<crate::map::terrain::TerrainMap>>,
+    terrain3d: Res<crate::map::resources::Terrain3d>,
+) {
+    let (Some(terrain), Some(field)) = (terrains.get(&map_terrain.0), terrain3d.field.as_ref())
+    else {
+        return;
+    };
+    let layout = crate::map::terrain3d::Layout3d::new(&map_info);
+    // metres above the ground, so that the lines are not hidden in it
+    let lift = 2.;
+    let draped = |from: Vec2, to: Vec2, up: f32| {
+        let steps = 8;
+        (0..=steps).map(move |i| {
+            let point = from.lerp(to, i as f32 / steps as f32);
+            layout.to_3d(point, field.height_at(point) + lift + up)
+        })
+    };
+    for (hexside, features) in terrain.hexsides.features.iter() {
+        let [hex, neighbor] = hexside.hexes();
+        let (center, other) = (
+            hex_grid.0.hex_to_world(hex),
+            hex_grid.0.hex_to_world(neighbor),
+        );
+        let [corner, other_corner] = hex_grid.0.edge(hexside.hex, hexside.direction);
+        for feature in features.iter() {
+            match feature {
+                crate::map::hexside::HexsideFeature::Road => {
+                    gizmos.linestrip(draped(center, other, 0.), ROAD_COLOR);
+                }
+                crate::map::hexside::HexsideFeature::Bridge => {
+                    let height = field.height_at(center).max(field.height_at(other)) + lift;
+                    gizmos.line(
+                        layout.to_3d(center, height),
+                        layout.to_3d(other, height),
+                        ROAD_COLOR,
+                    );
+                }
+                crate::map::hexside::HexsideFeature::River => {
+                    gizmos.linestrip(draped(corner, other_corner, 0.), RIVER_COLOR);
+                }
+                crate::map::hexside::HexsideFeature::Wall => {
+                    for up in [0., 3.] {
+                        gizmos.linestrip(draped(corner, other_corner, up), WALL_COLOR);
+                    }
+                }
+            }
+        }
     }
 }
 
@@ -563,10 +1521,6 @@ pub fn draw_line_collection(
     mut my_gizmos: Gizmos<crate::MyRoundGizmos>,
     time: Res<Time>,
 ) {
-    gizmos.cuboid(
-        Transform::from_translation(Vec3::Y * 0.5).with_scale(Vec3::splat(1.25)),
-        Color::BLACK,
-    );
     gizmos.rect(
         Vec3::new(time.elapsed_seconds().cos() * 2.5, 1., 0.),
         Quat::from_rotation_y(std::f32::consts::PI / 2.),
@@ -607,44 +1561,6 @@ pub fn draw_line_collection(
     gizmos.arrow(Vec3::ZERO, Vec3::ONE * 1.5, Color::YELLOW);
 }
 
-pub fn add_oper(
-    // time: Res<Time>
-    asset_server: Res<AssetServer>,
-    mut commands: Commands,
-    map_info: Res<crate::map::resources::MapInfo>,
-) {
-    commands.spawn((
-        SpriteBundle {
-            texture: asset_server.load(
-                // "wg/ncly/level21/".to_string()
-                //     + (x + 1).to_string().as_str()
-                //     + "-"
-                //     + (y + 1).to_string().as_str()
-                //     + ".png",
-                "wg/mlx/oper/001-01.png",
-                // "branding/bevy_bird_dark.png",
-            ),
-            // transform: Transform::from_xyz(, , 1.),
-            transform: Transform {
-                translation: bevy::prelude::Vec3 {
-                    x: map_info.unit_x / 4.,
-                    y: map_info.unit_y / 4.,
-                    z: 1.,
-                },
-                scale: bevy::prelude::Vec3 {
-                    x: 0.25,
-                    y: 0.25,
-                    z: 1.,
-                },
-                ..default()
-            },
-            ..default()
-        },
-        crate::map::entities::MapNC,
-        crate::map::entities::MapMenu,
-    ));
-}
-
 pub fn despawn_map_menu(
     query_enemy: Query<Entity, With<crate::map::entities::MapMenu>>,
     mut commands: Commands,
@@ -0,0 +1,272 @@
+// Terrain layer: what the ground is like in each hex of the map
+
+use bevy::prelude::*;
+
+/*
+ * The terrain of a map lives in a RON file next to the map image, e.g.
+ * "wg/mlx/map/1-8819p-6299p.png" -> "wg/mlx/map/1-8819p-6299p.terrain.ron".
+ * Hexes are keyed by their zero-based (col, row) offset coordinate; hexes missing from the file
+ * take the `default` terrain. Roads, bridges, rivers and walls lie on the hexsides between hexes,
+ * see `crate::map::hexside`:
+ *
+ * (
+ *     default: (terrain: Open),
+ *     hexes: [
+ *         (col: 12, row: 4, terrain: Forest, elevation: 1),
+ *         (col: 13, row: 4, terrain: Urban),
+ *     ],
+ *     hexsides: [
+ *         (col: 13, row: 4, side: South, features: [Bridge, River]),
+ *     ],
+ * )
+ *
+ * Older terrain files marked roads and rivers on the hexes instead, with `road: true` and
+ * `river_edges: [South, SouthWest]`. They are read into hexsides: a road between every two
+ * adjacent road hexes, a river along every river edge.
+ */
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum TerrainType {
+    #[default]
+    Open,
+    Forest,
+    Urban,
+    Water,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct HexTerrain {
+    pub terrain: TerrainType,
+    /// Height level of the ground, 0 is the lowest ground on the map.
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub elevation: i32,
+}
+
+// fields left at their default are not written back, as they would be left out by hand
+fn is_zero(value: &i32) -> bool {
+    *value == 0
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct TerrainEntry {
+    pub col: i32,
+    pub row: i32,
+    pub terrain: TerrainType,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub elevation: i32,
+    /// Older terrain files only, read into road hexsides.
+    #[serde(default, skip_serializing)]
+    pub road: bool,
+    /// Older terrain files only, read into river hexsides.
+    #[serde(default, skip_serializing)]
+    pub river_edges: Vec<crate::map::hex::HexDirection>,
+}
+
+/// On-disk layout of a terrain file.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct TerrainFile {
+    #[serde(default)]
+    pub default: HexTerrain,
+    #[serde(default)]
+    pub hexes: Vec<TerrainEntry>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hexsides: Vec<crate::map::hexside::HexsideEntry>,
+}
+
+#[derive(Asset, TypePath, Clone, Debug, Default, serde::Deserialize)]
+#[serde(from = "TerrainFile")]
+pub struct TerrainMap {
+    pub default: HexTerrain,
+    pub hexes: bevy::utils::HashMap<crate::map::hex::Hex, HexTerrain>,
+    pub hexsides: crate::map::hexside::HexsideMap,
+}
+
+impl From<TerrainFile> for TerrainMap {
+    fn from(file: TerrainFile) -> Self {
+        use crate::map::hexside::{Hexside, HexsideFeature};
+        let hex = |col: i32, row: i32| {
+            crate::map::hex::Hex::from_offset(crate::map::hex::OffsetCoord::new(col, row))
+        };
+        let mut hexsides = crate::map::hexside::HexsideMap::default();
+        for entry in file.hexsides.iter() {
+            for feature in entry.features.iter() {
+                hexsides.add(
+                    Hexside::new(hex(entry.col, entry.row), entry.side),
+                    *feature,
+                );
+            }
+        }
+        let roads: bevy::utils::HashSet<crate::map::hex::Hex> = file
+            .hexes
+            .iter()
+            .filter(|entry| entry.road)
+            .map(|entry| hex(entry.col, entry.row))
+            .collect();
+        for road in roads.iter() {
+            for neighbor in road.neighbors() {
+                if let (true, Some(hexside)) =
+                    (roads.contains(&neighbor), Hexside::between(*road, neighbor))
+                {
+                    hexsides.add(hexside, HexsideFeature::Road);
+                }
+            }
+        }
+        let hexes = file
+            .hexes
+            .into_iter()
+            .map(|entry| {
+                let entry_hex = hex(entry.col, entry.row);
+                for direction in entry.river_edges {
+                    hexsides.add(Hexside::new(entry_hex, direction), HexsideFeature::River);
+                }
+                (
+                    entry_hex,
+                    HexTerrain {
+                        terrain: entry.terrain,
+                        elevation: entry.elevation,
+                    },
+                )
+            })
+            .collect();
+        TerrainMap {
+            default: file.default,
+            hexes,
+            hexsides,
+        }
+    }
+}
+
+/// Hexes with the default terrain are left out, the others come column by column, then hexsides.
+impl From<&TerrainMap> for TerrainFile {
+    fn from(terrain: &TerrainMap) -> Self {
+        let mut hexes: Vec<TerrainEntry> = terrain
+            .hexes
+            .iter()
+            .filter(|(_, hex_terrain)| **hex_terrain != terrain.default)
+            .map(|(hex, hex_terrain)| {
+                let offset = hex.to_offset();
+                TerrainEntry {
+                    col: offset.col,
+                    row: offset.row,
+                    terrain: hex_terrain.terrain,
+                    elevation: hex_terrain.elevation,
+                    road: false,
+                    river_edges: Vec::new(),
+                }
+            })
+            .collect();
+        hexes.sort_by_key(|entry| (entry.col, entry.row));
+        let mut hexsides: Vec<crate::map::hexside::HexsideEntry> = terrain
+            .hexsides
+            .features
+            .iter()
+            .map(|(hexside, features)| {
+                let offset = hexside.hex.to_offset();
+                crate::map::hexside::HexsideEntry {
+                    col: offset.col,
+                    row: offset.row,
+                    side: hexside.direction,
+                    features: features.clone(),
+                }
+            })
+            .collect();
+        hexsides.sort_by_key(|entry| (entry.col, entry.row, entry.side as u8));
+        TerrainFile {
+            default: terrain.default.clone(),
+            hexes,
+            hexsides,
+        }
+    }
+}
+
+impl TerrainMap {
+    /// The terrain in the layout of a terrain file, one hex or hexside a line.
+    pub fn to_ron(&self) -> Result<String, ron::Error> {
+        ron::ser::to_string_pretty(
+            &TerrainFile::from(self),
+            ron::ser::PrettyConfig::default().depth_limit(2),
+        )
+    }
+
+    pub fn get(&self, hex: crate::map::hex::Hex) -> &HexTerrain {
+        self.hexes.get(&hex).unwrap_or(&self.default)
+    }
+}
+
+impl crate::tools::ron_file::RonFile for TerrainMap {
+    const WHAT: &'static str = "terrain file";
+    const EXTENSIONS: &'static [&'static str] = &["terrain.ron"];
+}
+
+pub type TerrainMapLoader = crate::tools::ron_file::RonLoader<TerrainMap>;
+
+/// Path of the terrain file that belongs to a map image.
+pub fn terrain_path(map_image: &str) -> String {
+    let stem = map_image
+        .rsplit_once('.')
+        .map_or(map_image, |(stem, _)| stem);
+    format!("{stem}.terrain.ron")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::hex::{Hex, HexDirection, OffsetCoord};
+    use crate::map::hexside::{Hexside, HexsideFeature};
+    use crate::tools::ron_file::RonFile;
+
+    fn hex(col: i32, row: i32) -> Hex {
+        Hex::from_offset(OffsetCoord::new(col, row))
+    }
+
+    #[test]
+    fn older_files_put_roads_and_rivers_on_hexsides() {
+        let terrain = TerrainMap::from_bytes(
+            b"(
+                default: (terrain: Open),
+                hexes: [
+                    (col: 4, row: 4, terrain: Open, road: true, river_edges: [South]),
+                    (col: 4, row: 5, terrain: Urban, road: true),
+                    (col: 8, row: 4, terrain: Open, road: true),
+                ],
+            )",
+        )
+        .unwrap();
+        assert_eq!(
+            terrain.hexsides.between(hex(4, 4), hex(4, 5)),
+            &[HexsideFeature::Road, HexsideFeature::River]
+        );
+        // a road hex with no road hex next to it has no road to follow
+        assert_eq!(terrain.hexsides.road_neighbors(hex(8, 4)).count(), 0);
+        assert_eq!(terrain.hexsides.features.len(), 1);
+        assert_eq!(terrain.get(hex(4, 5)).terrain, TerrainType::Urban);
+    }
+
+    #[test]
+    fn written_terrain_reads_back_the_same() {
+        let mut terrain = TerrainMap::from_bytes(
+            b"(
+                default: (terrain: Open),
+                hexes: [(col: 2, row: 3, terrain: Forest, elevation: 2)],
+                hexsides: [
+                    (col: 2, row: 3, side: SouthWest, features: [Wall]),
+                    (col: 5, row: 5, side: North, features: [River, Bridge]),
+                ],
+            )",
+        )
+        .unwrap();
+        terrain.hexsides.add(
+            Hexside::new(hex(7, 1), HexDirection::South),
+            HexsideFeature::Road,
+        );
+        let text = terrain.to_ron().unwrap();
+        assert!(!text.contains("road:") && !text.contains("river_edges"));
+        let read = TerrainMap::from_bytes(text.as_bytes()).unwrap();
+        assert_eq!(read.default, terrain.default);
+        assert_eq!(read.hexes, terrain.hexes);
+        assert_eq!(read.hexsides, terrain.hexsides);
+        assert_eq!(read.to_ron().unwrap(), text);
+    }
+}
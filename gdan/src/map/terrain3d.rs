@@ -0,0 +1,282 @@
+// 3D terrain: the ground of the map as a mesh, raised from a heightmap and draped with the map image
+
+use bevy::prelude::*;
+
+/*
+ * A heightmap lies next to the map image, "wg/mlx/map/1-8819p-6299p.png" ->
+ * "wg/mlx/map/1-8819p-6299p.height.ron", and covers the same ground:
+ *
+ * (
+ *     image: "wg/mlx/map/1-8819p-6299p.height.png",
+ *     min_height: 40.,
+ *     max_height: 160.,
+ * )
+ *
+ * The image is greyscale, black at `min_height` and white at `max_height` metres.
+ * Maps without a heightmap are raised from the elevation levels of their terrain file,
+ * `LEVEL_METERS` a level, smoothed from hex to hex.
+ *
+ * The 3D map is `MAP3D_WIDTH` wide and centred on the origin, north towards -Z,
+ * its heights `HEIGHT_EXAGGERATION` times higher than they are, so small ridges still show.
+ */
+pub const LEVEL_METERS: f32 = 10.;
+pub const MAP3D_WIDTH: f32 = 10.;
+pub const HEIGHT_EXAGGERATION: f32 = 3.;
+/// Mesh cells along the longer side of the map.
+pub const TERRAIN_CELLS: u32 = 256;
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct HeightmapFile {
+    pub image: String,
+    pub min_height: f32,
+    pub max_height: f32,
+}
+
+impl crate::tools::ron_file::RonFile for HeightmapFile {
+    const WHAT: &'static str = "heightmap";
+}
+
+/// Heights of a heightmap in metres, row by row from the top left corner.
+#[derive(Asset, TypePath, Clone, Debug)]
+pub struct Heightmap {
+    pub width: u32,
+    pub height: u32,
+    pub heights: Vec<f32>,
+}
+
+impl Heightmap {
+    /// Height at `(u, v)`, from (0, 0) at the top left corner to (1, 1) at the bottom right.
+    pub fn sample(&self, u: f32, v: f32) -> f32 {
+        bilinear(&self.heights, self.width, self.height, u, v)
+    }
+}
+
+fn bilinear(values: &[f32], width: u32, height: u32, u: f32, v: f32) -> f32 {
+    let x = u.clamp(0., 1.) * (width - 1) as f32;
+    let y = v.clamp(0., 1.) * (height - 1) as f32;
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let at = |x: u32, y: u32| values[(y * width + x) as usize];
+    let (fx, fy) = (x.fract(), y.fract());
+    let top = at(x0, y0) + (at(x1, y0) - at(x0, y0)) * fx;
+    let bottom = at(x0, y1) + (at(x1, y1) - at(x0, y1)) * fx;
+    top + (bottom - top) * fy
+}
+
+/// Path of the heightmap that belongs to a map image.
+pub fn heightmap_path(map_image: &str) -> String {
+    format!("{}.height.ron", crate::map::tiles::image_stem(map_image))
+}
+
+/// Where map points go in 3D.
+#[derive(Clone, Copy, Debug)]
+pub struct Layout3d {
+    /// Size of the map image, in pixels.
+    pub map_size: Vec2,
+    pub units_per_pixel: f32,
+    pub units_per_meter: f32,
+}
+
+impl Layout3d {
+    pub fn new(map_info: &crate::map::resources::MapInfo) -> Self {
+        let units_per_pixel = MAP3D_WIDTH / map_info.unit_x;
+        Layout3d {
+            map_size: Vec2::new(map_info.unit_x, map_info.unit_y),
+            units_per_pixel,
+            units_per_meter: units_per_pixel / map_info.meter_per_pixel * HEIGHT_EXAGGERATION,
+        }
+    }
+
+    /// A map point, in world coordinates of the 2D map, `height` metres up.
+    pub fn to_3d(&self, point: Vec2, height: f32) -> Vec3 {
+        let centred = (point - self.map_size / 2.) * self.units_per_pixel;
+        Vec3::new(centred.x, height * self.units_per_meter, -centred.y)
+    }
+}
+
+/// Heights of the ground in metres on a regular grid over the map, row by row from the top left.
+#[derive(Clone, Debug)]
+pub struct HeightField {
+    /// Grid points across and down.
+    pub cols: u32,
+    pub rows: u32,
+    pub map_size: Vec2,
+    pub heights: Vec<f32>,
+}
+
+impl HeightField {
+    fn build(map_size: Vec2, height_at: impl Fn(Vec2) -> f32) -> Self {
+        let cells = TERRAIN_CELLS as f32 / map_size.max_element();
+        let cols = (map_size.x * cells).ceil() as u32 + 1;
+        let rows = (map_size.y * cells).ceil() as u32 + 1;
+        let mut heights = Vec::with_capacity((cols * rows) as usize);
+        for row in 0..rows {
+            for col in 0..cols {
+                let u = col as f32 / (cols - 1) as f32;
+                let v = row as f32 / (rows - 1) as f32;
+                heights.push(height_at(Vec2::new(u * map_size.x, (1. - v) * map_size.y)));
+            }
+        }
+        HeightField {
+            cols,
+            rows,
+            map_size,
+            heights,
+        }
+    }
+
+    pub fn from_heightmap(heightmap: &Heightmap, map_size: Vec2) -> Self {
+        HeightField::build(map_size, |point| {
+            heightmap.sample(point.x / map_size.x, 1. - point.y / map_size.y)
+        })
+    }
+
+    /// Heights from the elevation levels of the hexes, blurred over about a hex.
+    pub fn from_terrain(
+        terrain: &crate::map::terrain::TerrainMap,
+        layout: &crate::map::hex::HexLayout,
+        map_size: Vec2,
+    ) -> Self {
+        let mut field = HeightField::build(map_size, |point| {
+            terrain.get(layout.world_to_hex(point)).elevation as f32 * LEVEL_METERS
+        });
+        let radius = (layout.size / map_size.max_element() * TERRAIN_CELLS as f32).ceil() as i32;
+        for _ in 0..2 {
+            field.blur(radius.max(1));
+        }
+        field
+    }
+
+    /// Box blur, across then down.
+    fn blur(&mut self, radius: i32) {
+        let (cols, rows) = (self.cols as i32, self.rows as i32);
+        for (step, lines, length) in [((1, 0), rows, cols), ((0, 1), cols, rows)] {
+            let mut blurred = self.heights.clone();
+            for line in 0..lines {
+                for i in 0..length {
+                    let index = |j: i32| {
+                        let j = j.clamp(0, length - 1);
+                        let (col, row) = if step.0 == 1 { (j, line) } else { (line, j) };
+                        (row * cols + col) as usize
+                    };
+                    let sum: f32 = (i - radius..=i + radius)
+                        .map(|j| self.heights[index(j)])
+                        .sum();
+                    blurred[index(i)] = sum / (2 * radius + 1) as f32;
+                }
+            }
+            self.heights = blurred;
+        }
+    }
+
+    /// Height of the ground at a map point, in world coordinates of the 2D map.
+    pub fn height_at(&self, point: Vec2) -> f32 {
+        bilinear(
+            &self.heights,
+            self.cols,
+            self.rows,
+            point.x / self.map_size.x,
+            1. - point.y / self.map_size.y,
+        )
+    }
+
+    /// The ground as a mesh, textured with the map image from its top left corner.
+    pub fn mesh(&self, layout: &Layout3d) -> Mesh {
+        let (cols, rows) = (self.cols, self.rows);
+        let mut positions = Vec::with_capacity(self.heights.len());
+        let mut uvs = Vec::with_capacity(self.heights.len());
+        for row in 0..rows {
+            for col in 0..cols {
+                let u = col as f32 / (cols - 1) as f32;
+                let v = row as f32 / (rows - 1) as f32;
+                let point = Vec2::new(u * self.map_size.x, (1. - v) * self.map_size.y);
+                let height = self.heights[(row * cols + col) as usize];
+                positions.push(layout.to_3d(point, height).to_array());
+                uvs.push([u, v]);
+            }
+        }
+        // normals from the slope between the grid points around each one
+        let at = |col: i32, row: i32| {
+            let col = col.clamp(0, cols as i32 - 1) as u32;
+            let row = row.clamp(0, rows as i32 - 1) as u32;
+            Vec3::from(positions[(row * cols + col) as usize])
+        };
+        let mut normals = Vec::with_capacity(positions.len());
+        for row in 0..rows as i32 {
+            for col in 0..cols as i32 {
+                let east = at(col + 1, row) - at(col - 1, row);
+                let south = at(col, row + 1) - at(col, row - 1);
+                normals.push(south.cross(east).normalize_or_zero().to_array());
+            }
+        }
+        let mut indices = Vec::with_capacity(((cols - 1) * (rows - 1) * 6) as usize);
+        for row in 0..rows - 1 {
+            for col in 0..cols - 1 {
+                let top_left = row * cols + col;
+                let bottom_left = top_left + cols;
+                indices.extend([
+                    top_left,
+                    bottom_left,
+                    top_left + 1,
+                    top_left + 1,
+                    bottom_left,
+                    bottom_left + 1,
+                ]);
+            }
+        }
+        Mesh::new(
+            bevy::render::render_resource::PrimitiveTopology::TriangleList,
+            bevy::render::render_asset::RenderAssetUsages::default(),
+        )
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+        .with_inserted_indices(bevy::render::mesh::Indices::U32(indices))
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum HeightmapError {
+    #[error(transparent)]
+    File(#[from] crate::tools::ron_file::RonFileError),
+    #[error("could not read heightmap image: {0}")]
+    Read(#[from] bevy::asset::ReadAssetBytesError),
+    #[error("could not decode heightmap image: {0}")]
+    Image(#[from] image::ImageError),
+}
+
+#[derive(Default)]
+pub struct HeightmapLoader;
+
+impl bevy::asset::AssetLoader for HeightmapLoader {
+    type Asset = Heightmap;
+    type Settings = ();
+    type Error = HeightmapError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut bevy::asset::io::Reader,
+        _settings: &'a (),
+        load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<Heightmap, HeightmapError>> {
+        Box::pin(async move {
+            let bytes = crate::tools::ron_file::read_asset::<HeightmapFile>(reader).await?;
+            let file: HeightmapFile = crate::tools::ron_file::RonFile::from_bytes(&bytes)?;
+            let image_bytes = load_context.read_asset_bytes(file.image.clone()).await?;
+            let image = image::load_from_memory(&image_bytes)?.to_luma16();
+            let range = file.max_height - file.min_height;
+            Ok(Heightmap {
+                width: image.width(),
+                height: image.height(),
+                heights: image
+                    .pixels()
+                    .map(|pixel| file.min_height + range * pixel.0[0] as f32 / u16::MAX as f32)
+                    .collect(),
+            })
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["height.ron"]
+    }
+}
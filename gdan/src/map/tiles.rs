@@ -0,0 +1,241 @@
+// Map tiles: a big map image cut into a pyramid of small tiles, loaded as the camera needs them
+
+use bevy::prelude::*;
+
+/*
+ * `gdan tile` cuts a map image into square tiles, once at full size and again at every half size,
+ * down to the level where the whole map fits in one tile. Levels are numbered like the satellite
+ * map levels of `MapInfo`: the full size level has the map's own level, each halving one less.
+ * Next to the image "wg/mlx/map/1-8819p-6299p.png" it writes
+ *
+ *   wg/mlx/map/1-8819p-6299p.pyramid.ron             the description below
+ *   wg/mlx/map/1-8819p-6299p.tiles/level21/1-1.png   top left tile of the full size level
+ *   wg/mlx/map/1-8819p-6299p.tiles/level21/2-1.png   the tile right of it
+ *   wg/mlx/map/1-8819p-6299p.tiles/level16/1-1.png   the whole map
+ *
+ * (
+ *     width: 8819,
+ *     height: 6299,
+ *     tile_size: 512,
+ *     max_level: 21,
+ *     min_level: 16,
+ * )
+ *
+ * Tiles are numbered "<column>-<row>" from 1, from the top left corner; those on the right and
+ * bottom edges are cut short.
+ */
+#[derive(Asset, TypePath, Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct TilePyramid {
+    /// Size of the full map image, in pixels.
+    pub width: u32,
+    pub height: u32,
+    pub tile_size: u32,
+    pub max_level: u8,
+    pub min_level: u8,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TileKey {
+    pub level: u8,
+    /// From 1, left to right.
+    pub col: u32,
+    /// From 1, top to bottom.
+    pub row: u32,
+}
+
+impl TileKey {
+    /// The tile of the next coarser level covering this one.
+    pub fn parent(self) -> Option<TileKey> {
+        Some(TileKey {
+            level: self.level.checked_sub(1)?,
+            col: (self.col - 1) / 2 + 1,
+            row: (self.row - 1) / 2 + 1,
+        })
+    }
+}
+
+impl TilePyramid {
+    /// Full size map pixels one tile pixel of `level` covers.
+    pub fn pixel_size(&self, level: u8) -> f32 {
+        2f32.powi(self.max_level.saturating_sub(level) as i32)
+    }
+
+    /// Map pixels one tile of `level` covers across.
+    fn span(&self, level: u8) -> f32 {
+        self.tile_size as f32 * self.pixel_size(level)
+    }
+
+    /// The coarsest level still as sharp as the screen at an orthographic projection `scale`.
+    pub fn level_for_scale(&self, scale: f32) -> u8 {
+        let halvings = scale.max(1.).log2().floor() as u8;
+        self.max_level.saturating_sub(halvings).max(self.min_level)
+    }
+
+    /// World rectangle a tile covers; world y points up, tile rows go down.
+    pub fn tile_rect(&self, key: TileKey) -> Rect {
+        let span = self.span(key.level);
+        let (width, height) = (self.width as f32, self.height as f32);
+        Rect::new(
+            (key.col - 1) as f32 * span,
+            (height - key.row as f32 * span).max(0.),
+            (key.col as f32 * span).min(width),
+            height - (key.row - 1) as f32 * span,
+        )
+    }
+
+    /// Tiles of `level` overlapping `view`, a world rectangle.
+    pub fn tiles_in(&self, level: u8, view: Rect) -> Vec<TileKey> {
+        let span = self.span(level);
+        let (width, height) = (self.width as f32, self.height as f32);
+        let cols = (width / span).ceil() as u32;
+        let rows = (height / span).ceil() as u32;
+        let col = |x: f32| ((x / span).floor().max(0.) as u32 + 1).min(cols);
+        let row = |y: f32| (((height - y) / span).floor().max(0.) as u32 + 1).min(rows);
+        if view.max.x < 0. || view.min.x > width || view.max.y < 0. || view.min.y > height {
+            return Vec::new();
+        }
+        let mut keys = Vec::new();
+        for row in row(view.max.y)..=row(view.min.y) {
+            for col in col(view.min.x)..=col(view.max.x) {
+                keys.push(TileKey { level, col, row });
+            }
+        }
+        keys
+    }
+}
+
+/// Path of the tile pyramid of a map image.
+pub fn pyramid_path(map_image: &str) -> String {
+    format!("{}.pyramid.ron", image_stem(map_image))
+}
+
+/// Path of one tile of the pyramid of a map image.
+pub fn tile_path(map_image: &str, key: TileKey) -> String {
+    format!(
+        "{}.tiles/level{}/{}-{}.png",
+        image_stem(map_image),
+        key.level,
+        key.col,
+        key.row
+    )
+}
+
+/// A map image path without its extension.
+pub fn image_stem(map_image: &str) -> &str {
+    map_image
+        .rsplit_once('.')
+        .map_or(map_image, |(stem, _)| stem)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TileError {
+    #[error("could not write tiles: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    File(#[from] crate::tools::ron_file::RonFileError),
+    #[error("could not cut map image: {0}")]
+    Image(#[from] image::ImageError),
+}
+
+/// Cuts the map image at `map_image` into its tile pyramid, full size at `max_level`.
+pub fn cut(map_image: &str, tile_size: u32, max_level: u8) -> Result<TilePyramid, TileError> {
+    let mut level_image = image::open(map_image)?.to_rgba8();
+    let (width, height) = level_image.dimensions();
+    let mut level = max_level;
+    loop {
+        let cols = level_image.width().div_ceil(tile_size);
+        let rows = level_image.height().div_ceil(tile_size);
+        for row in 1..=rows {
+            for col in 1..=cols {
+                let x = (col - 1) * tile_size;
+                let y = (row - 1) * tile_size;
+                let tile = image::imageops::crop_imm(
+                    &level_image,
+                    x,
+                    y,
+                    tile_size.min(level_image.width() - x),
+                    tile_size.min(level_image.height() - y),
+                )
+                .to_image();
+                let path = tile_path(map_image, TileKey { level, col, row });
+                if let Some(folder) = std::path::Path::new(&path).parent() {
+                    std::fs::create_dir_all(folder)?;
+                }
+                tile.save(path)?;
+            }
+        }
+        if (cols == 1 && rows == 1) || level == 0 {
+            break;
+        }
+        level_image = image::imageops::resize(
+            &level_image,
+            level_image.width().div_ceil(2),
+            level_image.height().div_ceil(2),
+            image::imageops::FilterType::Triangle,
+        );
+        level -= 1;
+    }
+    let pyramid = TilePyramid {
+        width,
+        height,
+        tile_size,
+        max_level,
+        min_level: level,
+    };
+    crate::tools::ron_file::RonFile::write(&pyramid, pyramid_path(map_image))?;
+    Ok(pyramid)
+}
+
+impl crate::tools::ron_file::RonFile for TilePyramid {
+    const WHAT: &'static str = "tile pyramid";
+    const EXTENSIONS: &'static [&'static str] = &["pyramid.ron"];
+}
+
+pub type TilePyramidLoader = crate::tools::ron_file::RonLoader<TilePyramid>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pyramid() -> TilePyramid {
+        TilePyramid {
+            width: 8819,
+            height: 6299,
+            tile_size: 512,
+            max_level: 21,
+            min_level: 16,
+        }
+    }
+
+    #[test]
+    fn parent_covers_its_tile() {
+        let pyramid = pyramid();
+        let whole = Rect::new(0., 0., 8819., 6299.);
+        for key in pyramid.tiles_in(21, whole) {
+            let mut child = key;
+            while let Some(parent) = child.parent().filter(|parent| parent.level >= 16) {
+                let (rect, parent_rect) = (pyramid.tile_rect(child), pyramid.tile_rect(parent));
+                assert!(parent_rect.contains(rect.min) && parent_rect.contains(rect.max));
+                child = parent;
+            }
+            assert_eq!(child.level, 16);
+            assert_eq!((child.col, child.row), (1, 1));
+        }
+    }
+
+    #[test]
+    fn coarsest_level_holds_the_whole_map() {
+        let pyramid = pyramid();
+        let whole = Rect::new(0., 0., 8819., 6299.);
+        assert_eq!(pyramid.tiles_in(16, whole).len(), 1);
+        assert_eq!(
+            pyramid.tile_rect(TileKey {
+                level: 16,
+                col: 1,
+                row: 1
+            }),
+            whole
+        );
+        assert_eq!(pyramid.tiles_in(21, whole).len(), 18 * 13);
+    }
+}
@@ -0,0 +1,190 @@
+// TCP connections: threads moving messages between sockets and channels the ECS polls
+
+use std::io::{BufRead, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+
+/*
+ * Each connection runs on its own thread, so the game never waits on the network.
+ * The thread writes what arrives on its outgoing channel and reads lines with a short timeout,
+ * passing every message it reads to the incoming channel. It ends when the socket closes,
+ * or when the outgoing channel is dropped.
+ */
+const POLL: std::time::Duration = std::time::Duration::from_millis(50);
+/// Longest message taken, the whole game sent on a reconnection included: a peer sending more
+/// without ending its line is dropped rather than filling the memory.
+pub const MAX_LINE: usize = 4 << 20;
+/// Time between two attempts of a client to reach the host.
+pub const RECONNECT_DELAY: std::time::Duration = std::time::Duration::from_secs(2);
+
+/// Identifies a connection to the host for as long as it lasts.
+pub type ConnectionId = u64;
+
+#[derive(Debug)]
+pub enum NetEvent<T> {
+    Connected(ConnectionId),
+    Message(ConnectionId, T),
+    Disconnected(ConnectionId),
+}
+
+/// Reads and writes one connection until it closes; returns whether the game dropped it.
+fn run_connection<In, Out>(
+    stream: std::net::TcpStream,
+    id: ConnectionId,
+    incoming: &Sender<NetEvent<In>>,
+    outgoing: &Receiver<Out>,
+) -> bool
+where
+    In: serde::de::DeserializeOwned,
+    Out: serde::Serialize,
+{
+    let _ = stream.set_nodelay(true);
+    if stream.set_read_timeout(Some(POLL)).is_err() {
+        return false;
+    }
+    let Ok(mut writer) = stream.try_clone() else {
+        return false;
+    };
+    let mut reader = std::io::BufReader::new(stream);
+    let mut line = Vec::new();
+    loop {
+        loop {
+            match outgoing.try_recv() {
+                Ok(message) => {
+                    let sent = crate::net::protocol::encode(&message)
+                        .map_err(std::io::Error::other)
+                        .and_then(|bytes| writer.write_all(&bytes));
+                    if sent.is_err() {
+                        return false;
+                    }
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    let _ = writer.shutdown(std::net::Shutdown::Both);
+                    return true;
+                }
+            }
+        }
+        // a line cut by the timeout is kept in `line` and completed on the next read;
+        // no more is read than it takes to find it too long
+        let limit = (MAX_LINE + 1 - line.len()) as u64;
+        match (&mut reader).take(limit).read_until(b'\n', &mut line) {
+            Ok(0) => return false,
+            Ok(_) if line.ends_with(b"\n") => {
+                match crate::net::protocol::decode::<In>(&line) {
+                    Ok(message) => {
+                        if incoming.send(NetEvent::Message(id, message)).is_err() {
+                            return true;
+                        }
+                    }
+                    Err(error) => bevy::log::warn!("connection {}: {}", id, error),
+                }
+                line.clear();
+            }
+            Ok(_) if line.len() > MAX_LINE => {
+                bevy::log::warn!("connection {}: line longer than {} bytes", id, MAX_LINE);
+                return false;
+            }
+            Ok(_) => {}
+            Err(error)
+                if matches!(
+                    error.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) => {}
+            Err(_) => return false,
+        }
+    }
+}
+
+/*
+ * Accepts connections on `listener` until `stop` is set. Each connection gets an id and a thread;
+ * its outgoing sender is handed over through `connected`, before the `Connected` event.
+ */
+pub fn spawn_listener<In, Out>(
+    listener: std::net::TcpListener,
+    incoming: Sender<NetEvent<In>>,
+    connected: Sender<(ConnectionId, Sender<Out>)>,
+    stop: Arc<AtomicBool>,
+) -> std::io::Result<()>
+where
+    In: serde::de::DeserializeOwned + Send + 'static,
+    Out: serde::Serialize + Send + 'static,
+{
+    listener.set_nonblocking(true)?;
+    std::thread::spawn(move || {
+        let mut next_id: ConnectionId = 1;
+        while !stop.load(Ordering::Relaxed) {
+            let stream = match listener.accept() {
+                Ok((stream, address)) => {
+                    bevy::log::info!("connection {} from {}", next_id, address);
+                    stream
+                }
+                Err(error) if error.kind() == std::io::ErrorKind::WouldBlock => {
+                    std::thread::sleep(POLL);
+                    continue;
+                }
+                Err(error) => {
+                    bevy::log::warn!("{}", error);
+                    std::thread::sleep(POLL);
+                    continue;
+                }
+            };
+            let id = next_id;
+            next_id += 1;
+            let (sender, outgoing) = std::sync::mpsc::channel();
+            if connected.send((id, sender)).is_err() {
+                break;
+            }
+            let incoming = incoming.clone();
+            std::thread::spawn(move || {
+                if stream.set_nonblocking(false).is_err() {
+                    return;
+                }
+                let _ = incoming.send(NetEvent::Connected(id));
+                run_connection(stream, id, &incoming, &outgoing);
+                let _ = incoming.send(NetEvent::Disconnected(id));
+            });
+        }
+    });
+    Ok(())
+}
+
+/*
+ * Connects to `address`, and connects again after losing the connection, until the game drops
+ * `outgoing` or sets `stop`. Messages given while there is no connection are dropped.
+ */
+pub fn spawn_client<In, Out>(
+    address: String,
+    incoming: Sender<NetEvent<In>>,
+    outgoing: Receiver<Out>,
+    stop: Arc<AtomicBool>,
+) where
+    In: serde::de::DeserializeOwned + Send + 'static,
+    Out: serde::Serialize + Send + 'static,
+{
+    std::thread::spawn(move || {
+        let mut id: ConnectionId = 0;
+        while !stop.load(Ordering::Relaxed) {
+            let stream = match std::net::TcpStream::connect(&address) {
+                Ok(stream) => stream,
+                Err(error) => {
+                    bevy::log::info!("{}: {}, trying again", address, error);
+                    std::thread::sleep(RECONNECT_DELAY);
+                    continue;
+                }
+            };
+            id += 1;
+            // whatever was meant for the previous connection is out of date
+            while outgoing.try_recv().is_ok() {}
+            if incoming.send(NetEvent::Connected(id)).is_err() {
+                break;
+            }
+            let dropped = run_connection(stream, id, &incoming, &outgoing);
+            if dropped || incoming.send(NetEvent::Disconnected(id)).is_err() {
+                break;
+            }
+            std::thread::sleep(RECONNECT_DELAY);
+        }
+    });
+}
@@ -0,0 +1,19 @@
+use bevy::ecs::component::Component;
+
+/// Root of the lobby screen of a network game.
+#[derive(Component)]
+pub struct LobbyMenu;
+
+/// Lobby text listing the sides, who plays them and whether they are ready.
+#[derive(Component)]
+pub struct LobbyText;
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LobbyButton {
+    Ready,
+    Back,
+}
+
+/// In-game line telling how the network game is going.
+#[derive(Component)]
+pub struct NetStatusText;
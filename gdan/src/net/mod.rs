@@ -0,0 +1,5 @@
+pub mod connection;
+pub mod entities;
+pub mod protocol;
+pub mod resources;
+pub mod systems;
@@ -0,0 +1,112 @@
+// Network protocol: the messages host and clients exchange, one RON value per line
+
+use crate::oper::components::Side;
+
+/*
+ * The host runs the rules; clients only send the orders of their side and show the state the
+ * host sends back. A session over one TCP connection goes:
+ *
+ *   client                                  host
+ *   Hello(token: None)               ->
+ *                                    <-     Welcome(side: Blue, token: 7215...)
+ *                                    <-     Lobby(...)           whenever a seat changes
+ *   Ready(true)                      ->
+ *                                    <-     Start(...)           the game as it is, dice left out
+ *   Command(Move(oper: 101, ...))    ->
+ *                                    <-     State(...)           what changed, once per frame
+ *                                    <-     Rejected(...)        orders the client may not give
+ *
+ * A client that lost its connection connects again with the token it was given and gets its
+ * seat back, with a new `Start` holding the whole game.
+ */
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ClientMessage {
+    /// First message on every connection; `token` is the one given by an earlier `Welcome`.
+    Hello {
+        name: String,
+        token: Option<u64>,
+    },
+    Ready(bool),
+    Command(crate::rule::commands::GameCommand),
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub enum HostMessage {
+    /// The seat given to the client, and the token to get it back after a lost connection.
+    Welcome {
+        side: Side,
+        token: u64,
+    },
+    /// The host will not take the client, e.g. every seat is taken.
+    Refused(String),
+    Lobby(LobbyInfo),
+    /// The game as it is, to play from: sent when it starts and again after a reconnection.
+    /// It only holds what the side has seen, and fresh dice rather than the host's.
+    Start(Box<crate::game::save::SaveGame>),
+    State(StateDelta),
+    Rejected {
+        command: crate::rule::commands::GameCommand,
+        reason: String,
+    },
+}
+
+/// One side of the game and who plays it.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct SeatInfo {
+    pub side: Side,
+    /// Name of the player, empty while the seat is free.
+    pub name: String,
+    pub connected: bool,
+    pub ready: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct LobbyInfo {
+    /// Asset path of the scenario to be played.
+    pub scenario: String,
+    pub seats: Vec<SeatInfo>,
+}
+
+/*
+ * What changed on the host since the last delta, as the side of the client knows it: the units that
+ * changed, the turn if it moved on, what the side has spotted if that changed, and the combat log
+ * entries from `combat_log_from` on, counted in the log of the client. The log only holds the
+ * fights the side took part in or saw.
+ */
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct StateDelta {
+    pub units: Vec<crate::game::session::Unit>,
+    pub turn_info: Option<crate::game::turn::TurnInfo>,
+    pub intel: Option<crate::rule::fog::Intel>,
+    pub combat_log_from: usize,
+    pub combat_log: Vec<crate::rule::combat::CombatLogEntry>,
+}
+
+impl StateDelta {
+    pub fn is_empty(&self) -> bool {
+        self.units.is_empty()
+            && self.turn_info.is_none()
+            && self.intel.is_none()
+            && self.combat_log.is_empty()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ProtocolError {
+    #[error("could not parse message: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("could not write message: {0}")]
+    Write(#[from] ron::Error),
+}
+
+/// A message as one line of RON, newline included.
+pub fn encode<T: serde::Serialize>(message: &T) -> Result<Vec<u8>, ProtocolError> {
+    let mut line = ron::ser::to_string(message)?.into_bytes();
+    line.push(b'\n');
+    Ok(line)
+}
+
+pub fn decode<T: serde::de::DeserializeOwned>(line: &[u8]) -> Result<T, ProtocolError> {
+    Ok(ron::de::from_bytes(line)?)
+}
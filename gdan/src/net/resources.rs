@@ -0,0 +1,328 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+use crate::net::connection::{ConnectionId, NetEvent};
+use crate::net::protocol::{ClientMessage, HostMessage, LobbyInfo, SeatInfo};
+use crate::oper::components::Side;
+
+/// Port used when the address does not give one.
+pub const DEFAULT_PORT: u16 = 7777;
+
+/// One side of a network game, played on the host or by a client.
+#[derive(Clone, Debug)]
+pub struct Seat {
+    pub side: Side,
+    pub name: String,
+    /// Played on the host itself.
+    pub local: bool,
+    /// Given to the client holding the seat, to take it back after a lost connection.
+    pub token: Option<u64>,
+    pub connection: Option<ConnectionId>,
+    pub ready: bool,
+    /// The client has been sent the game as it is, and gets deltas from then on.
+    pub synced: bool,
+}
+
+impl Seat {
+    pub fn is_connected(&self) -> bool {
+        self.local || self.connection.is_some()
+    }
+
+    pub fn info(&self) -> SeatInfo {
+        SeatInfo {
+            side: self.side,
+            name: self.name.clone(),
+            connected: self.is_connected(),
+            ready: self.ready,
+        }
+    }
+}
+
+/*
+ * The host of a network game: it accepts clients, gives them the free seat and runs the rules
+ * for everyone. Dropping it closes every connection.
+ */
+#[derive(bevy::ecs::system::Resource)]
+pub struct NetHost {
+    pub port: u16,
+    /// Asset path of the scenario played.
+    pub scenario: String,
+    pub seats: Vec<Seat>,
+    pub lobby: LobbyInfo,
+    events: Mutex<Receiver<NetEvent<ClientMessage>>>,
+    connected: Mutex<Receiver<(ConnectionId, Sender<HostMessage>)>>,
+    connections: bevy::utils::HashMap<ConnectionId, Sender<HostMessage>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl NetHost {
+    /// Listens on every interface on the port of `address`, any free one for port 0;
+    /// the host plays `side`.
+    pub fn start(
+        address: &str,
+        name: &str,
+        side: Side,
+        scenario: &str,
+    ) -> std::io::Result<NetHost> {
+        let port = address
+            .rsplit_once(':')
+            .and_then(|(_, port)| port.parse().ok())
+            .unwrap_or(DEFAULT_PORT);
+        let listener = std::net::TcpListener::bind(("0.0.0.0", port))?;
+        let port = listener.local_addr()?.port();
+        let (incoming, events) = std::sync::mpsc::channel();
+        let (connected_sender, connected) = std::sync::mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        crate::net::connection::spawn_listener(listener, incoming, connected_sender, stop.clone())?;
+        let seat = |side: Side, local: bool| Seat {
+            side,
+            name: if local {
+                name.to_string()
+            } else {
+                String::new()
+            },
+            local,
+            token: None,
+            connection: None,
+            ready: false,
+            synced: false,
+        };
+        let mut host = NetHost {
+            port,
+            scenario: scenario.to_string(),
+            seats: vec![seat(side, true), seat(side.opponent(), false)],
+            lobby: LobbyInfo::default(),
+            events: Mutex::new(events),
+            connected: Mutex::new(connected),
+            connections: bevy::utils::HashMap::new(),
+            stop,
+        };
+        host.refresh_lobby();
+        Ok(host)
+    }
+
+    /// Everything that happened on the connections since the last call.
+    pub fn poll(&mut self) -> Vec<NetEvent<ClientMessage>> {
+        if let Ok(connected) = self.connected.lock() {
+            self.connections.extend(connected.try_iter());
+        }
+        let events: Vec<NetEvent<ClientMessage>> = match self.events.lock() {
+            Ok(events) => events.try_iter().collect(),
+            Err(_) => Vec::new(),
+        };
+        for event in events.iter() {
+            if let NetEvent::Disconnected(id) = event {
+                self.connections.remove(id);
+            }
+        }
+        events
+    }
+
+    pub fn send(&self, id: ConnectionId, message: HostMessage) {
+        if let Some(sender) = self.connections.get(&id) {
+            let _ = sender.send(message);
+        }
+    }
+
+    /// Sends `message` to the clients holding a seat.
+    pub fn broadcast(&self, message: HostMessage) {
+        for id in self.seats.iter().filter_map(|seat| seat.connection) {
+            self.send(id, message.clone());
+        }
+    }
+
+    pub fn seat_mut(&mut self, id: ConnectionId) -> Option<&mut Seat> {
+        self.seats
+            .iter_mut()
+            .find(|seat| seat.connection == Some(id))
+    }
+
+    pub fn local_seat_mut(&mut self) -> Option<&mut Seat> {
+        self.seats.iter_mut().find(|seat| seat.local)
+    }
+
+    /// Every seat is taken and its player ready.
+    pub fn all_ready(&self) -> bool {
+        self.seats
+            .iter()
+            .all(|seat| seat.is_connected() && seat.ready)
+    }
+
+    /// Updates the lobby and sends it to the clients.
+    pub fn refresh_lobby(&mut self) {
+        self.lobby = LobbyInfo {
+            scenario: self.scenario.clone(),
+            seats: self.seats.iter().map(Seat::info).collect(),
+        };
+        self.broadcast(HostMessage::Lobby(self.lobby.clone()));
+    }
+}
+
+/// What the host last sent to the client of one side, to send it only what changed since.
+#[derive(Default)]
+pub struct SentState {
+    pub units: bevy::utils::HashMap<u32, crate::game::session::Unit>,
+    pub turn_info: Option<crate::game::turn::TurnInfo>,
+    pub intel: Option<crate::rule::fog::Intel>,
+    /// Entries of the host's combat log looked at, and how many of them were sent.
+    pub combat_log: usize,
+    pub combat_log_sent: usize,
+}
+
+impl SentState {
+    /*
+     * The units as `side` knows them: its own and the enemies in sight as they are, the enemies out
+     * of sight as they were last seen, in the hex they were last seen in. Enemies never seen are
+     * left out.
+     */
+    pub fn seen_by(
+        &self,
+        side: Side,
+        units: &[crate::game::session::Unit],
+        intel: &crate::rule::fog::Intel,
+    ) -> Vec<crate::game::session::Unit> {
+        units
+            .iter()
+            .filter_map(|unit| {
+                let id = unit.oper.id;
+                if unit.oper.side == side || intel.is_spotted(id) {
+                    return Some(unit.clone());
+                }
+                match (intel.last_known.get(&id), intel.last_seen.get(&id)) {
+                    (Some(hex), Some(oper)) => Some(crate::game::session::Unit {
+                        oper: oper.clone(),
+                        hex: *hex,
+                        counter: unit.counter.clone(),
+                        fired: false,
+                    }),
+                    _ => self.units.get(&id).cloned(),
+                }
+            })
+            .collect()
+    }
+
+    /// Whether `side` may learn of a fight: one of its units or an enemy it spotted took part.
+    fn knows_of(
+        side: Side,
+        units: &[crate::game::session::Unit],
+        intel: &crate::rule::fog::Intel,
+        entry: &crate::rule::combat::CombatLogEntry,
+    ) -> bool {
+        [entry.attacker_id, entry.defender_id].iter().any(|id| {
+            intel.is_spotted(*id)
+                || units
+                    .iter()
+                    .any(|unit| unit.oper.id == *id && unit.oper.side == side)
+        })
+    }
+
+    /// What changed for the client of `side` since the last delta, which is then taken as sent.
+    pub fn delta(
+        &mut self,
+        side: Side,
+        units: &[crate::game::session::Unit],
+        intel: &crate::rule::fog::Intel,
+        turn_info: &crate::game::turn::TurnInfo,
+        combat_log: &[crate::rule::combat::CombatLogEntry],
+    ) -> crate::net::protocol::StateDelta {
+        let seen = self.seen_by(side, units, intel);
+        if combat_log.len() < self.combat_log {
+            // the log went back, e.g. a replay was wound back: it is sent again from the start
+            self.combat_log = 0;
+            self.combat_log_sent = 0;
+        }
+        let entries: Vec<crate::rule::combat::CombatLogEntry> = combat_log[self.combat_log..]
+            .iter()
+            .filter(|entry| Self::knows_of(side, units, intel, entry))
+            .cloned()
+            .collect();
+        let delta = crate::net::protocol::StateDelta {
+            units: seen
+                .iter()
+                .filter(|unit| self.units.get(&unit.oper.id) != Some(*unit))
+                .cloned()
+                .collect(),
+            turn_info: (self.turn_info.as_ref() != Some(turn_info)).then(|| turn_info.clone()),
+            intel: (self.intel.as_ref() != Some(intel)).then(|| intel.clone()),
+            combat_log_from: self.combat_log_sent,
+            combat_log: entries,
+        };
+        self.units = seen.into_iter().map(|unit| (unit.oper.id, unit)).collect();
+        self.turn_info = Some(turn_info.clone());
+        self.intel = Some(intel.clone());
+        self.combat_log = combat_log.len();
+        self.combat_log_sent += delta.combat_log.len();
+        delta
+    }
+}
+
+impl Drop for NetHost {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/*
+ * A client of a network game: it keeps connecting to the host, sends it the orders of its side
+ * and queues the state the host sends back until the map can show it.
+ */
+#[derive(bevy::ecs::system::Resource)]
+pub struct NetClient {
+    pub address: String,
+    pub name: String,
+    pub token: Option<u64>,
+    pub connected: bool,
+    pub ready: bool,
+    pub lobby: LobbyInfo,
+    pub status: String,
+    /// State received from the host and not shown yet, oldest first.
+    pub pending: Vec<crate::net::protocol::StateDelta>,
+    events: Mutex<Receiver<NetEvent<HostMessage>>>,
+    outgoing: Sender<ClientMessage>,
+    stop: Arc<AtomicBool>,
+}
+
+impl NetClient {
+    pub fn start(address: &str, name: &str) -> NetClient {
+        let (incoming, events) = std::sync::mpsc::channel();
+        let (outgoing, outgoing_receiver) = std::sync::mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        crate::net::connection::spawn_client(
+            address.to_string(),
+            incoming,
+            outgoing_receiver,
+            stop.clone(),
+        );
+        NetClient {
+            address: address.to_string(),
+            name: name.to_string(),
+            token: None,
+            connected: false,
+            ready: false,
+            lobby: LobbyInfo::default(),
+            status: format!("connecting to {}", address),
+            pending: Vec::new(),
+            events: Mutex::new(events),
+            outgoing,
+            stop,
+        }
+    }
+
+    pub fn poll(&mut self) -> Vec<NetEvent<HostMessage>> {
+        match self.events.lock() {
+            Ok(events) => events.try_iter().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    pub fn send(&self, message: ClientMessage) {
+        let _ = self.outgoing.send(message);
+    }
+}
+
+impl Drop for NetClient {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
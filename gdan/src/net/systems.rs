@@ -0,0 +1,603 @@
+use bevy::prelude::*;
+
+use crate::net::connection::NetEvent;
+use crate::net::protocol::{ClientMessage, HostMessage};
+
+/*
+ * Entering the lobby starts the network side of the game: the host listens for a client,
+ * a client starts connecting to the host, and leaves the rules to it.
+ */
+pub fn start_network(
+    mut commands: Commands,
+    setup: Res<crate::game::resources::GameSetup>,
+    asset_server: Res<AssetServer>,
+    selected_scenario: Res<crate::game::resources::SelectedScenario>,
+) {
+    info!("start_network");
+    match setup.mode {
+        crate::game::resources::GameMode::Host => {
+            let scenario = asset_server
+                .get_path(selected_scenario.0.id())
+                .map(|path| path.path().to_string_lossy().into_owned())
+                .unwrap_or_default();
+            match crate::net::resources::NetHost::start(
+                &setup.address,
+                &setup.name,
+                setup.side,
+                &scenario,
+            ) {
+                Ok(host) => commands.insert_resource(host),
+                Err(error) => warn!("could not host on {}: {}", setup.address, error),
+            }
+        }
+        crate::game::resources::GameMode::Join => {
+            commands.insert_resource(crate::net::resources::NetClient::start(
+                &setup.address,
+                &setup.name,
+            ));
+            commands.insert_resource(crate::rule::resources::RemoteRules);
+        }
+        _ => {}
+    }
+}
+
+/// Leaving the network game closes its connections.
+pub fn stop_network(mut commands: Commands) {
+    info!("stop_network");
+    commands.remove_resource::<crate::net::resources::NetHost>();
+    commands.remove_resource::<crate::net::resources::NetClient>();
+    commands.remove_resource::<crate::rule::resources::RemoteRules>();
+}
+
+/*
+ * The lobby lists the sides of the game, who plays them and whether they are ready.
+ * The host starts the game once both players are ready.
+ */
+pub fn lobby_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
+    info!("lobby_menu");
+    commands.spawn((Camera2dBundle::default(), crate::net::entities::LobbyMenu));
+    let text_style = TextStyle {
+        font: asset_server.load("fonts/FiraSans-Bold.ttf"),
+        font_size: 40.,
+        color: Color::rgb(0.9, 0.9, 0.9),
+    };
+    commands
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    height: Val::Percent(100.),
+                    flex_direction: FlexDirection::Column,
+                    align_items: AlignItems::Center,
+                    justify_content: JustifyContent::Center,
+                    row_gap: Val::Px(15.),
+                    ..default()
+                },
+                ..default()
+            },
+            crate::net::entities::LobbyMenu,
+        ))
+        .with_children(|parent| {
+            parent.spawn((
+                TextBundle::from_section("Network game", text_style.clone()),
+                crate::net::entities::LobbyMenu,
+            ));
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                        font_size: 24.,
+                        color: Color::WHITE,
+                    },
+                )
+                .with_text_justify(JustifyText::Center),
+                crate::net::entities::LobbyText,
+                crate::net::entities::LobbyMenu,
+            ));
+            for (button, label) in [
+                (crate::net::entities::LobbyButton::Ready, "Ready"),
+                (crate::net::entities::LobbyButton::Back, "Back"),
+            ] {
+                parent
+                    .spawn((
+                        ButtonBundle {
+                            style: Style {
+                                width: Val::Px(200.),
+                                height: Val::Px(65.),
+                                border: UiRect::all(Val::Px(5.)),
+                                justify_content: JustifyContent::Center,
+                                align_items: AlignItems::Center,
+                                ..default()
+                            },
+                            border_color: BorderColor(Color::BLACK),
+                            background_color: Color::rgb(0.15, 0.15, 0.15).into(),
+                            ..default()
+                        },
+                        button,
+                        crate::net::entities::LobbyMenu,
+                    ))
+                    .with_children(|parent| {
+                        parent.spawn((
+                            TextBundle::from_section(label, text_style.clone()),
+                            crate::net::entities::LobbyMenu,
+                        ));
+                    });
+            }
+        });
+}
+
+pub fn lobby_buttons(
+    interaction_query: Query<
+        (&Interaction, &crate::net::entities::LobbyButton),
+        Changed<Interaction>,
+    >,
+    host: Option<ResMut<crate::net::resources::NetHost>>,
+    client: Option<ResMut<crate::net::resources::NetClient>>,
+    mut next_state: ResMut<NextState<crate::MyAppState>>,
+) {
+    for (interaction, button) in interaction_query.iter() {
+        if *interaction != Interaction::Pressed {
+            continue;
+        }
+        match button {
+            crate::net::entities::LobbyButton::Ready => {
+                if let Some(mut host) = host {
+                    if let Some(seat) = host.local_seat_mut() {
+                        seat.ready = !seat.ready;
+                    }
+                    host.refresh_lobby();
+                } else if let Some(mut client) = client {
+                    client.ready = !client.ready;
+                    client.send(ClientMessage::Ready(client.ready));
+                }
+                return;
+            }
+            crate::net::entities::LobbyButton::Back => {
+                next_state.set(crate::MyAppState::GameSetup);
+            }
+        }
+    }
+}
+
+fn lobby_label(lobby: &crate::net::protocol::LobbyInfo, status: &str) -> String {
+    let scenario = std::path::Path::new(&lobby.scenario)
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let mut label = format!("Scenario: {}\n\n", scenario);
+    for seat in lobby.seats.iter() {
+        let player = match (seat.connected, seat.name.is_empty()) {
+            (false, true) => "(free)".to_string(),
+            (false, false) => format!("{} (disconnected)", seat.name),
+            (true, _) => seat.name.clone(),
+        };
+        label.push_str(&format!(
+            "{:<5} {:<24} {}\n",
+            format!("{:?}", seat.side),
+            player,
+            if seat.ready { "ready" } else { "not ready" }
+        ));
+    }
+    label.push('\n');
+    label.push_str(status);
+    label
+}
+
+pub fn update_lobby_text(
+    host: Option<Res<crate::net::resources::NetHost>>,
+    client: Option<Res<crate::net::resources::NetClient>>,
+    mut query_text: Query<&mut Text, With<crate::net::entities::LobbyText>>,
+) {
+    let label = if let Some(host) = host {
+        lobby_label(&host.lobby, &format!("Hosting on port {}", host.port))
+    } else if let Some(client) = client {
+        lobby_label(&client.lobby, &client.status)
+    } else {
+        "Could not start the network game, see the log".to_string()
+    };
+    for mut text in query_text.iter_mut() {
+        if text.sections[0].value != label {
+            text.sections[0].value = label.clone();
+        }
+    }
+}
+
+pub fn despawn_lobby_menu(
+    mut commands: Commands,
+    query: Query<Entity, With<crate::net::entities::LobbyMenu>>,
+) {
+    info!("despawn_lobby_menu");
+    for entity in query.iter() {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// The host goes to the map once both players are ready; clients follow when sent the game.
+pub fn host_start_game(
+    host: Res<crate::net::resources::NetHost>,
+    mut next_state: ResMut<NextState<crate::MyAppState>>,
+) {
+    if host.all_ready() {
+        info!("host_start_game");
+        next_state.set(crate::MyAppState::MapMenu);
+    }
+}
+
+/*
+ * Why the host will not carry out `command` from the player of `side`: clients only give orders
+ * in their own turn, to their own units, against enemies they have spotted.
+ * The rules check everything else, as they do for the host's own orders.
+ */
+fn refuse_command(
+    command: &crate::rule::commands::GameCommand,
+    side: crate::oper::components::Side,
+    turn_info: &crate::game::turn::TurnInfo,
+    fog: &crate::rule::resources::FogOfWar,
+    query_oper: &Query<&crate::oper::components::Oper>,
+) -> Option<String> {
+    if turn_info.side != side {
+        return Some("not your turn".to_string());
+    }
+    let side_of = |id: u32| {
+        query_oper
+            .iter()
+            .find(|oper| oper.id == id)
+            .map(|oper| oper.side)
+    };
+    match *command {
+        crate::rule::commands::GameCommand::Select { .. } => {
+            Some("selections are not sent to the host".to_string())
+        }
+        crate::rule::commands::GameCommand::Move { oper, .. }
+        | crate::rule::commands::GameCommand::Attack { oper, .. }
+            if side_of(oper) != Some(side) =>
+        {
+            Some(format!("unit {} is not yours", oper))
+        }
+        crate::rule::commands::GameCommand::Attack { target, .. }
+            if !fog.has_spotted(side, target) =>
+        {
+            Some(format!("unit {} has not been spotted", target))
+        }
+        _ => None,
+    }
+}
+
+/*
+ * Takes the messages of the clients: a `Hello` gets the free seat, or the seat whose token it
+ * gives back; orders are checked and then carried out like the host's own.
+ * A seat stays reserved for its player after a lost connection, once the game has started.
+ */
+pub fn host_receive(
+    mut host: ResMut<crate::net::resources::NetHost>,
+    turn_info: Res<crate::game::turn::TurnInfo>,
+    fog: Res<crate::rule::resources::FogOfWar>,
+    query_oper: Query<&crate::oper::components::Oper>,
+    mut game_commands: EventWriter<crate::rule::commands::GameCommand>,
+) {
+    let events = host.poll();
+    if events.is_empty() {
+        return;
+    }
+    let started = turn_info.phase != crate::game::entities::GamePhase::Inactive;
+    let mut changed = false;
+    for event in events {
+        match event {
+            NetEvent::Connected(_) => {}
+            NetEvent::Message(id, ClientMessage::Hello { name, token }) => {
+                let reclaimed = host
+                    .seats
+                    .iter()
+                    .position(|seat| !seat.local && token.is_some() && seat.token == token);
+                let free = host.seats.iter().position(|seat| {
+                    !seat.local && seat.connection.is_none() && (seat.token.is_none() || !started)
+                });
+                let Some(index) = reclaimed.or(free) else {
+                    host.send(id, HostMessage::Refused("every seat is taken".to_string()));
+                    continue;
+                };
+                let seat = &mut host.seats[index];
+                if reclaimed.is_none() {
+                    seat.token = Some(rand::random());
+                    seat.ready = false;
+                }
+                seat.name = name;
+                seat.connection = Some(id);
+                seat.synced = false;
+                info!("{} plays {:?}", seat.name, seat.side);
+                let welcome = HostMessage::Welcome {
+                    side: seat.side,
+                    token: seat.token.unwrap_or_default(),
+                };
+                host.send(id, welcome);
+                changed = true;
+            }
+            NetEvent::Message(id, ClientMessage::Ready(ready)) => {
+                if let Some(seat) = host.seat_mut(id) {
+                    seat.ready = ready;
+                    changed = true;
+                }
+            }
+            NetEvent::Message(id, ClientMessage::Command(command)) => {
+                let Some(side) = host.seat_mut(id).map(|seat| seat.side) else {
+                    continue;
+                };
+                match refuse_command(&command, side, &turn_info, &fog, &query_oper) {
+                    None => {
+                        game_commands.send(command);
+                    }
+                    Some(reason) => {
+                        info!("{:?} from {:?}: {}", command, side, reason);
+                        host.send(id, HostMessage::Rejected { command, reason });
+                    }
+                }
+            }
+            NetEvent::Disconnected(id) => {
+                if let Some(seat) = host.seat_mut(id) {
+                    info!("{} ({:?}) lost the connection", seat.name, seat.side);
+                    seat.connection = None;
+                    seat.synced = false;
+                    changed = true;
+                }
+            }
+        }
+    }
+    if changed {
+        host.refresh_lobby();
+    }
+}
+
+/*
+ * Keeps the clients in step with the game: a client that has not seen it yet gets the game as its
+ * side knows it, then every frame what changed of that, if anything did.
+ * Enemies out of sight of a side are never sent where they are, only where they were last seen.
+ */
+pub fn host_sync_state(
+    mut host: ResMut<crate::net::resources::NetHost>,
+    turn_info: Res<crate::game::turn::TurnInfo>,
+    combat_log: Res<crate::rule::resources::CombatLog>,
+    fog: Res<crate::rule::resources::FogOfWar>,
+    query_oper: Query<crate::game::session::UnitComponents>,
+    mut sent: Local<
+        bevy::utils::HashMap<crate::oper::components::Side, crate::net::resources::SentState>,
+    >,
+) {
+    if turn_info.phase == crate::game::entities::GamePhase::Inactive {
+        sent.clear();
+        return;
+    }
+    let (_, units) = crate::game::session::units_from_world(query_oper.iter());
+    for index in 0..host.seats.len() {
+        let (side, synced) = (host.seats[index].side, host.seats[index].synced);
+        let Some(id) = host.seats[index].connection else {
+            continue;
+        };
+        let intel = fog.intel.get(&side).cloned().unwrap_or_default();
+        let state = sent.entry(side).or_default();
+        if synced {
+            let delta = state.delta(side, &units, &intel, &turn_info, &combat_log.0);
+            if !delta.is_empty() {
+                host.send(id, HostMessage::State(delta));
+            }
+            continue;
+        }
+        // the dice stay on the host, and each side only learns what it has spotted
+        *state = crate::net::resources::SentState::default();
+        let known = state.delta(side, &units, &intel, &turn_info, &combat_log.0);
+        let save = crate::game::save::SaveGame {
+            scenario: host.scenario.clone(),
+            turn_info: turn_info.clone(),
+            // deliberately not the host's dice: a client knowing the seed could foretell every roll
+            rng: crate::rule::resources::GameRng::default(),
+            units: known.units,
+            combat_log: known.combat_log,
+            intel: [(side, intel)].into_iter().collect(),
+            replay: None,
+            setup: None,
+            fog_view: None,
+        };
+        host.send(id, HostMessage::Start(Box::new(save)));
+        host.seats[index].synced = true;
+    }
+}
+
+/*
+ * Takes the messages of the host. The first `Start` sets up the map like a loaded game;
+ * the game sent again after a reconnection, and the deltas, wait in `NetClient::pending`
+ * for `client_apply_states`.
+ */
+#[allow(clippy::too_many_arguments)]
+pub fn client_receive(
+    mut client: ResMut<crate::net::resources::NetClient>,
+    mut setup: ResMut<crate::game::resources::GameSetup>,
+    turn_info: Res<crate::game::turn::TurnInfo>,
+    asset_server: Res<AssetServer>,
+    mut selected_scenario: ResMut<crate::game::resources::SelectedScenario>,
+    mut pending_load: ResMut<crate::game::resources::PendingLoad>,
+    state: Res<State<crate::MyAppState>>,
+    mut next_state: ResMut<NextState<crate::MyAppState>>,
+) {
+    let events = client.poll();
+    for event in events {
+        match event {
+            NetEvent::Connected(_) => {
+                client.connected = true;
+                client.status = format!("Connected to {}", client.address);
+                let hello = ClientMessage::Hello {
+                    name: client.name.clone(),
+                    token: client.token,
+                };
+                client.send(hello);
+                if client.ready {
+                    client.send(ClientMessage::Ready(true));
+                }
+            }
+            NetEvent::Message(_, HostMessage::Welcome { side, token }) => {
+                info!("playing {:?}", side);
+                client.token = Some(token);
+                client.status = format!("Connected to {}, playing {:?}", client.address, side);
+                setup.side = side;
+            }
+            NetEvent::Message(_, HostMessage::Refused(reason)) => {
+                client.status = format!("Refused by the host: {}", reason);
+            }
+            NetEvent::Message(_, HostMessage::Lobby(lobby)) => {
+                client.lobby = lobby;
+            }
+            NetEvent::Message(_, HostMessage::Start(save)) => {
+                if *state.get() == crate::MyAppState::MapMenu
+                    && turn_info.phase != crate::game::entities::GamePhase::Inactive
+                {
+                    client.pending.push(crate::net::protocol::StateDelta {
+                        intel: save.intel.get(&setup.side).cloned(),
+                        units: save.units,
+                        turn_info: Some(save.turn_info),
+                        combat_log_from: 0,
+                        combat_log: save.combat_log,
+                    });
+                } else {
+                    info!("client_receive, starting {}", save.scenario);
+                    selected_scenario.0 = asset_server.load(save.scenario.clone());
+                    pending_load.0 = Some(*save);
+                    client.pending.clear();
+                    next_state.set(crate::MyAppState::MapMenu);
+                }
+            }
+            NetEvent::Message(_, HostMessage::State(delta)) => {
+                client.pending.push(delta);
+            }
+            NetEvent::Message(_, HostMessage::Rejected { command, reason }) => {
+                warn!("{:?} rejected by the host: {}", command, reason);
+            }
+            NetEvent::Disconnected(_) => {
+                client.connected = false;
+                client.status = format!("Connection lost, reconnecting to {}", client.address);
+            }
+        }
+    }
+}
+
+/// Sends the orders given on this machine to the host; selections stay here.
+pub fn client_send_commands(
+    mut game_commands: EventReader<crate::rule::commands::GameCommand>,
+    client: Res<crate::net::resources::NetClient>,
+) {
+    for command in game_commands.read() {
+        if !matches!(command, crate::rule::commands::GameCommand::Select { .. }) {
+            client.send(ClientMessage::Command(command.clone()));
+        }
+    }
+}
+
+/*
+ * Shows the state sent by the host, once the map has been set up; moved units walk their way.
+ * Enemies first seen since the game was sent get their counters then.
+ */
+pub fn client_apply_states(
+    mut commands: Commands,
+    setup: Res<crate::game::resources::GameSetup>,
+    mut client: ResMut<crate::net::resources::NetClient>,
+    mut turn_info: ResMut<crate::game::turn::TurnInfo>,
+    mut combat_log: ResMut<crate::rule::resources::CombatLog>,
+    mut fog: ResMut<crate::rule::resources::FogOfWar>,
+    mut query_oper: Query<crate::game::session::UnitComponentsMut>,
+) {
+    if turn_info.phase == crate::game::entities::GamePhase::Inactive || client.pending.is_empty() {
+        return;
+    }
+    let entities: bevy::utils::HashMap<u32, Entity> = query_oper
+        .iter()
+        .map(|(entity, oper, _, _, _)| (oper.id, entity))
+        .collect();
+    for delta in std::mem::take(&mut client.pending) {
+        let mut shown = Vec::new();
+        for unit in delta.units {
+            if let Some(entity) = entities.get(&unit.oper.id) {
+                shown.push((*entity, unit));
+                continue;
+            }
+            let mut entity = commands.spawn((
+                unit.oper,
+                unit.counter,
+                crate::map::components::HexPosition(unit.hex),
+                crate::map::entities::MapNC,
+                crate::map::entities::MapMenu,
+            ));
+            if unit.fired {
+                entity.insert(crate::rule::components::Fired);
+            }
+        }
+        let moved = crate::game::session::write_units_back(&mut commands, &mut query_oper, shown);
+        for (entity, from, to) in moved {
+            commands
+                .entity(entity)
+                .insert(crate::rule::components::MovePath {
+                    path: from.line_to(to),
+                    progress: 0.,
+                });
+        }
+        if let Some(intel) = delta.intel {
+            fog.intel.insert(setup.side, intel);
+        }
+        if let Some(info) = delta.turn_info {
+            if *turn_info != info {
+                *turn_info = info;
+            }
+        }
+        if !delta.combat_log.is_empty() {
+            combat_log.0.truncate(delta.combat_log_from);
+            combat_log.0.extend(delta.combat_log);
+        }
+    }
+}
+
+/// The network line sits at the bottom right corner of the map.
+pub fn network_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+    info!("network_hud");
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                font_size: 20.,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            right: Val::Px(10.),
+            bottom: Val::Px(10.),
+            ..default()
+        }),
+        crate::net::entities::NetStatusText,
+        crate::map::entities::MapMenu,
+    ));
+}
+
+pub fn update_net_status_text(
+    host: Option<Res<crate::net::resources::NetHost>>,
+    client: Option<Res<crate::net::resources::NetClient>>,
+    mut query_text: Query<&mut Text, With<crate::net::entities::NetStatusText>>,
+) {
+    let label = if let Some(host) = host {
+        host.seats
+            .iter()
+            .filter(|seat| !seat.local)
+            .map(|seat| match seat.connection {
+                Some(_) => format!("{:?}: {}", seat.side, seat.name),
+                None => format!("{:?}: waiting for {} to reconnect", seat.side, seat.name),
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    } else if let Some(client) = client {
+        client.status.clone()
+    } else {
+        String::new()
+    };
+    for mut text in query_text.iter_mut() {
+        if text.sections[0].value != label {
+            text.sections[0].value = label.clone();
+        }
+    }
+}
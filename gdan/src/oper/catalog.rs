@@ -0,0 +1,95 @@
+// Unit definitions: operator types and their placement on the map
+
+use bevy::prelude::*;
+
+/*
+ * Operator types are described in a RON file, e.g. "wg/mlx/oper/opers.ron":
+ * `types` holds the values shared by every unit of a type and the counter image.
+ * Scenarios place individual operators on the map by zero-based (col, row);
+ * a unit may override the counter image of its type, e.g. to show the other side's colours.
+ */
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct OperType {
+    pub id: String,
+    pub name: String,
+    pub kind: crate::oper::components::OperKind,
+    pub strength: i32,
+    pub movement_points: u32,
+    pub attack: i32,
+    pub defence: i32,
+    #[serde(default)]
+    pub weapons: Vec<crate::oper::components::Weapon>,
+    /// Sighting range in hexes.
+    #[serde(default = "crate::oper::components::default_sight")]
+    pub sight: i32,
+    pub counter: String,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct OperPlacement {
+    pub id: u32,
+    pub type_id: String,
+    pub side: crate::oper::components::Side,
+    pub col: i32,
+    pub row: i32,
+    #[serde(default)]
+    pub counter: Option<String>,
+}
+
+#[derive(Asset, TypePath, Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+pub struct OperCatalog {
+    pub types: Vec<OperType>,
+}
+
+impl OperCatalog {
+    pub fn get_type(&self, type_id: &str) -> Option<&OperType> {
+        self.types.iter().find(|oper_type| oper_type.id == type_id)
+    }
+
+    /// Builds the operator for a placement, with its hex and counter image.
+    /// Returns `None` when the placement names an unknown type.
+    pub fn instantiate(
+        &self,
+        placement: &OperPlacement,
+    ) -> Option<(
+        crate::oper::components::Oper,
+        crate::map::hex::Hex,
+        crate::oper::components::OperCounter,
+    )> {
+        let oper_type = self.get_type(&placement.type_id)?;
+        let oper = crate::oper::components::Oper {
+            id: placement.id,
+            name: oper_type.name.clone(),
+            side: placement.side,
+            kind: oper_type.kind,
+            strength: oper_type.strength,
+            max_strength: oper_type.strength,
+            movement_points: oper_type.movement_points,
+            movement_left: oper_type.movement_points,
+            attack: oper_type.attack,
+            defence: oper_type.defence,
+            weapons: oper_type.weapons.clone(),
+            state: crate::oper::components::OperState::Ready,
+            sight: oper_type.sight,
+        };
+        let hex = crate::map::hex::Hex::from_offset(crate::map::hex::OffsetCoord::new(
+            placement.col,
+            placement.row,
+        ));
+        let counter = crate::oper::components::OperCounter(
+            placement
+                .counter
+                .clone()
+                .unwrap_or_else(|| oper_type.counter.clone()),
+        );
+        Some((oper, hex, counter))
+    }
+}
+
+impl crate::tools::ron_file::RonFile for OperCatalog {
+    const WHAT: &'static str = "unit definitions";
+    const EXTENSIONS: &'static [&'static str] = &["opers.ron"];
+}
+
+pub type OperCatalogLoader = crate::tools::ron_file::RonLoader<OperCatalog>;
@@ -0,0 +1,91 @@
+use bevy::prelude::*;
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum Side {
+    #[default]
+    Red,
+    Blue,
+}
+
+impl Side {
+    pub fn opponent(self) -> Side {
+        match self {
+            Side::Red => Side::Blue,
+            Side::Blue => Side::Red,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub enum OperKind {
+    Infantry,
+    Tank,
+    Vehicle,
+    Artillery,
+}
+
+#[derive(
+    Clone, Copy, Debug, Default, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+pub enum OperState {
+    #[default]
+    Ready,
+    /// Pinned down by fire: cannot move, fires at reduced effect.
+    Suppressed,
+    Destroyed,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Weapon {
+    pub name: String,
+    /// Maximum range in hexes.
+    pub range: i32,
+    pub attack: i32,
+    /// Effective against armoured targets.
+    #[serde(default)]
+    pub anti_armour: bool,
+}
+
+/// Sighting range in hexes of units whose type does not give one.
+pub const DEFAULT_SIGHT: i32 = 8;
+
+pub fn default_sight() -> i32 {
+    DEFAULT_SIGHT
+}
+
+/*
+ * An operator is one counter on the map: a unit with its combat values and current condition.
+ * The hex it stands in is kept in a separate HexPosition component.
+ */
+#[derive(Component, Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Oper {
+    pub id: u32,
+    pub name: String,
+    pub side: Side,
+    pub kind: OperKind,
+    pub strength: i32,
+    pub max_strength: i32,
+    /// Movement points available at the start of each turn.
+    pub movement_points: u32,
+    /// Movement points not yet spent this turn.
+    pub movement_left: u32,
+    pub attack: i32,
+    pub defence: i32,
+    pub weapons: Vec<Weapon>,
+    pub state: OperState,
+    /// Distance in hexes at which the unit spots enemies it has a line of sight to.
+    #[serde(default = "default_sight")]
+    pub sight: i32,
+}
+
+impl Oper {
+    pub fn is_destroyed(&self) -> bool {
+        self.state == OperState::Destroyed
+    }
+}
+
+/// Image path of the counter drawn for an operator.
+#[derive(Component, Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct OperCounter(pub String);
@@ -5,3 +5,7 @@ pub struct OperMenu;
 
 #[derive(Component)]
 pub struct Oper3D;
+
+/// Last known position of an enemy unit out of sight, shown faded on the map.
+#[derive(Component)]
+pub struct FogGhost(pub u32);
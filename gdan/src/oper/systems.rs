@@ -262,6 +262,38 @@ pub fn oper3d_scale_wander(
     }
 }
 
+/*
+ * Operators are spawned as plain data (Oper + HexPosition) once the unit definitions are loaded;
+ * the counter sprite is added separately, so the same entities work without any rendering.
+ */
+pub fn add_oper_sprites(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    hex_grid: Res<crate::map::resources::HexGrid>,
+    query_oper: Query<
+        (
+            Entity,
+            &crate::oper::components::OperCounter,
+            &crate::map::components::HexPosition,
+        ),
+        Added<crate::oper::components::Oper>,
+    >,
+) {
+    for (entity, counter, hex_position) in query_oper.iter() {
+        let center = hex_grid.0.hex_to_world(hex_position.0);
+        commands.entity(entity).insert(SpriteBundle {
+            texture: asset_server.load(counter.0.clone()),
+            sprite: Sprite {
+                // square counter that fits inside the hex whatever the image size
+                custom_size: Some(Vec2::splat(hex_grid.0.size * 1.2)),
+                ..default()
+            },
+            transform: Transform::from_xyz(center.x, center.y, 1.),
+            ..default()
+        });
+    }
+}
+
 pub fn despawn_oper_menu(
     query_enemy: Query<Entity, With<crate::oper::entities::OperMenu>>,
     mut commands: Commands,
@@ -273,3 +305,89 @@ pub fn despawn_oper_menu(
         // .insert(Friendly);
     }
 }
+
+/// Suppressed counters are dimmed, destroyed ones fade out.
+pub fn update_oper_sprites(
+    mut query_oper: Query<
+        (&crate::oper::components::Oper, &mut Sprite),
+        Changed<crate::oper::components::Oper>,
+    >,
+) {
+    for (oper, mut sprite) in query_oper.iter_mut() {
+        sprite.color = match oper.state {
+            crate::oper::components::OperState::Ready => Color::WHITE,
+            crate::oper::components::OperState::Suppressed => Color::rgb(0.6, 0.6, 0.6),
+            crate::oper::components::OperState::Destroyed => Color::rgba(0.3, 0.3, 0.3, 0.4),
+        };
+    }
+}
+
+/// Hides the enemy units the side the map is shown for has not spotted.
+pub fn update_oper_visibility(
+    fog: Res<crate::rule::resources::FogOfWar>,
+    turn_info: Res<crate::game::turn::TurnInfo>,
+    mut query_oper: Query<(&crate::oper::components::Oper, &mut Visibility)>,
+) {
+    for (oper, mut visibility) in query_oper.iter_mut() {
+        let wanted = if fog.is_shown(turn_info.side, oper) {
+            Visibility::Inherited
+        } else {
+            Visibility::Hidden
+        };
+        if *visibility != wanted {
+            *visibility = wanted;
+        }
+    }
+}
+
+/*
+ * Enemy units that went out of sight of the side the map is shown for are drawn as faded
+ * counters in the hex they were last seen in.
+ */
+pub fn update_fog_ghosts(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    hex_grid: Res<crate::map::resources::HexGrid>,
+    fog: Res<crate::rule::resources::FogOfWar>,
+    turn_info: Res<crate::game::turn::TurnInfo>,
+    query_oper: Query<(
+        &crate::oper::components::Oper,
+        &crate::oper::components::OperCounter,
+    )>,
+    mut query_ghost: Query<(Entity, &crate::oper::entities::FogGhost, &mut Transform)>,
+) {
+    let mut ghosts: bevy::utils::HashMap<u32, crate::map::hex::Hex> = fog
+        .shown(turn_info.side)
+        .map(|(_, intel)| intel.ghosts().collect())
+        .unwrap_or_default();
+    for (entity, ghost, mut transform) in query_ghost.iter_mut() {
+        match ghosts.remove(&ghost.0) {
+            Some(hex) => {
+                let center = hex_grid.0.hex_to_world(hex);
+                transform.translation.x = center.x;
+                transform.translation.y = center.y;
+            }
+            None => commands.entity(entity).despawn(),
+        }
+    }
+    for (id, hex) in ghosts {
+        let Some((_, counter)) = query_oper.iter().find(|(oper, _)| oper.id == id) else {
+            continue;
+        };
+        let center = hex_grid.0.hex_to_world(hex);
+        commands.spawn((
+            SpriteBundle {
+                texture: asset_server.load(counter.0.clone()),
+                sprite: Sprite {
+                    color: Color::rgba(1., 1., 1., 0.35),
+                    custom_size: Some(Vec2::splat(hex_grid.0.size * 1.2)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(center.x, center.y, 0.9),
+                ..default()
+            },
+            crate::oper::entities::FogGhost(id),
+            crate::map::entities::MapMenu,
+        ));
+    }
+}
@@ -0,0 +1,582 @@
+// Combat rules: combat result tables, dice and what fire does to the target
+
+use bevy::prelude::*;
+
+use crate::map::hexside::HexsideFeature;
+use crate::map::terrain::{HexTerrain, TerrainType};
+use crate::oper::components::{Oper, OperKind, OperState, Weapon};
+
+/*
+ * Combat is resolved on a combat result table loaded from a RON file, e.g.
+ * "wg/mlx/rule/combat.crt.ron":
+ *
+ * (
+ *     dice: 2,
+ *     columns: [
+ *         (differential: -2, results: [NoEffect, NoEffect, ..., Damaged(1)]),
+ *         (differential: 0, results: [NoEffect, ..., Suppressed, Damaged(1), Damaged(2)]),
+ *     ],
+ *     modifiers: (terrain: {Forest: -2, Urban: -3}, hexside: {Wall: -2}, long_range: -1, ...),
+ * )
+ *
+ * 1. The attack factor is the weapon's attack when shooting, the unit's own attack in close
+ *    combat, scaled down with the attacker's losses.
+ * 2. The differential, attack factor minus the defender's defence, picks the column:
+ *    the rightmost column whose `differential` is not above it, the leftmost one if none.
+ * 3. `dice` six-sided dice are rolled and the modifiers added to their total.
+ * 4. The modified total picks the result in the column, `results[0]` being the lowest possible
+ *    total; totals off either end of the column use the first or last result.
+ */
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum CombatResult {
+    NoEffect,
+    Suppressed,
+    /// Strength points lost; the unit is destroyed when none are left.
+    Damaged(i32),
+    Destroyed,
+}
+
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct CrtColumn {
+    /// Lowest attack/defence differential that uses this column.
+    pub differential: i32,
+    pub results: Vec<CombatResult>,
+}
+
+/// Dice roll modifiers, added to the dice total.
+#[derive(Clone, Debug, Default, serde::Serialize, serde::Deserialize)]
+#[serde(default)]
+pub struct CombatModifiers {
+    /// Cover of the defender's hex.
+    pub terrain: bevy::utils::HashMap<TerrainType, i32>,
+    /// Cover of the hexside the attack comes into the defender's hex through.
+    pub hexside: bevy::utils::HashMap<HexsideFeature, i32>,
+    /// Target further away than half the weapon's range.
+    pub long_range: i32,
+    /// Attacker stands higher than the defender.
+    pub firing_down: i32,
+    /// Attacker stands lower than the defender.
+    pub firing_up: i32,
+    pub suppressed_attacker: i32,
+    /// Armoured target hit by a weapon that is not anti-armour.
+    pub armour_without_anti_armour: i32,
+    pub close_combat: i32,
+}
+
+#[derive(Asset, TypePath, Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct CombatTable {
+    /// Number of six-sided dice rolled.
+    pub dice: u32,
+    /// Columns ordered by increasing differential.
+    pub columns: Vec<CrtColumn>,
+    #[serde(default)]
+    pub modifiers: CombatModifiers,
+}
+
+/// Everything the table needs to know about one attack.
+#[derive(Clone, Copy, Debug)]
+pub struct CombatInput<'a> {
+    pub attacker: &'a Oper,
+    pub defender: &'a Oper,
+    /// Weapon fired, `None` for close combat.
+    pub weapon: Option<&'a Weapon>,
+    /// Distance in hexes.
+    pub distance: i32,
+    pub attacker_terrain: &'a HexTerrain,
+    pub defender_terrain: &'a HexTerrain,
+    /// Features of the hexside the attack comes into the defender's hex through.
+    pub defender_hexside: &'a [HexsideFeature],
+    /// Whether the attacker can see the defender.
+    pub visible: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CombatOutcome {
+    pub attack: i32,
+    pub differential: i32,
+    /// Differential of the column used.
+    pub column: i32,
+    pub dice: Vec<u32>,
+    /// Every modifier applied, by name.
+    pub modifiers: Vec<(String, i32)>,
+    /// Dice total with the modifiers added.
+    pub total: i32,
+    pub result: CombatResult,
+}
+
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum CombatError {
+    #[error("the attacker is destroyed")]
+    AttackerDestroyed,
+    #[error("the target is already destroyed")]
+    DefenderDestroyed,
+    #[error("units of the same side cannot attack each other")]
+    SameSide,
+    #[error("the target cannot be seen")]
+    NotVisible,
+    #[error("the target is out of range")]
+    OutOfRange,
+    #[error("close combat needs adjacent units")]
+    NotAdjacent,
+    #[error("the combat table has no columns")]
+    EmptyTable,
+}
+
+impl CombatTable {
+    pub fn column(&self, differential: i32) -> Option<&CrtColumn> {
+        self.columns
+            .iter()
+            .rev()
+            .find(|column| column.differential <= differential)
+            .or(self.columns.first())
+    }
+
+    /// Modifiers that apply to an attack, by name.
+    pub fn modifiers(&self, input: &CombatInput) -> Vec<(String, i32)> {
+        let modifiers = &self.modifiers;
+        let mut applied = Vec::new();
+        let mut add = |name: &str, value: i32| {
+            if value != 0 {
+                applied.push((name.to_string(), value));
+            }
+        };
+        let cover = input.defender_terrain.terrain;
+        add(
+            &format!("{cover:?}").to_lowercase(),
+            modifiers.terrain.get(&cover).copied().unwrap_or(0),
+        );
+        for feature in input.defender_hexside.iter() {
+            add(
+                &format!("{feature:?}").to_lowercase(),
+                modifiers.hexside.get(feature).copied().unwrap_or(0),
+            );
+        }
+        match input.weapon {
+            Some(weapon) => {
+                if input.distance * 2 > weapon.range {
+                    add("long range", modifiers.long_range);
+                }
+                if is_armoured(input.defender.kind) && !weapon.anti_armour {
+                    add("armour", modifiers.armour_without_anti_armour);
+                }
+            }
+            None => add("close combat", modifiers.close_combat),
+        }
+        match input
+            .attacker_terrain
+            .elevation
+            .cmp(&input.defender_terrain.elevation)
+        {
+            std::cmp::Ordering::Greater => add("firing down", modifiers.firing_down),
+            std::cmp::Ordering::Less => add("firing up", modifiers.firing_up),
+            std::cmp::Ordering::Equal => {}
+        }
+        if input.attacker.state == OperState::Suppressed {
+            add("suppressed", modifiers.suppressed_attacker);
+        }
+        applied
+    }
+
+    /// Checks an attack is allowed; returns its attack factor, differential and column.
+    fn attack_column(&self, input: &CombatInput) -> Result<(i32, i32, &CrtColumn), CombatError> {
+        check_attack(input)?;
+        let base = match input.weapon {
+            Some(weapon) => weapon.attack,
+            None => input.attacker.attack,
+        };
+        let attack = scaled_attack(base, input.attacker);
+        let differential = attack - input.defender.defence;
+        let column = self.column(differential).ok_or(CombatError::EmptyTable)?;
+        Ok((attack, differential, column))
+    }
+
+    /// Result in `column` for a modified dice `total`.
+    fn result(&self, column: &CrtColumn, total: i32) -> CombatResult {
+        let index = (total - self.dice as i32).clamp(0, column.results.len() as i32 - 1);
+        column
+            .results
+            .get(index as usize)
+            .copied()
+            .unwrap_or(CombatResult::NoEffect)
+    }
+
+    /// Checks an attack is allowed, rolls the dice and looks up the result.
+    pub fn resolve(
+        &self,
+        input: &CombatInput,
+        rng: &mut impl rand::Rng,
+    ) -> Result<CombatOutcome, CombatError> {
+        let (attack, differential, column) = self.attack_column(input)?;
+        let dice: Vec<u32> = (0..self.dice).map(|_| rng.gen_range(1..=6)).collect();
+        let modifiers = self.modifiers(input);
+        let total =
+            dice.iter().sum::<u32>() as i32 + modifiers.iter().map(|(_, value)| value).sum::<i32>();
+        let result = self.result(column, total);
+        Ok(CombatOutcome {
+            attack,
+            differential,
+            column: column.differential,
+            dice,
+            modifiers,
+            total,
+            result,
+        })
+    }
+
+    /// Chance of each result of an attack, over every total the dice can roll.
+    pub fn result_odds(
+        &self,
+        input: &CombatInput,
+    ) -> Result<Vec<(CombatResult, f32)>, CombatError> {
+        let (_, _, column) = self.attack_column(input)?;
+        let modifier: i32 = self.modifiers(input).iter().map(|(_, value)| value).sum();
+        // chance of each dice total, by dice total
+        let mut totals = vec![1.0_f32];
+        for _ in 0..self.dice {
+            let mut next = vec![0.; totals.len() + 6];
+            for (sum, chance) in totals.iter().enumerate() {
+                for face in 1..=6 {
+                    next[sum + face] += chance / 6.;
+                }
+            }
+            totals = next;
+        }
+        let mut odds: Vec<(CombatResult, f32)> = Vec::new();
+        for (sum, chance) in totals
+            .into_iter()
+            .enumerate()
+            .filter(|(_, chance)| *chance > 0.)
+        {
+            let result = self.result(column, sum as i32 + modifier);
+            match odds.iter_mut().find(|(known, _)| *known == result) {
+                Some((_, total)) => *total += chance,
+                None => odds.push((result, chance)),
+            }
+        }
+        Ok(odds)
+    }
+}
+
+pub fn is_armoured(kind: OperKind) -> bool {
+    matches!(kind, OperKind::Tank | OperKind::Vehicle)
+}
+
+/// Attack factor of a unit that has taken losses, rounded up.
+pub fn scaled_attack(attack: i32, oper: &Oper) -> i32 {
+    if oper.max_strength <= 0 {
+        return attack;
+    }
+    (attack * oper.strength.max(0) + oper.max_strength - 1) / oper.max_strength
+}
+
+pub fn check_attack(input: &CombatInput) -> Result<(), CombatError> {
+    if input.attacker.is_destroyed() {
+        return Err(CombatError::AttackerDestroyed);
+    }
+    if input.defender.is_destroyed() {
+        return Err(CombatError::DefenderDestroyed);
+    }
+    if input.attacker.side == input.defender.side {
+        return Err(CombatError::SameSide);
+    }
+    match input.weapon {
+        Some(weapon) => {
+            if !input.visible {
+                return Err(CombatError::NotVisible);
+            }
+            if input.distance > weapon.range {
+                return Err(CombatError::OutOfRange);
+            }
+        }
+        None => {
+            if input.distance != 1 {
+                return Err(CombatError::NotAdjacent);
+            }
+        }
+    }
+    Ok(())
+}
+
+/*
+ * The weapon a unit fires at a target `distance` hexes away:
+ * the strongest one in range, anti-armour weapons first against tanks and vehicles.
+ */
+pub fn best_weapon<'a>(attacker: &'a Oper, defender: &Oper, distance: i32) -> Option<&'a Weapon> {
+    let armoured = is_armoured(defender.kind);
+    attacker
+        .weapons
+        .iter()
+        .filter(|weapon| weapon.range >= distance)
+        .max_by_key(|weapon| (armoured && weapon.anti_armour, weapon.attack))
+}
+
+/// Applies a combat result to the unit that was attacked.
+pub fn apply_result(oper: &mut Oper, result: CombatResult) {
+    match result {
+        CombatResult::NoEffect => {}
+        CombatResult::Suppressed => {
+            if !oper.is_destroyed() {
+                oper.state = OperState::Suppressed;
+            }
+        }
+        CombatResult::Damaged(loss) => {
+            oper.strength = (oper.strength - loss).max(0);
+            oper.state = if oper.strength == 0 {
+                OperState::Destroyed
+            } else {
+                OperState::Suppressed
+            };
+        }
+        CombatResult::Destroyed => {
+            oper.strength = 0;
+            oper.state = OperState::Destroyed;
+        }
+    }
+}
+
+/// One attack as recorded in the combat log.
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct CombatLogEntry {
+    pub turn: u32,
+    pub attacker_id: u32,
+    pub attacker: String,
+    pub defender_id: u32,
+    pub defender: String,
+    /// Weapon fired, empty for close combat.
+    pub weapon: String,
+    pub distance: i32,
+    pub outcome: CombatOutcome,
+}
+
+impl std::fmt::Display for CombatLogEntry {
+    /*
+     * e.g. "T2 Infantry platoon 1 -> Tank platoon 102, Anti-tank rocket at 2 hexes:
+     *       differential -2 (-2 column), 2d6 [6, 5] forest -2 long range -1 = 8: Suppressed"
+     */
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let outcome = &self.outcome;
+        write!(
+            f,
+            "T{} {} {} -> {} {}, ",
+            self.turn, self.attacker, self.attacker_id, self.defender, self.defender_id
+        )?;
+        if self.weapon.is_empty() {
+            write!(f, "close combat: ")?;
+        } else {
+            write!(f, "{} at {} hexes: ", self.weapon, self.distance)?;
+        }
+        write!(
+            f,
+            "differential {} ({} column), {}d6 {:?}",
+            outcome.differential,
+            outcome.column,
+            outcome.dice.len(),
+            outcome.dice
+        )?;
+        for (name, value) in outcome.modifiers.iter() {
+            write!(f, " {name} {value:+}")?;
+        }
+        write!(f, " = {}: {:?}", outcome.total, outcome.result)
+    }
+}
+
+impl crate::tools::ron_file::RonFile for CombatTable {
+    const WHAT: &'static str = "combat table";
+    const EXTENSIONS: &'static [&'static str] = &["crt.ron"];
+}
+
+pub type CombatTableLoader = crate::tools::ron_file::RonLoader<CombatTable>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::ron_file::RonFile;
+
+    fn table() -> CombatTable {
+        CombatTable::from_bytes(
+            b"(
+                dice: 2,
+                columns: [
+                    (differential: -2, results: [NoEffect, NoEffect, NoEffect, NoEffect, NoEffect, NoEffect, NoEffect, NoEffect, NoEffect, Suppressed, Suppressed]),
+                    (differential: 0, results: [NoEffect, NoEffect, NoEffect, NoEffect, NoEffect, Suppressed, Suppressed, Suppressed, Damaged(1), Damaged(1), Damaged(2)]),
+                    (differential: 2, results: [NoEffect, Suppressed, Suppressed, Suppressed, Damaged(1), Damaged(1), Damaged(2), Damaged(2), Destroyed, Destroyed, Destroyed]),
+                ],
+                modifiers: (terrain: {Forest: -2}, hexside: {Wall: -2}, long_range: -1, firing_down: 1, suppressed_attacker: -2),
+            )",
+        )
+        .unwrap()
+    }
+
+    fn oper(side: crate::oper::components::Side) -> Oper {
+        Oper {
+            id: 1,
+            name: "Infantry platoon".to_string(),
+            side,
+            kind: OperKind::Infantry,
+            strength: 3,
+            max_strength: 3,
+            movement_points: 6,
+            movement_left: 6,
+            attack: 3,
+            defence: 3,
+            weapons: vec![Weapon {
+                name: "Rifle".to_string(),
+                range: 4,
+                attack: 3,
+                anti_armour: false,
+            }],
+            state: OperState::Ready,
+            sight: 8,
+        }
+    }
+
+    /// Red fires its rifle at blue two hexes away, both on open ground.
+    fn input<'a>(red: &'a Oper, blue: &'a Oper, open: &'a HexTerrain) -> CombatInput<'a> {
+        CombatInput {
+            attacker: red,
+            defender: blue,
+            weapon: red.weapons.first(),
+            distance: 2,
+            attacker_terrain: open,
+            defender_terrain: open,
+            defender_hexside: &[],
+            visible: true,
+        }
+    }
+
+    #[test]
+    fn differential_off_the_table_uses_the_end_columns() {
+        let table = table();
+        let column = |differential| table.column(differential).unwrap().differential;
+        assert_eq!(column(-9), -2);
+        assert_eq!(column(-1), -2);
+        assert_eq!(column(0), 0);
+        assert_eq!(column(1), 0);
+        assert_eq!(column(9), 2);
+        let empty = CombatTable {
+            columns: Vec::new(),
+            ..table
+        };
+        assert!(empty.column(0).is_none());
+    }
+
+    #[test]
+    fn cover_and_modifiers_shift_the_total() {
+        let table = table();
+        let (mut red, blue) = (
+            oper(crate::oper::components::Side::Red),
+            oper(crate::oper::components::Side::Blue),
+        );
+        let open = HexTerrain::default();
+        assert!(table.modifiers(&input(&red, &blue, &open)).is_empty());
+        let (hill, forest) = (
+            HexTerrain {
+                terrain: TerrainType::Open,
+                elevation: 1,
+            },
+            HexTerrain {
+                terrain: TerrainType::Forest,
+                elevation: 0,
+            },
+        );
+        red.state = OperState::Suppressed;
+        let shifted = CombatInput {
+            distance: 3,
+            attacker_terrain: &hill,
+            defender_terrain: &forest,
+            defender_hexside: &[HexsideFeature::Wall],
+            ..input(&red, &blue, &open)
+        };
+        let modifiers = table.modifiers(&shifted);
+        assert_eq!(
+            modifiers,
+            [
+                ("forest", -2),
+                ("wall", -2),
+                ("long range", -1),
+                ("firing down", 1),
+                ("suppressed", -2)
+            ]
+            .map(|(name, value)| (name.to_string(), value))
+        );
+        let mut rng: rand_chacha::ChaCha8Rng = rand::SeedableRng::seed_from_u64(3);
+        let outcome = table.resolve(&shifted, &mut rng).unwrap();
+        assert_eq!(outcome.total, outcome.dice.iter().sum::<u32>() as i32 - 6);
+        // the attack of 3 against a defence of 3 is read in the 0 column
+        assert_eq!((outcome.differential, outcome.column), (0, 0));
+    }
+
+    #[test]
+    fn dice_roll_within_their_faces() {
+        let table = table();
+        let (red, blue) = (
+            oper(crate::oper::components::Side::Red),
+            oper(crate::oper::components::Side::Blue),
+        );
+        let open = HexTerrain::default();
+        let mut rng: rand_chacha::ChaCha8Rng = rand::SeedableRng::seed_from_u64(7);
+        for _ in 0..200 {
+            let outcome = table.resolve(&input(&red, &blue, &open), &mut rng).unwrap();
+            assert_eq!(outcome.dice.len(), 2);
+            assert!(outcome.dice.iter().all(|die| (1..=6).contains(die)));
+            assert!((2..=12).contains(&outcome.total));
+        }
+        // totals off the ends of the column use its first and last results
+        let column = table.column(0).unwrap();
+        assert_eq!(table.result(column, -5), CombatResult::NoEffect);
+        assert_eq!(table.result(column, 20), CombatResult::Damaged(2));
+    }
+
+    #[test]
+    fn odds_add_up_to_one() {
+        let table = table();
+        let (red, blue) = (
+            oper(crate::oper::components::Side::Red),
+            oper(crate::oper::components::Side::Blue),
+        );
+        let open = HexTerrain::default();
+        let odds = table.result_odds(&input(&red, &blue, &open)).unwrap();
+        let sum: f32 = odds.iter().map(|(_, chance)| chance).sum();
+        assert!((sum - 1.).abs() < 1e-5);
+        // 12 only, one chance in 36
+        let (_, damaged) = odds
+            .iter()
+            .find(|(result, _)| *result == CombatResult::Damaged(2))
+            .unwrap();
+        assert!((damaged - 1. / 36.).abs() < 1e-5);
+        // behind a wall in a forest, only the highest rolls suppress
+        let forest = HexTerrain {
+            terrain: TerrainType::Forest,
+            elevation: 0,
+        };
+        let covered = CombatInput {
+            defender_terrain: &forest,
+            defender_hexside: &[HexsideFeature::Wall],
+            ..input(&red, &blue, &open)
+        };
+        let odds = table.result_odds(&covered).unwrap();
+        assert_eq!(odds.len(), 2);
+        assert_eq!(odds[0].0, CombatResult::NoEffect);
+        assert!((odds.iter().map(|(_, chance)| chance).sum::<f32>() - 1.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn losses_come_off_step_by_step() {
+        let mut unit = oper(crate::oper::components::Side::Blue);
+        apply_result(&mut unit, CombatResult::NoEffect);
+        assert_eq!((unit.strength, unit.state), (3, OperState::Ready));
+        apply_result(&mut unit, CombatResult::Suppressed);
+        assert_eq!((unit.strength, unit.state), (3, OperState::Suppressed));
+        apply_result(&mut unit, CombatResult::Damaged(1));
+        assert_eq!((unit.strength, unit.state), (2, OperState::Suppressed));
+        // more losses than steps left destroy the unit, with no strength below 0
+        apply_result(&mut unit, CombatResult::Damaged(5));
+        assert_eq!((unit.strength, unit.state), (0, OperState::Destroyed));
+        apply_result(&mut unit, CombatResult::Suppressed);
+        assert_eq!(unit.state, OperState::Destroyed);
+        let mut fresh = oper(crate::oper::components::Side::Blue);
+        apply_result(&mut fresh, CombatResult::Destroyed);
+        assert_eq!((fresh.strength, fresh.state), (0, OperState::Destroyed));
+    }
+}
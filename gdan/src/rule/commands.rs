@@ -0,0 +1,301 @@
+// Game commands: the orders players give, checked and carried out by the rules
+
+use bevy::prelude::*;
+
+use crate::game::entities::GamePhase;
+use crate::game::turn::TurnInfo;
+use crate::map::hex::Hex;
+use crate::oper::components::Oper;
+
+/*
+ * Every change to a game goes through a command. Units are named by their scenario id rather
+ * than by entity, so a command means the same thing in the windowed game, in a headless
+ * simulation (`crate::game::session::GameSession`), in a saved replay or over the network.
+ *
+ * The functions below are the rules for each command. They only look at plain data:
+ * `execute` carries out a whole command on a list of units, and both the ECS systems and the
+ * headless session play every command through it, so a replay played either way ends the same.
+ */
+#[derive(Event, Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum GameCommand {
+    /// Pick a unit of the side to play, or drop the selection with `None`.
+    /// Only the player's view changes; the command is kept so replays show what was picked.
+    Select {
+        oper: Option<u32>,
+    },
+    /// Move a unit to a hex it can reach with its movement points left.
+    Move {
+        oper: u32,
+        to: Hex,
+    },
+    /// Attack an enemy unit: fire in the shooting phase, close combat in the close combat phase.
+    Attack {
+        oper: u32,
+        target: u32,
+    },
+    EndPhase,
+}
+
+#[derive(Clone, Debug, PartialEq, thiserror::Error)]
+pub enum CommandError {
+    #[error("no operator with id {0}")]
+    UnknownOper(u32),
+    #[error("the game is not running")]
+    NotRunning,
+    #[error("it is not this side's turn")]
+    NotYourTurn,
+    #[error("not allowed in the {0:?} phase")]
+    WrongPhase(GamePhase),
+    #[error("the hex cannot be reached")]
+    Unreachable,
+    #[error("the side has not spotted unit {0}")]
+    NotSpotted(u32),
+    #[error("the unit has already attacked this phase")]
+    AlreadyAttacked,
+    #[error("no weapon in range")]
+    NoWeaponInRange,
+    #[error(transparent)]
+    Combat(#[from] crate::rule::combat::CombatError),
+}
+
+/// Checks that `oper` belongs to the side to play and that the phase allows the order.
+pub fn check_order(
+    turn_info: &TurnInfo,
+    oper: &Oper,
+    phases: &[GamePhase],
+) -> Result<(), CommandError> {
+    if matches!(turn_info.phase, GamePhase::Inactive | GamePhase::GameOver) {
+        return Err(CommandError::NotRunning);
+    }
+    if oper.side != turn_info.side {
+        return Err(CommandError::NotYourTurn);
+    }
+    if !phases.contains(&turn_info.phase) {
+        return Err(CommandError::WrongPhase(turn_info.phase));
+    }
+    Ok(())
+}
+
+/*
+ * Moves `oper` from `from` to `to` along the cheapest path and spends the movement points.
+ * `occupied` holds the other units on the map. Returns the path taken, both ends included.
+ */
+pub fn move_oper(
+    rules: &crate::rule::resources::RuleBook,
+    turn_info: &TurnInfo,
+    oper: &mut Oper,
+    from: Hex,
+    to: Hex,
+    occupied: &bevy::utils::HashMap<Hex, crate::oper::components::Side>,
+) -> Result<Vec<Hex>, CommandError> {
+    check_order(turn_info, oper, &[GamePhase::Movement])?;
+    let reachable =
+        crate::rule::movement::oper_reachable(oper, from, &rules.terrain, &rules.layout, occupied);
+    let (Some(path), Some(cost)) = (reachable.path_to(to), reachable.cost(to)) else {
+        return Err(CommandError::Unreachable);
+    };
+    if path.len() < 2 {
+        return Err(CommandError::Unreachable);
+    }
+    oper.movement_left -= cost;
+    Ok(path)
+}
+
+/*
+ * Resolves an attack of `attacker` on `defender`, each given with its hex.
+ * The result is not applied: the caller passes `entry.outcome.result` to
+ * `crate::rule::combat::apply_result` on the defender and marks the attacker as having fired.
+ */
+pub fn attack(
+    rules: &crate::rule::resources::RuleBook,
+    turn_info: &TurnInfo,
+    (attacker, from): (&Oper, Hex),
+    (defender, to): (&Oper, Hex),
+    fired: bool,
+    rng: &mut impl rand::Rng,
+) -> Result<crate::rule::combat::CombatLogEntry, CommandError> {
+    check_order(
+        turn_info,
+        attacker,
+        &[GamePhase::Shooting, GamePhase::CloseCombat],
+    )?;
+    if fired {
+        return Err(CommandError::AlreadyAttacked);
+    }
+    let distance = from.distance(to);
+    let weapon = match turn_info.phase {
+        GamePhase::Shooting => Some(
+            crate::rule::combat::best_weapon(attacker, defender, distance)
+                .ok_or(CommandError::NoWeaponInRange)?,
+        ),
+        _ => None,
+    };
+    let input = crate::rule::combat::CombatInput {
+        attacker,
+        defender,
+        weapon,
+        distance,
+        attacker_terrain: rules.terrain.get(from),
+        defender_terrain: rules.terrain.get(to),
+        defender_hexside: rules.terrain.hexsides.entering(from, to),
+        visible: crate::rule::los::line_of_sight(&rules.terrain, from, to).visible,
+    };
+    let outcome = rules.combat_table.resolve(&input, rng)?;
+    Ok(crate::rule::combat::CombatLogEntry {
+        turn: turn_info.turn,
+        attacker_id: attacker.id,
+        attacker: attacker.name.clone(),
+        defender_id: defender.id,
+        defender: defender.name.clone(),
+        weapon: weapon.map_or(String::new(), |weapon| weapon.name.clone()),
+        distance,
+        outcome,
+    })
+}
+
+/// Ends the current phase; `begin_phase` must then be called on every unit.
+pub fn end_phase(turn_info: &mut TurnInfo) -> Result<(), CommandError> {
+    if matches!(turn_info.phase, GamePhase::Inactive | GamePhase::GameOver) {
+        return Err(CommandError::NotRunning);
+    }
+    turn_info.next_phase();
+    Ok(())
+}
+
+/*
+ * What happens to a unit when a phase starts: the side starting its movement phase gets its
+ * movement points back, suppressed units of the side in its end phase rally.
+ * Which units have attacked is forgotten at the start of every phase.
+ */
+pub fn begin_phase(turn_info: &TurnInfo, oper: &mut Oper) {
+    if oper.side != turn_info.side || oper.is_destroyed() {
+        return;
+    }
+    match turn_info.phase {
+        GamePhase::Movement => oper.movement_left = oper.movement_points,
+        GamePhase::End if oper.state == crate::oper::components::OperState::Suppressed => {
+            oper.state = crate::oper::components::OperState::Ready;
+        }
+        _ => {}
+    }
+}
+
+/// What a command carried out did, for the caller to show.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Executed {
+    Selected,
+    /// The path taken, both ends included; it stops short of the hex ordered on contact.
+    Moved(Vec<Hex>),
+    /// The attack, for the caller to add to the combat log.
+    Attacked(crate::rule::combat::CombatLogEntry),
+    PhaseEnded,
+}
+
+/*
+ * Checks and carries out one command on `units`, looking again at what each side has spotted
+ * after a move, an attack or the start of a phase. A refused command changes nothing.
+ * A move running into an enemy out of sight stops in the hex before it with no movement points
+ * left, and the look taken afterwards spots that enemy.
+ */
+pub fn execute(
+    rules: &crate::rule::resources::RuleBook,
+    turn_info: &mut TurnInfo,
+    rng: &mut impl rand::Rng,
+    units: &mut [crate::game::session::Unit],
+    intel: &mut bevy::utils::HashMap<crate::oper::components::Side, crate::rule::fog::Intel>,
+    command: &GameCommand,
+) -> Result<Executed, CommandError> {
+    let index = |units: &[crate::game::session::Unit], id: u32| {
+        units
+            .iter()
+            .position(|unit| unit.oper.id == id)
+            .ok_or(CommandError::UnknownOper(id))
+    };
+    let executed = match *command {
+        GameCommand::Select { oper } => {
+            if let Some(oper) = oper {
+                index(units, oper)?;
+            }
+            return Ok(Executed::Selected);
+        }
+        GameCommand::Move { oper, to } => {
+            let mover = index(units, oper)?;
+            let side = units[mover].oper.side;
+            let spotted = |id: u32| intel.get(&side).is_some_and(|intel| intel.is_spotted(id));
+            // the path is planned round the units the side knows of, as the player planned it
+            let (known, hidden): (Vec<&crate::game::session::Unit>, Vec<_>) = units
+                .iter()
+                .filter(|unit| unit.oper.id != oper && !unit.oper.is_destroyed())
+                .partition(|unit| unit.oper.side == side || spotted(unit.oper.id));
+            let occupied = known
+                .iter()
+                .map(|unit| (unit.hex, unit.oper.side))
+                .collect();
+            let mut moved = units[mover].oper.clone();
+            let path = move_oper(
+                rules,
+                turn_info,
+                &mut moved,
+                units[mover].hex,
+                to,
+                &occupied,
+            )?;
+            // running into an enemy out of sight ends the move in the hex before it
+            if let Some(contact) = path
+                .iter()
+                .position(|hex| hidden.iter().any(|unit| unit.hex == *hex))
+            {
+                let mut path = path;
+                path.truncate(contact);
+                moved.movement_left = 0;
+                units[mover].oper = moved;
+                units[mover].hex = *path.last().unwrap();
+                Executed::Moved(path)
+            } else {
+                units[mover].oper = moved;
+                units[mover].hex = to;
+                Executed::Moved(path)
+            }
+        }
+        GameCommand::Attack { oper, target } => {
+            let attacker = index(units, oper)?;
+            let defender = index(units, target)?;
+            check_order(
+                turn_info,
+                &units[attacker].oper,
+                &[GamePhase::Shooting, GamePhase::CloseCombat],
+            )?;
+            // nothing is fired at that the side has not spotted, so nothing is learnt from a refusal
+            let side = units[attacker].oper.side;
+            if units[defender].oper.side != side
+                && !intel
+                    .get(&side)
+                    .is_some_and(|intel| intel.is_spotted(target))
+            {
+                return Err(CommandError::NotSpotted(target));
+            }
+            let entry = attack(
+                rules,
+                turn_info,
+                (&units[attacker].oper, units[attacker].hex),
+                (&units[defender].oper, units[defender].hex),
+                units[attacker].fired,
+                rng,
+            )?;
+            crate::rule::combat::apply_result(&mut units[defender].oper, entry.outcome.result);
+            units[attacker].fired = true;
+            Executed::Attacked(entry)
+        }
+        GameCommand::EndPhase => {
+            end_phase(turn_info)?;
+            for unit in units.iter_mut() {
+                begin_phase(turn_info, &mut unit.oper);
+                unit.fired = false;
+            }
+            Executed::PhaseEnded
+        }
+    };
+    let seen: Vec<(&Oper, Hex)> = units.iter().map(|unit| (&unit.oper, unit.hex)).collect();
+    crate::rule::fog::update_intel(intel, &rules.terrain, &seen);
+    Ok(executed)
+}
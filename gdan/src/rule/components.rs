@@ -0,0 +1,13 @@
+use bevy::prelude::*;
+
+/// A counter sliding along the hexes of its last move; removed when it arrives.
+#[derive(Component, Clone, Debug)]
+pub struct MovePath {
+    pub path: Vec<crate::map::hex::Hex>,
+    /// How far along the path the counter is, in hexes.
+    pub progress: f32,
+}
+
+/// The operator has already attacked in the current phase.
+#[derive(Component, Clone, Debug)]
+pub struct Fired;
@@ -2,3 +2,11 @@ use bevy::prelude::*;
 
 #[derive(Component)]
 pub struct RuleMenu;
+
+/// Shading over a hex the selected operator can move to.
+#[derive(Component)]
+pub struct ReachableHex;
+
+/// In-game panel listing the latest combat results.
+#[derive(Component)]
+pub struct CombatLogText;
@@ -0,0 +1,247 @@
+// Fog of war: which enemy units each side has spotted, and where it last saw the others
+
+use crate::map::hex::Hex;
+use crate::map::terrain::{TerrainMap, TerrainType};
+use crate::oper::components::{Oper, Side};
+
+/*
+ * A unit spots an enemy within its sighting range (`Oper::sight`) when it has a line of sight to
+ * it. Units in forest or urban hexes are concealed and only spotted at half that range, rounded
+ * up. Adjacent units always spot each other; destroyed units spot nothing.
+ *
+ * Each side keeps an `Intel`: the enemy units it has spotted now, and the hex and state each
+ * enemy was last seen in. An enemy slipping out of sight leaves a ghost at its last known hex,
+ * as it was then, until it is spotted again or a unit of the side looks into that hex and finds
+ * it empty.
+ * An enemy seen destroyed is forgotten.
+ */
+
+pub fn is_concealing(terrain: TerrainType) -> bool {
+    matches!(terrain, TerrainType::Forest | TerrainType::Urban)
+}
+
+/// Whether `observer`, standing in `from`, spots a unit standing in `to`.
+pub fn spots(terrain: &TerrainMap, observer: &Oper, from: Hex, to: Hex) -> bool {
+    if observer.is_destroyed() {
+        return false;
+    }
+    let distance = from.distance(to);
+    if distance <= 1 {
+        return true;
+    }
+    let range = if is_concealing(terrain.get(to).terrain) {
+        (observer.sight + 1) / 2
+    } else {
+        observer.sight
+    };
+    distance <= range && crate::rule::los::line_of_sight(terrain, from, to).visible
+}
+
+/// What one side knows of the enemy.
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Intel {
+    /// Enemy units in sight now, destroyed ones included.
+    pub spotted: bevy::utils::HashSet<u32>,
+    /// Hex each enemy unit still in the game was last seen in.
+    pub last_known: bevy::utils::HashMap<u32, Hex>,
+    /// The enemy units of `last_known` as they were when last seen.
+    #[serde(default)]
+    pub last_seen: bevy::utils::HashMap<u32, Oper>,
+}
+
+impl Intel {
+    pub fn is_spotted(&self, id: u32) -> bool {
+        self.spotted.contains(&id)
+    }
+
+    /// Enemy units out of sight, and the hex they were last seen in.
+    pub fn ghosts(&self) -> impl Iterator<Item = (u32, Hex)> + '_ {
+        self.last_known
+            .iter()
+            .filter(|(id, _)| !self.spotted.contains(*id))
+            .map(|(id, hex)| (*id, *hex))
+    }
+
+    /// Looks again from every unit of `side` at every unit of the other side.
+    pub fn update(&mut self, terrain: &TerrainMap, side: Side, units: &[(&Oper, Hex)]) {
+        let observers: Vec<(&Oper, Hex)> = units
+            .iter()
+            .copied()
+            .filter(|(oper, _)| oper.side == side && !oper.is_destroyed())
+            .collect();
+        let seen = |hex: Hex| {
+            observers
+                .iter()
+                .any(|(observer, from)| spots(terrain, observer, *from, hex))
+        };
+        self.spotted.clear();
+        for (enemy, hex) in units.iter().filter(|(oper, _)| oper.side != side) {
+            if !seen(*hex) {
+                continue;
+            }
+            self.spotted.insert(enemy.id);
+            if enemy.is_destroyed() {
+                self.last_known.remove(&enemy.id);
+            } else {
+                self.last_known.insert(enemy.id, *hex);
+                self.last_seen.insert(enemy.id, (*enemy).clone());
+            }
+        }
+        // ghosts in hexes seen to be empty are dropped
+        let spotted = &self.spotted;
+        self.last_known
+            .retain(|id, hex| spotted.contains(id) || !seen(*hex));
+        let last_known = &self.last_known;
+        self.last_seen.retain(|id, _| last_known.contains_key(id));
+    }
+}
+
+/// Looks again at what both sides have spotted, after units moved or were hit.
+pub fn update_intel(
+    intel: &mut bevy::utils::HashMap<Side, Intel>,
+    terrain: &TerrainMap,
+    units: &[(&Oper, Hex)],
+) {
+    for side in [Side::Red, Side::Blue] {
+        intel.entry(side).or_default().update(terrain, side, units);
+    }
+}
+
+/// Which side the map is shown for.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub enum FogView {
+    /// The side to play, changing with the turn.
+    #[default]
+    SideToPlay,
+    Side(Side),
+    /// No fog, every unit shown.
+    All,
+}
+
+impl FogView {
+    /// The next view: side to play, red, blue, everything.
+    pub fn next(self) -> FogView {
+        match self {
+            FogView::SideToPlay => FogView::Side(Side::Red),
+            FogView::Side(Side::Red) => FogView::Side(Side::Blue),
+            FogView::Side(Side::Blue) => FogView::All,
+            FogView::All => FogView::SideToPlay,
+        }
+    }
+
+    /// The side whose intel is shown, `None` to show everything.
+    pub fn viewer(self, side_to_play: Side) -> Option<Side> {
+        match self {
+            FogView::SideToPlay => Some(side_to_play),
+            FogView::Side(side) => Some(side),
+            FogView::All => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::hex::OffsetCoord;
+    use crate::tools::ron_file::RonFile;
+
+    fn hex(col: i32, row: i32) -> Hex {
+        Hex::from_offset(OffsetCoord::new(col, row))
+    }
+
+    fn oper(id: u32, side: Side) -> Oper {
+        Oper {
+            id,
+            name: format!("{id}"),
+            side,
+            kind: crate::oper::components::OperKind::Infantry,
+            strength: 4,
+            max_strength: 4,
+            movement_points: 6,
+            movement_left: 6,
+            attack: 3,
+            defence: 3,
+            weapons: Vec::new(),
+            state: crate::oper::components::OperState::Ready,
+            sight: 8,
+        }
+    }
+
+    /// Red looks from `from` at a blue unit standing in `to`.
+    fn look(intel: &mut Intel, terrain: &TerrainMap, red: &Oper, from: Hex, blue: &Oper, to: Hex) {
+        intel.update(terrain, Side::Red, &[(red, from), (blue, to)]);
+    }
+
+    #[test]
+    fn enemy_out_of_sight_leaves_a_ghost() {
+        let terrain = TerrainMap::default();
+        let (red, blue) = (oper(1, Side::Red), oper(101, Side::Blue));
+        let mut intel = Intel::default();
+        look(&mut intel, &terrain, &red, hex(5, 10), &blue, hex(5, 16));
+        assert!(intel.is_spotted(101));
+        assert_eq!(intel.ghosts().count(), 0);
+        // red falls back out of sight of the blue unit and of its hex
+        look(&mut intel, &terrain, &red, hex(5, 0), &blue, hex(5, 16));
+        assert!(!intel.is_spotted(101));
+        assert_eq!(intel.ghosts().collect::<Vec<_>>(), vec![(101, hex(5, 16))]);
+        // what happens to it out of sight is not learnt
+        let mut hit = blue.clone();
+        hit.strength = 1;
+        look(&mut intel, &terrain, &red, hex(5, 0), &hit, hex(5, 16));
+        assert_eq!(intel.last_seen[&101], blue);
+    }
+
+    #[test]
+    fn ghost_dropped_when_its_hex_is_seen_empty() {
+        let terrain = TerrainMap::default();
+        let (red, blue) = (oper(1, Side::Red), oper(101, Side::Blue));
+        let mut intel = Intel::default();
+        look(&mut intel, &terrain, &red, hex(5, 10), &blue, hex(5, 16));
+        look(&mut intel, &terrain, &red, hex(5, 0), &blue, hex(5, 30));
+        assert_eq!(intel.ghosts().collect::<Vec<_>>(), vec![(101, hex(5, 16))]);
+        // still out of sight, but red sees the hex it was in is empty
+        look(&mut intel, &terrain, &red, hex(5, 10), &blue, hex(5, 30));
+        assert!(!intel.is_spotted(101));
+        assert!(intel.last_known.is_empty());
+        assert!(intel.last_seen.is_empty());
+    }
+
+    #[test]
+    fn enemy_seen_destroyed_is_forgotten() {
+        let terrain = TerrainMap::default();
+        let (red, mut blue) = (oper(1, Side::Red), oper(101, Side::Blue));
+        let mut intel = Intel::default();
+        look(&mut intel, &terrain, &red, hex(5, 10), &blue, hex(5, 16));
+        blue.state = crate::oper::components::OperState::Destroyed;
+        look(&mut intel, &terrain, &red, hex(5, 10), &blue, hex(5, 16));
+        assert!(intel.is_spotted(101));
+        assert!(intel.last_known.is_empty());
+        // and leaves no ghost once out of sight
+        look(&mut intel, &terrain, &red, hex(5, 0), &blue, hex(5, 16));
+        assert_eq!(intel.ghosts().count(), 0);
+    }
+
+    #[test]
+    fn wall_on_the_way_hides_the_enemy() {
+        let terrain = TerrainMap::from_bytes(
+            b"(default: (terrain: Open), hexsides: [(col: 5, row: 2, side: South, features: [Wall])])",
+        )
+        .unwrap();
+        let red = oper(1, Side::Red);
+        assert!(!spots(&terrain, &red, hex(5, 0), hex(5, 6)));
+        assert!(spots(&TerrainMap::default(), &red, hex(5, 0), hex(5, 6)));
+        // next door, a wall hides nothing
+        assert!(spots(&terrain, &red, hex(5, 2), hex(5, 3)));
+        let mut intel = Intel::default();
+        look(
+            &mut intel,
+            &terrain,
+            &red,
+            hex(5, 0),
+            &oper(101, Side::Blue),
+            hex(5, 6),
+        );
+        assert!(!intel.is_spotted(101));
+        assert!(intel.last_known.is_empty());
+    }
+}
@@ -0,0 +1,113 @@
+// Line of sight: can one hex see another across the terrain in between
+
+use crate::map::hex::Hex;
+use crate::map::hexside::{Hexside, HexsideFeature};
+use crate::map::terrain::{TerrainMap, TerrainType};
+
+/*
+ * Heights are measured in elevation levels.
+ * An observer or target stands EYE_HEIGHT above the ground of its hex; forests and buildings
+ * rise OBSTACLE_HEIGHT above theirs, walls WALL_HEIGHT above the higher of the two hexes they
+ * stand between. The sight line runs straight from observer to target over the hexes crossed
+ * between the two hex centres; it is blocked by the first hex or wall that reaches above it.
+ * The two end hexes and the walls around them never block, so adjacent hexes always see each
+ * other and a unit behind a wall can look over it.
+ */
+pub const EYE_HEIGHT: f32 = 0.5;
+pub const OBSTACLE_HEIGHT: f32 = 1.;
+pub const WALL_HEIGHT: f32 = 1.;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LineOfSight {
+    pub visible: bool,
+    /// First hex that blocks the view, if any; the hex behind the wall when a wall blocks it.
+    pub blocking: Option<Hex>,
+    /// The wall that blocks the view, if any.
+    pub blocking_wall: Option<Hexside>,
+    /// Hexes crossed from observer to target, both included.
+    pub hexes: Vec<Hex>,
+}
+
+/// Height of whatever stands in a hex and may block the view.
+pub fn obstacle_height(terrain: TerrainType) -> f32 {
+    match terrain {
+        TerrainType::Forest | TerrainType::Urban => OBSTACLE_HEIGHT,
+        TerrainType::Open | TerrainType::Water => 0.,
+    }
+}
+
+pub fn line_of_sight(terrain: &TerrainMap, from: Hex, to: Hex) -> LineOfSight {
+    let hexes = from.line_to(to);
+    let from_height = terrain.get(from).elevation as f32 + EYE_HEIGHT;
+    let to_height = terrain.get(to).elevation as f32 + EYE_HEIGHT;
+    let steps = (hexes.len() - 1).max(1) as f32;
+    let sight_line = |step: f32| from_height + (to_height - from_height) * (step / steps);
+    let mut blocking = None;
+    let mut blocking_wall = None;
+    // walls first: the wall on the way into a hex is met before the hex
+    for i in 1..hexes.len().saturating_sub(1) {
+        let (before, hex) = (hexes[i - 1], hexes[i]);
+        if i > 1 && terrain.hexsides.has(before, hex, HexsideFeature::Wall) {
+            let ground = terrain
+                .get(before)
+                .elevation
+                .max(terrain.get(hex).elevation);
+            if ground as f32 + WALL_HEIGHT > sight_line(i as f32 - 0.5) {
+                blocking = Some(hex);
+                blocking_wall = Hexside::between(before, hex);
+                break;
+            }
+        }
+        let hex_terrain = terrain.get(hex);
+        if hex_terrain.elevation as f32 + obstacle_height(hex_terrain.terrain)
+            > sight_line(i as f32)
+        {
+            blocking = Some(hex);
+            break;
+        }
+    }
+    LineOfSight {
+        visible: blocking.is_none(),
+        blocking,
+        blocking_wall,
+        hexes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::hex::OffsetCoord;
+    use crate::tools::ron_file::RonFile;
+
+    fn hex(col: i32, row: i32) -> Hex {
+        Hex::from_offset(OffsetCoord::new(col, row))
+    }
+
+    fn walled(row: i32) -> TerrainMap {
+        let file = format!(
+            "(default: (terrain: Open), hexsides: [(col: 5, row: {row}, side: South, features: [Wall])])"
+        );
+        TerrainMap::from_bytes(file.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn wall_on_the_way_blocks_the_view() {
+        let sight = line_of_sight(&walled(2), hex(5, 0), hex(5, 6));
+        assert_eq!(
+            sight.hexes,
+            (0..=6).map(|row| hex(5, row)).collect::<Vec<_>>()
+        );
+        assert!(!sight.visible);
+        assert_eq!(sight.blocking, Some(hex(5, 3)));
+        assert_eq!(sight.blocking_wall, Hexside::between(hex(5, 2), hex(5, 3)));
+        assert!(line_of_sight(&TerrainMap::default(), hex(5, 0), hex(5, 6)).visible);
+    }
+
+    #[test]
+    fn units_look_over_their_own_wall() {
+        assert!(line_of_sight(&walled(0), hex(5, 0), hex(5, 6)).visible);
+        assert!(line_of_sight(&walled(5), hex(5, 0), hex(5, 6)).visible);
+        assert!(line_of_sight(&walled(0), hex(5, 0), hex(5, 1)).visible);
+    }
+}
@@ -1,4 +1,10 @@
+pub mod combat;
+pub mod commands;
 pub mod components;
 pub mod entities;
+pub mod fog;
+pub mod los;
+pub mod movement;
+pub mod plugin;
 pub mod resources;
 pub mod systems;
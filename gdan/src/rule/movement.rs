@@ -0,0 +1,216 @@
+// Movement rules: movement point costs and the hexes a unit can reach
+
+use crate::map::hex::Hex;
+use crate::map::hexside::HexsideFeature;
+use crate::map::terrain::{TerrainMap, TerrainType};
+use crate::oper::components::OperKind;
+
+/*
+ * Entering a hex costs movement points depending on its terrain:
+ *
+ *   along a road   1      (across a road or bridge hexside, whatever the terrain)
+ *   open           2
+ *   urban          3
+ *   forest         3      6 for tanks and vehicles
+ *   water          impassable
+ *
+ * Climbing costs 2 more per elevation level gained. Crossing a river hexside costs 4 more unless
+ * a bridge spans it, climbing over a wall 2 more, see `crate::map::hexside`.
+ */
+pub const ROAD_COST: u32 = 1;
+pub const CLIMB_COST: u32 = 2;
+pub const RIVER_COST: u32 = 4;
+pub const WALL_COST: u32 = 2;
+
+/// Movement points needed to step from `from` into the adjacent hex `to`, `None` if impassable.
+pub fn step_cost(terrain: &TerrainMap, kind: OperKind, from: Hex, to: Hex) -> Option<u32> {
+    let from_terrain = terrain.get(from);
+    let to_terrain = terrain.get(to);
+    let hexside = terrain.hexsides.between(from, to);
+    let tracked = matches!(kind, OperKind::Tank | OperKind::Vehicle);
+    let base = match to_terrain.terrain {
+        TerrainType::Water => return None,
+        _ if hexside.iter().any(|feature| feature.crosses()) => ROAD_COST,
+        TerrainType::Open => 2,
+        TerrainType::Urban => 3,
+        TerrainType::Forest if tracked => 6,
+        TerrainType::Forest => 3,
+    };
+    let climb = (to_terrain.elevation - from_terrain.elevation).max(0) as u32 * CLIMB_COST;
+    let river =
+        if hexside.contains(&HexsideFeature::River) && !hexside.contains(&HexsideFeature::Bridge) {
+            RIVER_COST
+        } else {
+            0
+        };
+    let wall = if hexside.contains(&HexsideFeature::Wall) {
+        WALL_COST
+    } else {
+        0
+    };
+    Some(base + climb + river + wall)
+}
+
+/// Result of a movement search: the cheapest cost and previous hex for every reachable hex.
+#[derive(Clone, Debug, Default)]
+pub struct Reachable {
+    pub start: Hex,
+    pub costs: bevy::utils::HashMap<Hex, (u32, Hex)>,
+    /// Reached hexes the unit may move through but not stop in.
+    pub pass_through: bevy::utils::HashSet<Hex>,
+}
+
+impl Reachable {
+    /// Whether the unit can end its move in `hex`.
+    pub fn contains(&self, hex: Hex) -> bool {
+        self.costs.contains_key(&hex) && !self.pass_through.contains(&hex)
+    }
+
+    pub fn cost(&self, hex: Hex) -> Option<u32> {
+        self.costs.get(&hex).map(|(cost, _)| *cost)
+    }
+
+    /// Hexes the unit may end its move in, the start hex excluded.
+    pub fn destinations(&self) -> impl Iterator<Item = Hex> + '_ {
+        self.costs
+            .keys()
+            .copied()
+            .filter(|hex| *hex != self.start && !self.pass_through.contains(hex))
+    }
+
+    /// Shortest path from the start hex to `hex`, both ends included.
+    pub fn path_to(&self, hex: Hex) -> Option<Vec<Hex>> {
+        if !self.contains(hex) {
+            return None;
+        }
+        let mut path = vec![hex];
+        let mut current = hex;
+        while current != self.start {
+            current = self.costs[&current].1;
+            path.push(current);
+        }
+        path.reverse();
+        Some(path)
+    }
+}
+
+/*
+ * Dijkstra over the hex grid, bounded by the movement points available.
+ * `step_cost` returns the cost of moving between two adjacent hexes, `None` if the step is not
+ * allowed (off the map, impassable terrain, enemy units). `can_stop` filters hexes the unit may
+ * move through but not end in, such as hexes held by friendly units.
+ */
+pub fn reachable(
+    start: Hex,
+    movement_points: u32,
+    step_cost: impl Fn(Hex, Hex) -> Option<u32>,
+    can_stop: impl Fn(Hex) -> bool,
+) -> Reachable {
+    let mut costs = bevy::utils::HashMap::new();
+    costs.insert(start, (0, start));
+    let mut frontier = std::collections::BinaryHeap::new();
+    frontier.push(std::cmp::Reverse((0, start)));
+    while let Some(std::cmp::Reverse((cost, hex))) = frontier.pop() {
+        if costs.get(&hex).is_some_and(|(best, _)| *best < cost) {
+            continue;
+        }
+        for next in hex.neighbors() {
+            let Some(step) = step_cost(hex, next) else {
+                continue;
+            };
+            let next_cost = cost + step;
+            if next_cost > movement_points {
+                continue;
+            }
+            if costs.get(&next).is_none_or(|(best, _)| next_cost < *best) {
+                costs.insert(next, (next_cost, hex));
+                frontier.push(std::cmp::Reverse((next_cost, next)));
+            }
+        }
+    }
+    let pass_through = costs
+        .keys()
+        .copied()
+        .filter(|hex| *hex != start && !can_stop(*hex))
+        .collect();
+    Reachable {
+        start,
+        costs,
+        pass_through,
+    }
+}
+
+/*
+ * Hexes a unit can reach this turn from `from`: it may not leave the map or enter hexes held by
+ * the enemy, and may pass through but not stop in hexes held by its own side.
+ * Suppressed and destroyed units cannot move at all.
+ */
+pub fn oper_reachable(
+    oper: &crate::oper::components::Oper,
+    from: Hex,
+    terrain: &TerrainMap,
+    layout: &crate::map::hex::HexLayout,
+    occupied: &bevy::utils::HashMap<Hex, crate::oper::components::Side>,
+) -> Reachable {
+    let movement_points = match oper.state {
+        crate::oper::components::OperState::Ready => oper.movement_left,
+        _ => 0,
+    };
+    reachable(
+        from,
+        movement_points,
+        |a, b| {
+            if !layout.contains(b) || occupied.get(&b).is_some_and(|side| *side != oper.side) {
+                return None;
+            }
+            step_cost(terrain, oper.kind, a, b)
+        },
+        |hex| !occupied.contains_key(&hex),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::hex::OffsetCoord;
+    use crate::tools::ron_file::RonFile;
+
+    fn hex(col: i32, row: i32) -> Hex {
+        Hex::from_offset(OffsetCoord::new(col, row))
+    }
+
+    fn terrain() -> TerrainMap {
+        TerrainMap::from_bytes(
+            b"(
+                default: (terrain: Open),
+                hexes: [(col: 1, row: 1, terrain: Forest), (col: 3, row: 1, terrain: Water)],
+                hexsides: [
+                    (col: 0, row: 0, side: South, features: [Road]),
+                    (col: 1, row: 0, side: South, features: [River]),
+                    (col: 2, row: 0, side: South, features: [River, Bridge]),
+                    (col: 4, row: 0, side: South, features: [Wall]),
+                ],
+            )",
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn hexsides_change_the_cost_of_a_step() {
+        let terrain = terrain();
+        let cost = |kind, col| step_cost(&terrain, kind, hex(col, 0), hex(col, 1));
+        assert_eq!(cost(OperKind::Infantry, 5), Some(2));
+        assert_eq!(cost(OperKind::Infantry, 0), Some(ROAD_COST));
+        // the river comes on top of the forest, and tanks pay more for the forest
+        assert_eq!(cost(OperKind::Infantry, 1), Some(3 + RIVER_COST));
+        assert_eq!(cost(OperKind::Tank, 1), Some(6 + RIVER_COST));
+        assert_eq!(cost(OperKind::Infantry, 2), Some(ROAD_COST));
+        assert_eq!(cost(OperKind::Infantry, 3), None);
+        assert_eq!(cost(OperKind::Infantry, 4), Some(2 + WALL_COST));
+        // hexsides are the same from either side
+        assert_eq!(
+            step_cost(&terrain, OperKind::Infantry, hex(4, 1), hex(4, 0)),
+            Some(2 + WALL_COST)
+        );
+    }
+}
@@ -0,0 +1,47 @@
+// Rules plugin: what an app needs to play a game, and nothing that needs a window
+
+use bevy::prelude::*;
+
+/// The windowed game adds this plugin next to DefaultPlugins; a headless game runs on
+///
+/// ```
+/// use bevy::prelude::*;
+/// use gdan::game::session::GameSession;
+/// use gdan::rule::commands::GameCommand;
+/// use gdan::rule::plugin::RulesPlugin;
+///
+/// let session = GameSession::load("assets", "scenarios/river-crossing.scenario.ron", 42).unwrap();
+/// let mut app = App::new();
+/// app.add_plugins((MinimalPlugins, RulesPlugin));
+/// session.spawn(&mut app.world);
+/// app.world.send_event(GameCommand::EndPhase);
+/// app.update();
+/// assert_eq!(
+///     app.world.resource::<gdan::game::turn::TurnInfo>().phase,
+///     gdan::game::entities::GamePhase::Shooting
+/// );
+/// ```
+pub struct RulesPlugin;
+
+impl Plugin for RulesPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<crate::game::entities::GamePhase>()
+            .init_resource::<crate::game::turn::TurnInfo>()
+            .init_resource::<crate::rule::resources::GameRng>()
+            .init_resource::<crate::rule::resources::CombatLog>()
+            .init_resource::<crate::rule::resources::FogOfWar>()
+            .add_event::<crate::rule::commands::GameCommand>()
+            .add_systems(
+                Update,
+                (
+                    crate::rule::systems::apply_game_commands
+                        .run_if(not(resource_exists::<crate::rule::resources::RemoteRules>)),
+                    crate::rule::systems::update_fog_of_war
+                        .run_if(not(resource_exists::<crate::rule::resources::RemoteRules>)),
+                    crate::game::systems::sync_game_phase
+                        .run_if(resource_changed::<crate::game::turn::TurnInfo>),
+                )
+                    .chain(),
+            );
+    }
+}
@@ -0,0 +1,96 @@
+/// The operator picked on the map and where it can move to.
+#[derive(bevy::ecs::system::Resource, Default)]
+pub struct OperSelection {
+    pub entity: Option<bevy::ecs::entity::Entity>,
+    pub reachable: Option<crate::rule::movement::Reachable>,
+}
+
+/// Hex of the unit a line of sight is being dragged from, while the left button is held.
+#[derive(bevy::ecs::system::Resource, Default)]
+pub struct LosDrag(pub Option<crate::map::hex::Hex>);
+
+/*
+ * Dice for combat. Every roll comes from this generator, so a game started from the same seed
+ * with the same orders plays out the same way.
+ */
+#[derive(bevy::ecs::system::Resource, Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct GameRng {
+    pub seed: u64,
+    pub rng: rand_chacha::ChaCha8Rng,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng {
+            seed,
+            rng: rand::SeedableRng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        GameRng::new(0)
+    }
+}
+
+/// Every attack of the game so far, oldest first.
+#[derive(bevy::ecs::system::Resource, Clone, Debug, Default)]
+pub struct CombatLog(pub Vec<crate::rule::combat::CombatLogEntry>);
+
+/*
+ * The rule data of the scenario being played, copied out of its assets:
+ * the rules run on it without an asset server, in the windowed game as in a headless one.
+ */
+#[derive(bevy::ecs::system::Resource, Clone, Debug)]
+pub struct RuleBook {
+    pub terrain: crate::map::terrain::TerrainMap,
+    pub layout: crate::map::hex::HexLayout,
+    pub combat_table: crate::rule::combat::CombatTable,
+    pub objectives: Vec<crate::game::scenario::Objective>,
+}
+
+/*
+ * Present while another machine runs the rules, see `crate::net`: commands given here are sent
+ * to it instead of being carried out, and the state it sends back is shown, what the side has
+ * spotted included.
+ */
+#[derive(bevy::ecs::system::Resource, Default)]
+pub struct RemoteRules;
+
+/// What each side knows of the enemy, and which side the map is shown for.
+#[derive(bevy::ecs::system::Resource, Clone, Debug, Default)]
+pub struct FogOfWar {
+    pub view: crate::rule::fog::FogView,
+    pub intel: bevy::utils::HashMap<crate::oper::components::Side, crate::rule::fog::Intel>,
+}
+
+impl FogOfWar {
+    /// Intel of the side the map is shown for, `None` when every unit is shown.
+    pub fn shown(
+        &self,
+        side_to_play: crate::oper::components::Side,
+    ) -> Option<(crate::oper::components::Side, crate::rule::fog::Intel)> {
+        let viewer = self.view.viewer(side_to_play)?;
+        Some((viewer, self.intel.get(&viewer).cloned().unwrap_or_default()))
+    }
+
+    /// Whether `side` has the enemy unit `id` in sight.
+    pub fn has_spotted(&self, side: crate::oper::components::Side, id: u32) -> bool {
+        self.intel
+            .get(&side)
+            .is_some_and(|intel| intel.is_spotted(id))
+    }
+
+    /// Whether `oper` is shown on the map: units of the viewing side and the enemies it spotted.
+    pub fn is_shown(
+        &self,
+        side_to_play: crate::oper::components::Side,
+        oper: &crate::oper::components::Oper,
+    ) -> bool {
+        match self.view.viewer(side_to_play) {
+            None => true,
+            Some(viewer) => oper.side == viewer || self.has_spotted(viewer, oper.id),
+        }
+    }
+}
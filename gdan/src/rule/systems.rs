@@ -69,6 +69,613 @@ pub fn draw_cursor(
     gizmos.circle_2d(point, 10., Color::WHITE);
 }
 
+/// Occupied hexes and the side holding them, destroyed operators and `except` left out.
+pub fn occupied_hexes<'a>(
+    opers: impl Iterator<
+        Item = (
+            Entity,
+            &'a crate::oper::components::Oper,
+            &'a crate::map::components::HexPosition,
+        ),
+    >,
+    except: Option<Entity>,
+) -> bevy::utils::HashMap<crate::map::hex::Hex, crate::oper::components::Side> {
+    opers
+        .filter(|(entity, oper, _)| Some(*entity) != except && !oper.is_destroyed())
+        .map(|(_, oper, hex_position)| (hex_position.0, oper.side))
+        .collect()
+}
+
+/// Sends a `Select` command when `oper` is not the unit already selected.
+fn send_select(
+    game_commands: &mut EventWriter<crate::rule::commands::GameCommand>,
+    selected: Option<u32>,
+    oper: Option<u32>,
+) {
+    if oper != selected {
+        game_commands.send(crate::rule::commands::GameCommand::Select { oper });
+    }
+}
+
+/*
+ * Left click on a unit of the side to play selects it and shows where it can go;
+ * left click on one of those hexes orders it there along the cheapest path.
+ * Right click, or a click anywhere else, drops the selection.
+ */
+pub fn select_and_move_oper(
+    mut hex_clicked: EventReader<crate::map::events::HexClicked>,
+    mut game_commands: EventWriter<crate::rule::commands::GameCommand>,
+    turn_info: Res<crate::game::turn::TurnInfo>,
+    selection: Res<crate::rule::resources::OperSelection>,
+    query_oper: Query<(
+        Entity,
+        &crate::oper::components::Oper,
+        &crate::map::components::HexPosition,
+    )>,
+) {
+    for event in hex_clicked.read() {
+        let selected = selection
+            .entity
+            .and_then(|entity| query_oper.get(entity).ok())
+            .map(|(_, oper, _)| oper.id);
+        if event.button != MouseButton::Left {
+            send_select(&mut game_commands, selected, None);
+            continue;
+        }
+
+        // a destination of the selected unit: move it
+        if let (Some(Ok((_, oper, _))), Some(reachable)) = (
+            selection.entity.map(|entity| query_oper.get(entity)),
+            selection.reachable.as_ref(),
+        ) {
+            if reachable.contains(event.hex) && event.hex != reachable.start {
+                game_commands.send(crate::rule::commands::GameCommand::Move {
+                    oper: oper.id,
+                    to: event.hex,
+                });
+                continue;
+            }
+        }
+
+        // otherwise select the unit in the hex, if it belongs to the side to play
+        let clicked = query_oper.iter().find(|(_, oper, hex_position)| {
+            hex_position.0 == event.hex && oper.side == turn_info.side && !oper.is_destroyed()
+        });
+        send_select(
+            &mut game_commands,
+            selected,
+            clicked.map(|(_, oper, _)| oper.id),
+        );
+    }
+}
+
+/// Carries out the `Select` commands on the selection shown on the map.
+pub fn apply_selection_commands(
+    mut game_commands: EventReader<crate::rule::commands::GameCommand>,
+    mut selection: ResMut<crate::rule::resources::OperSelection>,
+    query_oper: Query<(Entity, &crate::oper::components::Oper)>,
+) {
+    for command in game_commands.read() {
+        let crate::rule::commands::GameCommand::Select { oper } = *command else {
+            continue;
+        };
+        *selection = crate::rule::resources::OperSelection::default();
+        let Some((entity, oper)) = oper.and_then(|id| {
+            query_oper
+                .iter()
+                .find(|(_, oper)| oper.id == id && !oper.is_destroyed())
+        }) else {
+            continue;
+        };
+        info!(
+            "{} {} selected, {} movement points left",
+            oper.name, oper.id, oper.movement_left
+        );
+        selection.entity = Some(entity);
+    }
+}
+
+/*
+ * Works out where the selected unit can go, again whenever it moves or the fog lifts.
+ * Only the units shown on the map stand in the way: holes left by enemies out of sight would
+ * give them away. A move into one of those is refused when it is carried out.
+ */
+pub fn update_selection_reachable(
+    rules: Option<Res<crate::rule::resources::RuleBook>>,
+    turn_info: Res<crate::game::turn::TurnInfo>,
+    fog: Res<crate::rule::resources::FogOfWar>,
+    mut selection: ResMut<crate::rule::resources::OperSelection>,
+    query_oper: Query<(
+        Entity,
+        Ref<crate::oper::components::Oper>,
+        Ref<crate::map::components::HexPosition>,
+    )>,
+) {
+    let (Some(rules), Some(entity)) = (rules, selection.entity) else {
+        return;
+    };
+    let Ok((_, oper, hex_position)) = query_oper.get(entity) else {
+        *selection = crate::rule::resources::OperSelection::default();
+        return;
+    };
+    if selection.reachable.is_some()
+        && !oper.is_changed()
+        && !hex_position.is_changed()
+        && !fog.is_changed()
+    {
+        return;
+    }
+    let occupied = occupied_hexes(
+        query_oper
+            .iter()
+            .map(|(entity, oper, hex_position)| {
+                (entity, oper.into_inner(), hex_position.into_inner())
+            })
+            .filter(|(_, oper, _)| fog.is_shown(turn_info.side, oper)),
+        Some(entity),
+    );
+    selection.reachable = Some(crate::rule::movement::oper_reachable(
+        &oper,
+        hex_position.0,
+        &rules.terrain,
+        &rules.layout,
+        &occupied,
+    ));
+}
+
+pub fn clear_oper_selection(mut selection: ResMut<crate::rule::resources::OperSelection>) {
+    info!("clear_oper_selection");
+    *selection = crate::rule::resources::OperSelection::default();
+}
+
+/*
+ * In the shooting and close combat phases, left click on a unit of the side to play selects it,
+ * left click on an enemy unit then orders the attack: with the best weapon in range when
+ * shooting, hand to hand against an adjacent unit in close combat. Each unit attacks once per phase,
+ * and only enemy units its side has spotted can be picked.
+ */
+pub fn select_and_fire(
+    mut hex_clicked: EventReader<crate::map::events::HexClicked>,
+    mut game_commands: EventWriter<crate::rule::commands::GameCommand>,
+    turn_info: Res<crate::game::turn::TurnInfo>,
+    selection: Res<crate::rule::resources::OperSelection>,
+    fog: Res<crate::rule::resources::FogOfWar>,
+    query_oper: Query<(
+        Entity,
+        &crate::oper::components::Oper,
+        &crate::map::components::HexPosition,
+        Has<crate::rule::components::Fired>,
+    )>,
+) {
+    for event in hex_clicked.read() {
+        let selected = selection
+            .entity
+            .and_then(|entity| query_oper.get(entity).ok())
+            .map(|(_, oper, _, _)| oper.id);
+        if event.button != MouseButton::Left {
+            send_select(&mut game_commands, selected, None);
+            continue;
+        }
+        let clicked = query_oper.iter().find(|(_, oper, hex_position, _)| {
+            hex_position.0 == event.hex
+                && !oper.is_destroyed()
+                && (oper.side == turn_info.side || fog.has_spotted(turn_info.side, oper.id))
+        });
+        let Some((_, defender, _, fired)) = clicked else {
+            send_select(&mut game_commands, selected, None);
+            continue;
+        };
+
+        // a unit of the side to play: select it
+        if defender.side == turn_info.side {
+            if fired {
+                info!("{} {} has already attacked", defender.name, defender.id);
+                send_select(&mut game_commands, selected, None);
+            } else {
+                send_select(&mut game_commands, selected, Some(defender.id));
+            }
+            continue;
+        }
+
+        // an enemy unit: the selected unit attacks it
+        if let Some(attacker) = selected {
+            game_commands.send(crate::rule::commands::GameCommand::Attack {
+                oper: attacker,
+                target: defender.id,
+            });
+        }
+        send_select(&mut game_commands, selected, None);
+    }
+}
+
+/*
+ * Carries out the commands of this frame, in order, whether they come from the mouse,
+ * the keyboard or elsewhere. Refused commands are logged and change nothing.
+ * The units are copied out of their components and played through
+ * `crate::rule::commands::execute`, as a headless session plays them, then copied back,
+ * see `crate::game::session::units_from_world`.
+ */
+#[allow(clippy::too_many_arguments)]
+pub fn apply_game_commands(
+    mut commands: Commands,
+    mut game_commands: EventReader<crate::rule::commands::GameCommand>,
+    rules: Option<Res<crate::rule::resources::RuleBook>>,
+    mut turn_info: ResMut<crate::game::turn::TurnInfo>,
+    mut rng: ResMut<crate::rule::resources::GameRng>,
+    mut combat_log: ResMut<crate::rule::resources::CombatLog>,
+    mut fog: ResMut<crate::rule::resources::FogOfWar>,
+    mut query_oper: Query<crate::game::session::UnitComponentsMut>,
+) {
+    let Some(rules) = rules else {
+        game_commands.clear();
+        return;
+    };
+    if game_commands.is_empty() {
+        return;
+    }
+    let (entities, mut units) = crate::game::session::units_from_world(query_oper.iter());
+    // worked on copies, so that resources only show as changed when they did change
+    let mut turn = turn_info.clone();
+    let mut intel = fog.intel.clone();
+    for command in game_commands.read() {
+        let executed = crate::rule::commands::execute(
+            &rules,
+            &mut turn,
+            &mut rng.rng,
+            &mut units,
+            &mut intel,
+            command,
+        );
+        match (command, executed) {
+            (
+                crate::rule::commands::GameCommand::Move { oper, .. },
+                Ok(crate::rule::commands::Executed::Moved(path)),
+            ) => {
+                let Some(index) = units.iter().position(|unit| unit.oper.id == *oper) else {
+                    continue;
+                };
+                info!(
+                    "{} {} moves {} hexes",
+                    units[index].oper.name,
+                    oper,
+                    path.len() - 1
+                );
+                commands
+                    .entity(entities[index])
+                    .insert(crate::rule::components::MovePath { path, progress: 0. });
+            }
+            (_, Ok(crate::rule::commands::Executed::Attacked(entry))) => {
+                info!("{}", entry);
+                combat_log.0.push(entry);
+            }
+            (_, Ok(crate::rule::commands::Executed::PhaseEnded)) => {
+                info!("turn {} {:?} {:?}", turn.turn, turn.side, turn.phase);
+            }
+            (_, Ok(_)) => {}
+            (_, Err(error)) => info!("{:?}: {}", command, error),
+        }
+    }
+    if turn != *turn_info {
+        *turn_info = turn;
+    }
+    if intel != fog.intel {
+        fog.intel = intel;
+    }
+    crate::game::session::write_units_back(
+        &mut commands,
+        &mut query_oper,
+        entities.into_iter().zip(units),
+    );
+}
+
+/*
+ * Looks again at what each side has spotted whenever a unit moves, is hit or comes onto the map.
+ */
+pub fn update_fog_of_war(
+    rules: Option<Res<crate::rule::resources::RuleBook>>,
+    mut fog: ResMut<crate::rule::resources::FogOfWar>,
+    query_oper: Query<(
+        Ref<crate::oper::components::Oper>,
+        Ref<crate::map::components::HexPosition>,
+    )>,
+) {
+    let Some(rules) = rules else {
+        return;
+    };
+    if !query_oper
+        .iter()
+        .any(|(oper, hex_position)| oper.is_changed() || hex_position.is_changed())
+    {
+        return;
+    }
+    let units: Vec<(&crate::oper::components::Oper, crate::map::hex::Hex)> = query_oper
+        .iter()
+        .map(|(oper, hex_position)| (oper.into_inner(), hex_position.0))
+        .collect();
+    crate::rule::fog::update_intel(&mut fog.intel, &rules.terrain, &units);
+}
+
+/*
+ * 'V' changes the side the map is shown for: the side to play, red, blue, or every unit.
+ * Hot-seat games always show the side to play, games against the computer and network games
+ * the side played on this machine.
+ */
+pub fn cycle_fog_view(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    setup: Res<crate::game::resources::GameSetup>,
+    mut fog: ResMut<crate::rule::resources::FogOfWar>,
+) {
+    if setup.mode != crate::game::resources::GameMode::Solo {
+        return;
+    }
+    if keyboard.just_pressed(KeyCode::KeyV) {
+        fog.view = fog.view.next();
+        info!("fog of war view {:?}", fog.view);
+    }
+}
+
+/// Circles the spotted enemy units the selected unit can attack in the current phase.
+#[allow(clippy::too_many_arguments)]
+pub fn draw_fire_targets(
+    mut gizmos: Gizmos,
+    phase: Res<State<crate::game::entities::GamePhase>>,
+    selection: Res<crate::rule::resources::OperSelection>,
+    fog: Res<crate::rule::resources::FogOfWar>,
+    hex_grid: Res<crate::map::resources::HexGrid>,
+    map_terrain: Res<crate::map::resources::MapTerrain>,
+    terrains: Res<Assets<crate::map::terrain::TerrainMap>>,
+    query_oper: Query<(
+        &crate::oper::components::Oper,
+        &crate::map::components::HexPosition,
+    )>,
+) {
+    let Some(Ok((attacker, attacker_hex))) = selection.entity.map(|e| query_oper.get(e)) else {
+        return;
+    };
+    let default_terrain = crate::map::terrain::TerrainMap::default();
+    let terrain = terrains.get(&map_terrain.0).unwrap_or(&default_terrain);
+    let shooting = *phase.get() == crate::game::entities::GamePhase::Shooting;
+    let from = attacker_hex.0;
+    gizmos.circle_2d(
+        hex_grid.0.hex_to_world(from),
+        hex_grid.0.size * 0.8,
+        Color::YELLOW,
+    );
+    for (defender, defender_hex) in query_oper.iter() {
+        if defender.side == attacker.side || !fog.has_spotted(attacker.side, defender.id) {
+            continue;
+        }
+        let distance = from.distance(defender_hex.0);
+        let weapon = crate::rule::combat::best_weapon(attacker, defender, distance);
+        if shooting && weapon.is_none() {
+            continue;
+        }
+        let input = crate::rule::combat::CombatInput {
+            attacker,
+            defender,
+            weapon: if shooting { weapon } else { None },
+            distance,
+            attacker_terrain: terrain.get(from),
+            defender_terrain: terrain.get(defender_hex.0),
+            defender_hexside: terrain.hexsides.entering(from, defender_hex.0),
+            // only worked out for targets in range, line of sight is the costly check
+            visible: !shooting
+                || crate::rule::los::line_of_sight(terrain, from, defender_hex.0).visible,
+        };
+        if crate::rule::combat::check_attack(&input).is_ok() {
+            gizmos.circle_2d(
+                hex_grid.0.hex_to_world(defender_hex.0),
+                hex_grid.0.size * 0.8,
+                Color::RED,
+            );
+        }
+    }
+}
+
+pub fn combat_log_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+    info!("combat_log_hud");
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                font_size: 16.,
+                color: Color::WHITE,
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(10.),
+            bottom: Val::Px(70.),
+            max_width: Val::Percent(60.),
+            ..default()
+        }),
+        crate::rule::entities::CombatLogText,
+        crate::map::entities::MapMenu,
+    ));
+}
+
+/// Shows the latest entries of the combat log, newest last.
+pub fn update_combat_log_text(
+    combat_log: Res<crate::rule::resources::CombatLog>,
+    mut query_text: Query<&mut Text, With<crate::rule::entities::CombatLogText>>,
+) {
+    let lines = 5;
+    let start = combat_log.0.len().saturating_sub(lines);
+    let label = combat_log.0[start..]
+        .iter()
+        .map(|entry| entry.to_string())
+        .collect::<Vec<_>>()
+        .join("\n");
+    for mut text in query_text.iter_mut() {
+        text.sections[0].value = label.clone();
+    }
+}
+
+pub fn shade_reachable_hexes(
+    mut commands: Commands,
+    selection: Res<crate::rule::resources::OperSelection>,
+    hex_grid: Res<crate::map::resources::HexGrid>,
+    query_shade: Query<Entity, With<crate::rule::entities::ReachableHex>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut color_materials: ResMut<Assets<ColorMaterial>>,
+    mut shade: Local<Option<(bevy::sprite::Mesh2dHandle, Handle<ColorMaterial>)>>,
+) {
+    if !selection.is_changed() {
+        return;
+    }
+    for entity in query_shade.iter() {
+        commands.entity(entity).despawn();
+    }
+    let Some(reachable) = selection.reachable.as_ref() else {
+        return;
+    };
+    let (mesh, material) = shade
+        .get_or_insert_with(|| {
+            (
+                bevy::sprite::Mesh2dHandle(
+                    meshes.add(RegularPolygon::new(hex_grid.0.size * 0.95, 6)),
+                ),
+                color_materials.add(Color::rgba(0.2, 0.8, 0.3, 0.35)),
+            )
+        })
+        .clone();
+    for hex in reachable.destinations() {
+        let center = hex_grid.0.hex_to_world(hex);
+        commands.spawn((
+            bevy::sprite::MaterialMesh2dBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                // the polygon mesh is pointy-topped, turn it to match the flat-topped grid
+                transform: Transform::from_xyz(center.x, center.y, 0.5)
+                    .with_rotation(Quat::from_rotation_z(std::f32::consts::FRAC_PI_6)),
+                ..default()
+            },
+            crate::rule::entities::ReachableHex,
+            crate::map::entities::MapMenu,
+        ));
+    }
+}
+
+/// Previews the path the selected unit would take to the hovered hex.
+pub fn draw_move_preview(
+    mut gizmos: Gizmos,
+    selection: Res<crate::rule::resources::OperSelection>,
+    hovered_hex: Res<crate::map::resources::HoveredHex>,
+    hex_grid: Res<crate::map::resources::HexGrid>,
+) {
+    let (Some(reachable), Some(hex)) = (selection.reachable.as_ref(), hovered_hex.0) else {
+        return;
+    };
+    let Some(path) = reachable.path_to(hex) else {
+        return;
+    };
+    gizmos.linestrip_2d(
+        path.iter().map(|hex| hex_grid.0.hex_to_world(*hex)),
+        Color::LIME_GREEN,
+    );
+}
+
+pub fn animate_move_path(
+    mut commands: Commands,
+    time: Res<Time>,
+    hex_grid: Res<crate::map::resources::HexGrid>,
+    mut query_path: Query<(
+        Entity,
+        &mut Transform,
+        &mut crate::rule::components::MovePath,
+    )>,
+) {
+    // hexes per second
+    let speed = 4.;
+    for (entity, mut transform, mut move_path) in query_path.iter_mut() {
+        move_path.progress += time.delta_seconds() * speed;
+        let last = move_path.path.len() - 1;
+        let position = if move_path.progress >= last as f32 {
+            commands
+                .entity(entity)
+                .remove::<crate::rule::components::MovePath>();
+            hex_grid.0.hex_to_world(move_path.path[last])
+        } else {
+            let step = move_path.progress.floor() as usize;
+            hex_grid.0.hex_to_world(move_path.path[step]).lerp(
+                hex_grid.0.hex_to_world(move_path.path[step + 1]),
+                move_path.progress.fract(),
+            )
+        };
+        transform.translation.x = position.x;
+        transform.translation.y = position.y;
+    }
+}
+
+/// Pressing the left button on a unit starts a line of sight drag, releasing it ends the drag.
+pub fn los_drag(
+    buttons: Res<ButtonInput<MouseButton>>,
+    hovered_hex: Res<crate::map::resources::HoveredHex>,
+    query_oper: Query<(
+        &crate::oper::components::Oper,
+        &crate::map::components::HexPosition,
+    )>,
+    mut los_drag: ResMut<crate::rule::resources::LosDrag>,
+) {
+    if buttons.just_pressed(MouseButton::Left) {
+        los_drag.0 = hovered_hex.0.filter(|hex| {
+            query_oper
+                .iter()
+                .any(|(oper, hex_position)| hex_position.0 == *hex && !oper.is_destroyed())
+        });
+    }
+    if buttons.just_released(MouseButton::Left) {
+        los_drag.0 = None;
+    }
+}
+
+/*
+ * While dragging from a unit, the sight line to the hovered hex is drawn:
+ * green when the target can be seen, otherwise green up to the blocking hex and red beyond it,
+ * with the blocking wall in red when a wall blocks it.
+ */
+pub fn draw_line_of_sight(
+    mut gizmos: Gizmos,
+    los_drag: Res<crate::rule::resources::LosDrag>,
+    hovered_hex: Res<crate::map::resources::HoveredHex>,
+    hex_grid: Res<crate::map::resources::HexGrid>,
+    map_terrain: Res<crate::map::resources::MapTerrain>,
+    terrains: Res<Assets<crate::map::terrain::TerrainMap>>,
+) {
+    let (Some(from), Some(to)) = (los_drag.0, hovered_hex.0) else {
+        return;
+    };
+    if from == to {
+        return;
+    }
+    let default_terrain = crate::map::terrain::TerrainMap::default();
+    let terrain = terrains.get(&map_terrain.0).unwrap_or(&default_terrain);
+    let los = crate::rule::los::line_of_sight(terrain, from, to);
+    let start = hex_grid.0.hex_to_world(from);
+    let end = hex_grid.0.hex_to_world(to);
+    match los.blocking {
+        None => {
+            gizmos.line_2d(start, end, Color::GREEN);
+        }
+        Some(blocking) => {
+            let blocked_at = hex_grid.0.hex_to_world(blocking);
+            // point of the sight line level with the blocking hex centre
+            let hit = start + (end - start).normalize() * start.distance(blocked_at);
+            gizmos.line_2d(start, hit, Color::GREEN);
+            gizmos.line_2d(hit, end, Color::RED);
+            gizmos.circle_2d(blocked_at, hex_grid.0.size * 0.5, Color::RED);
+        }
+    }
+    if let Some(wall) = los.blocking_wall {
+        let [from, to] = hex_grid.0.edge(wall.hex, wall.direction);
+        gizmos.line_2d(from, to, Color::RED);
+    }
+    for hex in los.hexes.iter() {
+        gizmos.circle_2d(hex_grid.0.hex_to_world(*hex), 4., Color::WHITE);
+    }
+}
+
 pub fn despawn_rule_menu(
     query_enemy: Query<Entity, With<crate::rule::entities::RuleMenu>>,
     mut commands: Commands,
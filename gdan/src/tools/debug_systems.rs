@@ -0,0 +1,83 @@
+use bevy::prelude::*;
+
+/// The debug overlay of `gdan --debug`: frame rate and the state of the app, over every screen.
+pub struct DebugOverlayPlugin {
+    pub enabled: bool,
+}
+
+impl Plugin for DebugOverlayPlugin {
+    fn build(&self, app: &mut App) {
+        if !self.enabled {
+            return;
+        }
+        app.add_plugins(bevy::diagnostic::FrameTimeDiagnosticsPlugin)
+            .add_systems(Startup, debug_overlay)
+            .add_systems(Update, update_debug_overlay);
+    }
+}
+
+/// Text of the debug overlay, shown over every screen with `gdan --debug`.
+#[derive(Component)]
+pub struct DebugOverlayText;
+
+pub fn debug_overlay(mut commands: Commands, asset_server: Res<AssetServer>) {
+    info!("debug_overlay");
+    commands.spawn((
+        TextBundle {
+            // above the hot-seat hand-off screen
+            z_index: ZIndex::Global(200),
+            ..TextBundle::from_section(
+                "",
+                TextStyle {
+                    font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                    font_size: 16.,
+                    color: Color::YELLOW,
+                },
+            )
+            .with_style(Style {
+                position_type: PositionType::Absolute,
+                right: Val::Px(10.),
+                top: Val::Px(10.),
+                ..default()
+            })
+        },
+        DebugOverlayText,
+    ));
+}
+
+/*
+ * Frame rate, screen, hex under the cursor and the turn being played, e.g.
+ *
+ *   60 fps
+ *   MapMenu
+ *   hex (14, 15)
+ *   turn 2 Blue Shooting
+ */
+pub fn update_debug_overlay(
+    diagnostics: Res<bevy::diagnostic::DiagnosticsStore>,
+    state: Res<State<crate::MyAppState>>,
+    hovered_hex: Res<crate::map::resources::HoveredHex>,
+    turn_info: Res<crate::game::turn::TurnInfo>,
+    mut query_text: Query<&mut Text, With<DebugOverlayText>>,
+) {
+    let fps = diagnostics
+        .get(&bevy::diagnostic::FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|fps| fps.smoothed())
+        .unwrap_or(0.);
+    let mut lines = vec![format!("{:.0} fps", fps), format!("{:?}", state.get())];
+    if let Some(hex) = hovered_hex.0 {
+        lines.push(format!("hex ({}, {})", hex.q, hex.r));
+    }
+    if turn_info.phase != crate::game::entities::GamePhase::Inactive {
+        lines.push(format!(
+            "turn {} {:?} {:?}",
+            turn_info.turn, turn_info.side, turn_info.phase
+        ));
+    }
+    let label = lines.join("\n");
+    for mut text in query_text.iter_mut() {
+        if text.sections[0].value != label {
+            text.sections[0].value = label.clone();
+        }
+    }
+}
@@ -1 +1,3 @@
 pub mod camera3d_systems;
+pub mod debug_systems;
+pub mod ron_file;
@@ -0,0 +1,101 @@
+// RON files: how every file of game data is read, parsed and written
+
+/*
+ * Scenarios, terrain, unit definitions, combat tables, computer player profiles, tile pyramids,
+ * saves and replays are all RON. A type kept in such a file implements `RonFile`, naming what the
+ * file holds for the error messages, and gets the same parsing whether the bytes come from disk or
+ * from the asset server:
+ *
+ *     let save = SaveGame::read("saves/quicksave.save.ron")?;
+ *     save.write("saves/quicksave.save.ron")?;
+ *
+ * Types loaded as assets as they are name their file extensions and register a `RonLoader`.
+ * Files are written pretty-printed, so they stay easy to read and edit by hand.
+ */
+
+#[derive(Debug, thiserror::Error)]
+pub enum RonFileError {
+    #[error("could not read {0}: {1}")]
+    Io(&'static str, #[source] std::io::Error),
+    #[error("could not parse {0}: {1}")]
+    Parse(&'static str, #[source] Box<ron::error::SpannedError>),
+    #[error("could not write {0}: {1}")]
+    Write(&'static str, #[source] ron::Error),
+}
+
+pub trait RonFile: serde::de::DeserializeOwned {
+    /// What the file holds, as the error messages name it.
+    const WHAT: &'static str;
+    /// Extensions of the asset files, for `RonLoader`.
+    const EXTENSIONS: &'static [&'static str] = &[];
+
+    /// Parses the file without going through the asset server.
+    fn from_bytes(bytes: &[u8]) -> Result<Self, RonFileError> {
+        ron::de::from_bytes(bytes).map_err(|error| RonFileError::Parse(Self::WHAT, Box::new(error)))
+    }
+
+    fn read(path: impl AsRef<std::path::Path>) -> Result<Self, RonFileError> {
+        let bytes = std::fs::read(path).map_err(|error| RonFileError::Io(Self::WHAT, error))?;
+        Self::from_bytes(&bytes)
+    }
+
+    fn to_ron(&self) -> Result<String, RonFileError>
+    where
+        Self: serde::Serialize,
+    {
+        ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(|error| RonFileError::Write(Self::WHAT, error))
+    }
+
+    /// Writes the file, creating its folder if needed.
+    fn write(&self, path: impl AsRef<std::path::Path>) -> Result<(), RonFileError>
+    where
+        Self: serde::Serialize,
+    {
+        let path = path.as_ref();
+        let io = |error| RonFileError::Io(Self::WHAT, error);
+        if let Some(folder) = path.parent() {
+            std::fs::create_dir_all(folder).map_err(io)?;
+        }
+        std::fs::write(path, self.to_ron()?).map_err(io)
+    }
+}
+
+/// Reads a whole asset file, for the loader of a `T` to parse.
+pub async fn read_asset<T: RonFile>(
+    reader: &mut bevy::asset::io::Reader<'_>,
+) -> Result<Vec<u8>, RonFileError> {
+    let mut bytes = Vec::new();
+    bevy::asset::AsyncReadExt::read_to_end(reader, &mut bytes)
+        .await
+        .map_err(|error| RonFileError::Io(T::WHAT, error))?;
+    Ok(bytes)
+}
+
+/// Asset loader of a type kept in a RON file as it is.
+pub struct RonLoader<T>(std::marker::PhantomData<fn() -> T>);
+
+impl<T> Default for RonLoader<T> {
+    fn default() -> Self {
+        RonLoader(std::marker::PhantomData)
+    }
+}
+
+impl<T: RonFile + bevy::asset::Asset> bevy::asset::AssetLoader for RonLoader<T> {
+    type Asset = T;
+    type Settings = ();
+    type Error = RonFileError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut bevy::asset::io::Reader,
+        _settings: &'a (),
+        _load_context: &'a mut bevy::asset::LoadContext,
+    ) -> bevy::utils::BoxedFuture<'a, Result<T, RonFileError>> {
+        Box::pin(async move { T::from_bytes(&read_asset::<T>(reader).await?) })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        T::EXTENSIONS
+    }
+}
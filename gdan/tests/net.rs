@@ -0,0 +1,237 @@
+// A host and a client talking over localhost: what the client is sent, and what it may send
+
+use bevy::prelude::*;
+use gdan::game::session::{GameSession, Unit};
+use gdan::net::connection::NetEvent;
+use gdan::net::protocol::{ClientMessage, HostMessage};
+use gdan::net::resources::{NetClient, NetHost};
+use gdan::oper::components::Side;
+use gdan::rule::commands::GameCommand;
+
+const SCENARIO: &str = "scenarios/river-crossing.scenario.ron";
+const TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// A game on the map of a host playing red, with the blue seat free.
+fn host() -> App {
+    let assets = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+    let session = GameSession::load(assets, SCENARIO, 1).unwrap();
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, gdan::rule::plugin::RulesPlugin))
+        .add_systems(
+            Update,
+            (
+                gdan::net::systems::host_receive.before(gdan::rule::systems::apply_game_commands),
+                gdan::net::systems::host_sync_state.after(gdan::rule::systems::update_fog_of_war),
+            ),
+        );
+    session.spawn(&mut app.world);
+    let mut host = NetHost::start("127.0.0.1:0", "host", Side::Red, SCENARIO).unwrap();
+    host.local_seat_mut().unwrap().ready = true;
+    app.insert_resource(host);
+    app.update();
+    app
+}
+
+/// Runs the host until the client has been sent a message `done` is happy with.
+fn run_until(
+    app: &mut App,
+    client: &mut NetClient,
+    received: &mut Vec<HostMessage>,
+    mut done: impl FnMut(&HostMessage) -> bool,
+) {
+    let start = std::time::Instant::now();
+    while start.elapsed() < TIMEOUT {
+        app.update();
+        for event in client.poll() {
+            match event {
+                NetEvent::Connected(_) => client.send(ClientMessage::Hello {
+                    name: "client".to_string(),
+                    token: None,
+                }),
+                NetEvent::Message(_, message) => {
+                    let found = done(&message);
+                    received.push(message);
+                    if found {
+                        return;
+                    }
+                }
+                NetEvent::Disconnected(_) => {}
+            }
+        }
+        std::thread::sleep(std::time::Duration::from_millis(10));
+    }
+    panic!("the host did not send what was waited for");
+}
+
+/// Every red unit sent is in sight of blue as it is, or where and as blue last saw it.
+fn assert_only_seen(world: &mut World, units: &[Unit]) {
+    let intel = world
+        .resource::<gdan::rule::resources::FogOfWar>()
+        .intel
+        .get(&Side::Blue)
+        .cloned()
+        .unwrap_or_default();
+    let live: Vec<gdan::oper::components::Oper> = world
+        .query::<&gdan::oper::components::Oper>()
+        .iter(world)
+        .cloned()
+        .collect();
+    for unit in units.iter().filter(|unit| unit.oper.side == Side::Red) {
+        let id = unit.oper.id;
+        if intel.is_spotted(id) {
+            assert!(live.contains(&unit.oper), "unit {id} sent unlike it is");
+            continue;
+        }
+        assert_eq!(
+            intel.last_known.get(&id),
+            Some(&unit.hex),
+            "unit {id} sent where blue has not seen it"
+        );
+        let seen = &intel.last_seen[&id];
+        assert_eq!(
+            (unit.oper.strength, unit.oper.state, unit.oper.movement_left),
+            (seen.strength, seen.state, seen.movement_left),
+            "unit {id} sent unlike blue last saw it"
+        );
+    }
+}
+
+/// Sends a blue unit to look at a red unit out of sight and back, then hits the red unit.
+fn look_and_hit(app: &mut App, (id, hex): (u32, gdan::map::hex::Hex)) {
+    let mut query = app.world.query::<(
+        &mut gdan::oper::components::Oper,
+        &mut gdan::map::components::HexPosition,
+    )>();
+    let (blue, home) = query
+        .iter(&app.world)
+        .find(|(oper, _)| oper.side == Side::Blue)
+        .map(|(oper, hex_position)| (oper.id, hex_position.0))
+        .unwrap();
+    for to in [hex.neighbors()[0], home] {
+        let (_, mut hex_position) = query
+            .iter_mut(&mut app.world)
+            .find(|(oper, _)| oper.id == blue)
+            .unwrap();
+        hex_position.0 = to;
+        app.update();
+    }
+    let (mut oper, _) = query
+        .iter_mut(&mut app.world)
+        .find(|(oper, _)| oper.id == id)
+        .unwrap();
+    oper.strength = 1;
+    oper.state = gdan::oper::components::OperState::Suppressed;
+    app.update();
+}
+
+fn hidden_red_units(world: &mut World) -> Vec<(u32, gdan::map::hex::Hex)> {
+    let fog = world.resource::<gdan::rule::resources::FogOfWar>().clone();
+    world
+        .query::<(
+            &gdan::oper::components::Oper,
+            &gdan::map::components::HexPosition,
+        )>()
+        .iter(world)
+        .filter(|(oper, _)| oper.side == Side::Red && !fog.has_spotted(Side::Blue, oper.id))
+        .map(|(oper, hex_position)| (oper.id, hex_position.0))
+        .collect()
+}
+
+#[test]
+fn client_only_learns_what_its_side_has_spotted() {
+    let mut app = host();
+    let hidden = hidden_red_units(&mut app.world);
+    assert!(!hidden.is_empty());
+    let ghost = hidden[0].0;
+    look_and_hit(&mut app, hidden[0]);
+    let intel = app
+        .world
+        .resource::<gdan::rule::resources::FogOfWar>()
+        .intel[&Side::Blue]
+        .clone();
+    assert!(intel.ghosts().any(|(id, _)| id == ghost));
+    let port = app.world.resource::<NetHost>().port;
+    let mut client = NetClient::start(&format!("127.0.0.1:{}", port), "client");
+    let mut received = Vec::new();
+    run_until(&mut app, &mut client, &mut received, |message| {
+        matches!(message, HostMessage::Start(_))
+    });
+    assert!(matches!(
+        received[0],
+        HostMessage::Welcome {
+            side: Side::Blue,
+            ..
+        }
+    ));
+    let Some(HostMessage::Start(save)) = received.last() else {
+        unreachable!()
+    };
+    assert_only_seen(&mut app.world, &save.units);
+    assert!(save.units.iter().any(|unit| unit.oper.id == ghost));
+    assert_eq!(save.intel.keys().collect::<Vec<_>>(), vec![&Side::Blue]);
+
+    // red moves where blue cannot see it, and the turn goes on
+    let moved = hidden.iter().find_map(|(id, hex)| {
+        hex.neighbors().into_iter().find_map(|to| {
+            let mut trial = GameSession::load(
+                std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets"),
+                SCENARIO,
+                1,
+            )
+            .unwrap();
+            trial
+                .apply(&GameCommand::Move { oper: *id, to })
+                .ok()
+                .map(|_| (*id, to))
+        })
+    });
+    let (oper, to) = moved.unwrap();
+    app.world.send_event(GameCommand::Move { oper, to });
+    app.world.send_event(GameCommand::EndPhase);
+    received.clear();
+    run_until(
+        &mut app,
+        &mut client,
+        &mut received,
+        |message| matches!(message, HostMessage::State(delta) if delta.turn_info.is_some()),
+    );
+    for message in received.iter() {
+        if let HostMessage::State(delta) = message {
+            assert_only_seen(&mut app.world, &delta.units);
+        }
+    }
+
+    // and blue may not give orders in the turn of red
+    client.send(ClientMessage::Command(GameCommand::Move {
+        oper: 101,
+        to: gdan::map::hex::Hex::new(0, 0),
+    }));
+    received.clear();
+    run_until(&mut app, &mut client, &mut received, |message| {
+        matches!(message, HostMessage::Rejected { .. })
+    });
+}
+
+#[test]
+fn line_without_end_drops_the_connection() {
+    use std::io::{Read, Write};
+    let host = NetHost::start("127.0.0.1:0", "host", Side::Red, SCENARIO).unwrap();
+    let mut stream = std::net::TcpStream::connect(("127.0.0.1", host.port)).unwrap();
+    let chunk = vec![b'x'; 64 << 10];
+    let mut written = 0;
+    while written <= gdan::net::connection::MAX_LINE {
+        if stream.write_all(&chunk).is_err() {
+            break;
+        }
+        written += chunk.len();
+    }
+    stream.set_read_timeout(Some(TIMEOUT)).unwrap();
+    let mut byte = [0; 1];
+    match stream.read(&mut byte) {
+        Ok(read) => assert_eq!(read, 0),
+        Err(error) => assert!(!matches!(
+            error.kind(),
+            std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+        )),
+    }
+}
@@ -0,0 +1,115 @@
+// A recorded game played back both ways, headless and in an ECS world, must end the same
+
+use bevy::prelude::*;
+use gdan::ai::player::{AiPlayer, AiView};
+use gdan::game::replay::Replay;
+use gdan::game::session::GameSession;
+use gdan::oper::components::Side;
+
+const SCENARIO: &str = "scenarios/river-crossing.scenario.ron";
+
+fn start(seed: u64) -> GameSession {
+    let assets = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets");
+    GameSession::load(assets, SCENARIO, seed).unwrap()
+}
+
+/// Plays a whole game with the computer on both sides and keeps every command given.
+fn record(seed: u64) -> Replay {
+    let mut session = start(seed);
+    let mut players = [
+        AiPlayer::new(Side::Red, Default::default()),
+        AiPlayer::new(Side::Blue, Default::default()),
+    ];
+    let mut replay = Replay::new(SCENARIO, seed);
+    while !session.is_over() {
+        let side = session.turn_info.side;
+        let player = players
+            .iter_mut()
+            .find(|player| player.side == side)
+            .unwrap();
+        let command = player
+            .next_command(&AiView::from_session(&session, side))
+            .unwrap();
+        let _ = session.apply(&command);
+        replay.commands.push(command);
+    }
+    replay
+}
+
+/// Plays the replay in an ECS world, `batch` commands a frame.
+fn play_in_world(replay: &Replay, batch: usize) -> World {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, gdan::rule::plugin::RulesPlugin));
+    start(replay.seed).spawn(&mut app.world);
+    app.update();
+    for commands in replay.commands.chunks(batch) {
+        for command in commands {
+            app.world.send_event(command.clone());
+        }
+        app.update();
+    }
+    std::mem::take(&mut app.world)
+}
+
+fn assert_same_game(session: &GameSession, world: &mut World) {
+    let mut units: Vec<(gdan::oper::components::Oper, gdan::map::hex::Hex, bool)> = world
+        .query::<(
+            &gdan::oper::components::Oper,
+            &gdan::map::components::HexPosition,
+            Has<gdan::rule::components::Fired>,
+        )>()
+        .iter(world)
+        .map(|(oper, hex_position, fired)| (oper.clone(), hex_position.0, fired))
+        .collect();
+    units.sort_by_key(|(oper, _, _)| oper.id);
+    let mut expected: Vec<(gdan::oper::components::Oper, gdan::map::hex::Hex, bool)> = session
+        .units
+        .iter()
+        .map(|unit| (unit.oper.clone(), unit.hex, unit.fired))
+        .collect();
+    expected.sort_by_key(|(oper, _, _)| oper.id);
+    assert_eq!(units, expected);
+    assert_eq!(
+        world.resource::<gdan::rule::resources::FogOfWar>().intel,
+        session.intel
+    );
+    assert_eq!(
+        *world.resource::<gdan::game::turn::TurnInfo>(),
+        session.turn_info
+    );
+    assert_eq!(
+        world.resource::<gdan::rule::resources::CombatLog>().0,
+        session.combat_log.0
+    );
+}
+
+#[test]
+fn replay_ends_the_same_headless_and_in_the_world() {
+    let replay = record(5);
+    assert!(!replay.commands.is_empty());
+    let mut session = start(replay.seed);
+    replay.play(&mut session, replay.commands.len());
+    assert!(session.is_over());
+    assert!(!session.combat_log.0.is_empty());
+    assert_same_game(&session, &mut play_in_world(&replay, 1));
+}
+
+#[test]
+fn commands_of_one_frame_end_the_same() {
+    let replay = record(9);
+    let mut session = start(replay.seed);
+    replay.play(&mut session, replay.commands.len());
+    assert_same_game(&session, &mut play_in_world(&replay, 7));
+}
+
+#[test]
+fn seek_halfway_matches_the_game_played_so_far() {
+    let replay = record(3);
+    let half = Replay {
+        commands: replay.commands[..replay.commands.len() / 2].to_vec(),
+        ..replay.clone()
+    };
+    let mut session = start(replay.seed);
+    replay.play(&mut session, half.commands.len());
+    assert_same_game(&session, &mut play_in_world(&half, 1));
+}
@@ -0,0 +1,236 @@
+// Whole games played headless through `GameSession`, and the orders the rules refuse
+
+use gdan::game::entities::GamePhase;
+use gdan::game::session::GameSession;
+use gdan::map::hex::{Hex, OffsetCoord};
+use gdan::oper::components::Side;
+use gdan::rule::commands::{CommandError, GameCommand};
+use gdan::tools::ron_file::RonFile;
+
+const SCENARIO: &str = "scenarios/river-crossing.scenario.ron";
+
+fn assets() -> std::path::PathBuf {
+    std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("assets")
+}
+
+fn session(seed: u64) -> GameSession {
+    GameSession::load(assets(), SCENARIO, seed).unwrap()
+}
+
+fn hex(col: i32, row: i32) -> Hex {
+    Hex::from_offset(OffsetCoord { col, row })
+}
+
+fn play_to_the_end(session: &mut GameSession) {
+    let mut players = vec![
+        gdan::ai::player::AiPlayer::new(Side::Red, Default::default()),
+        gdan::ai::player::AiPlayer::new(Side::Blue, Default::default()),
+    ];
+    gdan::ai::player::play_session(session, &mut players);
+}
+
+#[test]
+fn computer_players_finish_the_game() {
+    let mut game = session(7);
+    play_to_the_end(&mut game);
+    assert!(game.is_over());
+    assert_eq!(game.turn_info.phase, GamePhase::GameOver);
+    assert_eq!(game.turn_info.turn, game.turn_info.turn_limit);
+    assert!(game.apply(&GameCommand::EndPhase).is_err());
+}
+
+#[test]
+fn same_seed_plays_the_same_game() {
+    let (mut first, mut second) = (session(11), session(11));
+    play_to_the_end(&mut first);
+    play_to_the_end(&mut second);
+    assert_eq!(first.units, second.units);
+    assert_eq!(first.combat_log.0, second.combat_log.0);
+    assert_eq!(first.score(), second.score());
+}
+
+#[test]
+fn attack_refused_in_the_movement_phase() {
+    let mut game = session(1);
+    assert_eq!(game.turn_info.phase, GamePhase::Movement);
+    assert_eq!(
+        game.apply(&GameCommand::Attack {
+            oper: 1,
+            target: 101
+        }),
+        Err(CommandError::WrongPhase(GamePhase::Movement))
+    );
+}
+
+#[test]
+fn move_refused_for_the_side_not_playing() {
+    let mut game = session(1);
+    assert_eq!(game.turn_info.side, Side::Red);
+    let before = game.units.clone();
+    assert_eq!(
+        game.apply(&GameCommand::Move {
+            oper: 101,
+            to: hex(21, 15)
+        }),
+        Err(CommandError::NotYourTurn)
+    );
+    assert_eq!(game.units, before);
+}
+
+#[test]
+fn move_refused_out_of_reach() {
+    let mut game = session(1);
+    let before = game.units.clone();
+    assert_eq!(
+        game.apply(&GameCommand::Move {
+            oper: 1,
+            to: hex(30, 2)
+        }),
+        Err(CommandError::Unreachable)
+    );
+    assert_eq!(game.units, before);
+}
+
+#[test]
+fn attack_refused_out_of_range() {
+    let mut game = session(1);
+    game.apply(&GameCommand::EndPhase).unwrap();
+    assert_eq!(game.turn_info.phase, GamePhase::Shooting);
+    // spotted, as by a unit further forward, yet far out of the reach of this one
+    game.intel.get_mut(&Side::Red).unwrap().spotted.insert(102);
+    assert_eq!(
+        game.apply(&GameCommand::Attack {
+            oper: 1,
+            target: 102
+        }),
+        Err(CommandError::NoWeaponInRange)
+    );
+    assert!(game.combat_log.0.is_empty());
+}
+
+#[test]
+fn unknown_units_refused() {
+    let mut game = session(1);
+    assert_eq!(
+        game.apply(&GameCommand::Select { oper: Some(999) }),
+        Err(CommandError::UnknownOper(999))
+    );
+}
+
+#[test]
+fn map_sheet_lays_out_the_scenario_map() {
+    let mut scenario = session(1).scenario;
+    assert_eq!(scenario.map_info().unit_x, 8819.);
+    scenario.map_sheet = Some(gdan::game::scenario::MapSheet {
+        width: 1500,
+        height: 1000,
+        hex_size: 50.,
+        level: 20,
+        label_x: 1,
+        label_y: 1,
+    });
+    let map_info = scenario.map_info();
+    assert_eq!((map_info.unit_x, map_info.unit_y), (1500., 1000.));
+    // a level less covers twice the ground with every pixel
+    let default = gdan::map::resources::MapInfo::default();
+    assert_eq!(map_info.meter_per_pixel, default.meter_per_pixel * 2.);
+    let layout = gdan::map::hex::HexLayout::from_map_info(&map_info);
+    assert_eq!((layout.cols, layout.rows), (20, 12));
+}
+
+#[test]
+fn move_into_an_enemy_out_of_sight_stops_before_it() {
+    let game = session(1);
+    let from = game
+        .units
+        .iter()
+        .find(|unit| unit.oper.id == 1)
+        .unwrap()
+        .hex;
+    // the first hex two steps away the unit can move to on an empty map
+    let to = from
+        .neighbors()
+        .into_iter()
+        .flat_map(|next| next.neighbors())
+        .filter(|to| to.distance(from) == 2)
+        .find(|to| {
+            let mut trial = session(1);
+            trial.apply(&GameCommand::Move { oper: 1, to: *to }).is_ok()
+        })
+        .unwrap();
+    let mut game = game;
+    let enemy = game
+        .units
+        .iter_mut()
+        .find(|unit| unit.oper.id == 101)
+        .unwrap();
+    enemy.hex = to;
+    game.intel.get_mut(&Side::Red).unwrap().spotted.remove(&101);
+    game.apply(&GameCommand::Move { oper: 1, to }).unwrap();
+    let unit = game.units.iter().find(|unit| unit.oper.id == 1).unwrap();
+    assert_eq!(unit.hex.distance(from), 1);
+    assert_eq!(unit.hex.distance(to), 1);
+    assert_eq!(unit.oper.movement_left, 0);
+    assert!(game.intel[&Side::Red].is_spotted(101));
+}
+
+#[test]
+fn attack_on_an_enemy_out_of_sight_refused() {
+    let mut game = session(1);
+    game.apply(&GameCommand::EndPhase).unwrap();
+    game.intel.get_mut(&Side::Red).unwrap().spotted.remove(&101);
+    let before = game.units.clone();
+    assert_eq!(
+        game.apply(&GameCommand::Attack {
+            oper: 1,
+            target: 101
+        }),
+        Err(CommandError::NotSpotted(101))
+    );
+    assert_eq!(game.units, before);
+    assert!(game.combat_log.0.is_empty());
+}
+
+#[test]
+fn saved_game_plays_on_the_same() {
+    let mut game = session(5);
+    let mut players = [
+        gdan::ai::player::AiPlayer::new(Side::Red, Default::default()),
+        gdan::ai::player::AiPlayer::new(Side::Blue, Default::default()),
+    ];
+    // a turn and a half in, with units moved and spotted
+    while game.turn_info.turn < 2 || game.turn_info.side != Side::Blue {
+        let player = players
+            .iter_mut()
+            .find(|player| player.side == game.turn_info.side)
+            .unwrap();
+        let view = gdan::ai::player::AiView::from_session(&game, player.side);
+        let command = player.next_command(&view).unwrap_or(GameCommand::EndPhase);
+        let _ = game.apply(&command);
+    }
+    let mut save = gdan::game::save::SaveGame::from_session(SCENARIO, &game);
+    save.setup = Some(gdan::game::resources::GameSetup {
+        mode: gdan::game::resources::GameMode::Computer,
+        side: Side::Blue,
+        ..Default::default()
+    });
+    save.fog_view = Some(gdan::rule::fog::FogView::Side(Side::Blue));
+    let path = std::env::temp_dir().join(format!("gdan-{}.save.ron", std::process::id()));
+    save.write(&path).unwrap();
+    let read = gdan::game::save::SaveGame::read(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(read.scenario, SCENARIO);
+    assert_eq!(read.setup, save.setup);
+    assert_eq!(read.fog_view, save.fog_view);
+
+    let mut restored = GameSession::restore(read, game.scenario.clone(), game.rules.clone());
+    assert_eq!(restored.turn_info, game.turn_info);
+    assert_eq!(restored.units, game.units);
+    assert_eq!(restored.intel, game.intel);
+    assert_eq!(restored.combat_log.0, game.combat_log.0);
+    // the dice go on rolling the same
+    play_to_the_end(&mut game);
+    play_to_the_end(&mut restored);
+    assert_eq!(restored.units, game.units);
+    assert_eq!(restored.combat_log.0, game.combat_log.0);
+}